@@ -0,0 +1,67 @@
+//! Time `calc_version` on a generated repository.
+//!
+//! ```sh
+//! cargo bench --bench calc_version
+//! ```
+
+use std::{
+    env::temp_dir,
+    time::{Duration, Instant},
+};
+
+use git2::{IndexEntry, IndexTime, Repository};
+use sver::{filemode::FileMode, sver_repository::SverRepository};
+use uuid::Uuid;
+
+const FILES: usize = 20_000;
+const ITERATIONS: u32 = 10;
+
+fn main() {
+    let repo = setup_repository(FILES);
+    let work_dir = repo.workdir().unwrap().to_str().unwrap().to_string();
+    let sver_repo = SverRepository::new(&work_dir).unwrap();
+
+    let mut elapsed = Duration::ZERO;
+    let mut version = String::new();
+    for _ in 0..ITERATIONS {
+        let start = Instant::now();
+        version = sver_repo.calc_version().unwrap().version;
+        elapsed += start.elapsed();
+    }
+    println!(
+        "calc_version files:{FILES}, iterations:{ITERATIONS}, average:{:?}, version:{version}",
+        elapsed / ITERATIONS
+    );
+    std::fs::remove_dir_all(work_dir).unwrap();
+}
+
+// Only the index is written. The sources are read from the index, not from the working tree.
+fn setup_repository(files: usize) -> Repository {
+    let mut path = temp_dir();
+    path.push(format!("sver-bench-{}", Uuid::now_v7()));
+    let repo = Repository::init(&path).unwrap();
+    {
+        let mut index = repo.index().unwrap();
+        for i in 0..files {
+            let content = format!("file {i}");
+            index
+                .add(&IndexEntry {
+                    ctime: IndexTime::new(0, 0),
+                    mtime: IndexTime::new(0, 0),
+                    dev: 0,
+                    ino: 0,
+                    mode: FileMode::Blob.into(),
+                    uid: 0,
+                    gid: 0,
+                    file_size: content.len() as u32,
+                    id: repo.blob(content.as_bytes()).unwrap(),
+                    flags: 0,
+                    flags_extended: 0,
+                    path: format!("dir{}/file{i}.txt", i % 100).into_bytes(),
+                })
+                .unwrap();
+        }
+        index.write().unwrap();
+    }
+    repo
+}
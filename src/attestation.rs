@@ -0,0 +1,110 @@
+use std::{collections::BTreeMap, fmt::Display, path::Path};
+
+use git2::{Index, Repository};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    sver_config::{config_content_str, CalculationTarget},
+    SverError,
+};
+
+/// File name of expected versions committed at the repository root.
+pub const ATTESTATION_FILE_NAME: &str = "sver-versions.toml";
+
+/// Minimum digits of an expected version, same as the minimum of `--length`.
+/// A shorter prefix matches too many versions to verify anything.
+pub const MIN_EXPECTED_LENGTH: usize = 4;
+
+/// Expected versions of targets.
+///
+/// ```toml
+/// [versions]
+/// "service1" = "3f1bec06015e"
+/// "service2:prof1" = "fd0053eab4b8"
+/// ```
+#[derive(Serialize, Deserialize, Default, Debug)]
+pub(crate) struct Attestations {
+    #[serde(default)]
+    versions: BTreeMap<String, String>,
+}
+
+impl Attestations {
+    // Load from the index like config files. `None` if the file is not committed.
+    pub(crate) fn load(repo: &Repository, index: &Index) -> Result<Option<Self>, SverError> {
+        let Some(entry) = index.get_path(Path::new(ATTESTATION_FILE_NAME), 0) else {
+            return Ok(None);
+        };
+        let content = config_content_str(repo.find_blob(entry.id)?.content())?;
+        Ok(Some(toml::from_str(&content)?))
+    }
+
+    // Keys are `path:profile` and the profile can be omitted like dependencies.
+    pub(crate) fn get(&self, target: &CalculationTarget) -> Option<&str> {
+        self.versions
+            .iter()
+            .find(|(key, _)| {
+                let key = CalculationTarget::parse_from_setting(key);
+                let key_path = if key.path == "." { "" } else { &key.path };
+                key_path == target.path && key.profile == target.profile
+            })
+            .map(|(_, version)| version.as_str())
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub enum Verification {
+    /// The version starts with the expected version.
+    Verified {
+        version: String,
+    },
+    Mismatched {
+        expected: String,
+        actual: String,
+    },
+    /// No expected version is given and not found in the attestation file.
+    MissingAttestation,
+    /// The expected version is shorter than [`MIN_EXPECTED_LENGTH`].
+    TooShort {
+        expected: String,
+    },
+}
+
+impl Verification {
+    pub(crate) fn compare(expected: &str, actual: String) -> Self {
+        if expected.len() < MIN_EXPECTED_LENGTH {
+            Verification::TooShort {
+                expected: expected.to_string(),
+            }
+        } else if actual.starts_with(expected) {
+            Verification::Verified { version: actual }
+        } else {
+            Verification::Mismatched {
+                expected: expected.to_string(),
+                actual,
+            }
+        }
+    }
+
+    pub fn is_verified(&self) -> bool {
+        matches!(self, Verification::Verified { .. })
+    }
+}
+
+impl Display for Verification {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Verification::Verified { version } => writeln!(f, "[OK]\t{version}"),
+            Verification::Mismatched { expected, actual } => {
+                writeln!(f, "[Fail]\texpected:{expected}, actual:{actual}")
+            }
+            Verification::MissingAttestation => writeln!(
+                f,
+                "[Fail]\texpected version is not found in {ATTESTATION_FILE_NAME}"
+            ),
+            Verification::TooShort { expected } => writeln!(
+                f,
+                "[Fail]\texpected version must have at least {MIN_EXPECTED_LENGTH} digits. expected:{expected}"
+            ),
+        }
+    }
+}
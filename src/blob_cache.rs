@@ -0,0 +1,94 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, LazyLock, Mutex, PoisonError},
+};
+
+use git2::{Oid, Repository};
+
+// Config files and symbolic links are read again by every target that reaches them, and each
+// target opens its own repository. Blobs are addressed by their content, so the cache is shared
+// by all repositories in the process.
+static CACHE: LazyLock<Mutex<HashMap<Oid, Arc<[u8]>>>> =
+    LazyLock::new(|| Mutex::new(HashMap::new()));
+
+// Larger blobs are not cached, since they are sources rather than configs or links.
+const MAX_CACHED_BLOB_SIZE: usize = 64 * 1024;
+// Bounds the memory of the cache for repositories with a huge number of configs or links.
+const MAX_CACHED_BLOBS: usize = 16 * 1024;
+
+/// Content of the blob `oid`, read from the object database only at the first time.
+pub(crate) fn blob_content(repo: &Repository, oid: Oid) -> Result<Arc<[u8]>, git2::Error> {
+    // the map is consistent even if a thread panicked while holding the lock
+    if let Some(content) = CACHE
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner)
+        .get(&oid)
+    {
+        return Ok(content.clone());
+    }
+    let content: Arc<[u8]> = Arc::from(repo.find_blob(oid)?.content());
+    if content.len() <= MAX_CACHED_BLOB_SIZE {
+        let mut cache = CACHE.lock().unwrap_or_else(PoisonError::into_inner);
+        if cache.len() < MAX_CACHED_BLOBS {
+            cache.insert(oid, content.clone());
+        }
+    }
+    Ok(content)
+}
+
+#[cfg(test)]
+mod blob_cache_tests {
+    use std::sync::Arc;
+
+    use git2::{Odb, Repository};
+
+    use super::{blob_content, CACHE, MAX_CACHED_BLOB_SIZE};
+
+    // A repository whose objects are only in memory, so nothing is left in the temp dir.
+    fn setup_repository() -> Repository {
+        let odb = Odb::new().unwrap();
+        odb.add_new_mempack_backend(1).unwrap();
+        Repository::from_odb(odb).unwrap()
+    }
+
+    #[test]
+    fn cached_blob_test() {
+        let repo = setup_repository();
+        let oid = repo
+            .blob(b"[default]\ndependencies = [\"lib1\"]\n")
+            .unwrap();
+        let large_oid = repo.blob(&vec![b'a'; MAX_CACHED_BLOB_SIZE + 1]).unwrap();
+
+        let first = blob_content(&repo, oid).unwrap();
+        let second = blob_content(&repo, oid).unwrap();
+        assert_eq!(&*first, b"[default]\ndependencies = [\"lib1\"]\n");
+        // served from memory
+        assert!(Arc::ptr_eq(&first, &second));
+
+        // the content is shared by other repositories
+        let other_repo = setup_repository();
+        assert!(Arc::ptr_eq(
+            &first,
+            &blob_content(&other_repo, oid).unwrap()
+        ));
+
+        let first = blob_content(&repo, large_oid).unwrap();
+        let second = blob_content(&repo, large_oid).unwrap();
+        assert_eq!(first, second);
+        assert!(!Arc::ptr_eq(&first, &second));
+    }
+
+    #[test]
+    fn poisoned_cache_test() {
+        let repo = setup_repository();
+        let oid = repo.blob(b"[default]\n").unwrap();
+
+        let _ = std::thread::spawn(|| {
+            let _cache = CACHE.lock().unwrap();
+            panic!("poison the cache");
+        })
+        .join();
+
+        assert_eq!(&*blob_content(&repo, oid).unwrap(), b"[default]\n");
+    }
+}
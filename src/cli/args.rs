@@ -1,8 +1,16 @@
 use clap::{Parser, Subcommand, ValueEnum};
 
+use super::outputs::VersionTemplate;
+
 #[derive(Parser, Debug)]
 #[command(author, version, about = "Version calculator based on source code.", long_about = None)]
 pub(crate) struct Args {
+    /// root directory of the repository. paths are relative from it instead of the current directory
+    #[arg(long, global = true, value_name = "DIR")]
+    pub repo: Option<String>,
+    /// colorize the text output. `auto` colorizes only on a terminal
+    #[arg(long, global = true, default_value = "auto")]
+    pub color: ColorMode,
     #[command(subcommand)]
     pub command: Commands,
 }
@@ -11,39 +19,232 @@ pub(crate) struct Args {
 pub(crate) enum Commands {
     /// calc version
     Calc {
-        /// target paths
+        /// target paths. `-` reads targets from stdin, one per line
         paths: Vec<String>,
+        /// file of target paths, one per line. appended to the paths
+        #[arg(long, value_name = "FILE")]
+        paths_from: Option<String>,
+        /// profile of all targets, overriding `:profile` of the paths
+        #[arg(long)]
+        profile: Option<String>,
 
         /// format of calculation result
         #[arg(short, long, default_value = "version-only")]
         output: OutputFormat,
-        /// length of version
+        /// length of version. short(12), long(64) or number of digits between 4 and 64
         #[arg(short, long, default_value = "short")]
         length: VersionLength,
+        /// encoding of the digest. the version is truncated to `--length` after encoded
+        #[arg(long, default_value = "hex")]
+        encoding: VersionEncoding,
+        /// format of version. {version}, {short} and {path} are expanded
+        #[arg(short, long, default_value = "{version}")]
+        template: VersionTemplate,
+        /// ignore file mode, line endings and trailing whitespace of text files
+        #[arg(long)]
+        normalize: bool,
+        /// include the resolved dependency targets in the version
+        #[arg(long)]
+        hash_graph: bool,
+        /// omit the target path from the version, so a moved directory keeps the version
+        #[arg(long)]
+        path_independent: bool,
+        /// hash config files without metadata such as description
+        #[arg(long)]
+        ignore_config_metadata: bool,
+        /// with core.symlinks=false, follow regular files whose content is a path in the index as symbolic links
+        #[arg(long)]
+        symlinks_as_blobs: bool,
+        /// include untracked files which are not ignored (local only version)
+        #[arg(long)]
+        include_untracked: bool,
+        /// exclude pattern relative from the repository root, applied to all dependencies (can be specified multiple times)
+        #[arg(long = "global-exclude", value_name = "PATTERN")]
+        global_excludes: Vec<String>,
+        /// treat a config file without default profile as an empty default profile
+        #[arg(long)]
+        lenient: bool,
+        /// hash the content of files instead of their git object ids
+        #[arg(long)]
+        hash_content: bool,
+        /// how submodules are included in the version
+        #[arg(long, default_value = "commit")]
+        submodule_mode: SubmoduleModeArg,
+        /// max depth of nested submodules with `--submodule-mode recurse`
+        #[arg(long, default_value_t = 8)]
+        submodule_max_depth: usize,
+        /// report how many files each exclude removed to stderr
+        #[arg(long)]
+        report_excludes: bool,
+        /// print the number of sources and their total size in bytes with the version
+        #[arg(long, conflicts_with = "preview")]
+        stats: bool,
+        /// print versions of every profile of the target, keyed by the profile
+        #[arg(long, conflicts_with_all = ["profile", "preview", "stats"])]
+        all_profiles: bool,
+        /// print a single version folding the versions of all targets in the repository
+        #[arg(long, conflicts_with_all = ["profile", "preview", "stats", "all_profiles", "template"])]
+        aggregate: bool,
+        /// print the version at each commit of `<from>..<to>` (or the history of a revision) where it changed
+        #[arg(long, value_name = "RANGE", conflicts_with_all = ["preview", "stats", "all_profiles", "aggregate"])]
+        rev_range: Option<String>,
+        /// print versions of HEAD and the index, and whether committing changes the version
+        #[arg(long, conflicts_with_all = ["output", "template", "encoding"])]
+        preview: bool,
+        /// print the version again whenever the sources of the target change
+        #[cfg(feature = "watch")]
+        #[arg(long, conflicts_with_all = ["preview", "stats", "all_profiles", "aggregate", "rev_range"])]
+        watch: bool,
     },
     /// list package dependencies
     List {
+        /// target path. `-` reads a target from stdin
+        #[arg(default_value = ".")]
+        path: String,
+        /// profile of the target, overriding `:profile` of the path
+        #[arg(long)]
+        profile: Option<String>,
+        /// print SHA-256 digest of each file after the path
+        #[arg(long)]
+        digests: bool,
+        /// print the target which included each file, and whether it is the target itself, a dependency or a symlink
+        #[arg(long, conflicts_with = "digests")]
+        why: bool,
+        /// format of sources
+        #[arg(short, long, default_value = "text")]
+        output: ReportFormat,
+    },
+
+    /// print version, sources and their statistics of a target in json
+    Report {
         /// target path
         #[arg(default_value = ".")]
         path: String,
     },
 
+    /// verify the version of a target is still the expected version
+    Verify {
+        /// target path
+        path: String,
+        /// expected version. read from sver-versions.toml if omitted
+        expected: Option<String>,
+    },
+
     /// generate empty config file
     Init {
         /// target path
         #[arg(default_value = ".")]
         path: String,
+        /// write sibling directories as commented out dependencies
+        #[arg(long)]
+        scan: bool,
+        /// profiles written as sections (comma separated)
+        #[arg(long, value_delimiter = ',', default_value = "default")]
+        profiles: Vec<String>,
+    },
+
+    /// print the profile of a target as used for the calculation (TOML)
+    Config {
+        /// target path
+        #[arg(default_value = ".")]
+        path: String,
     },
 
+    /// print JSON Schema of config file
+    Schema,
+
     /// validate all config files in repository
-    Validate,
+    Validate {
+        /// dependency treated as valid even if it matches no file (can be specified multiple times)
+        #[arg(long = "allow-dependency", value_name = "DEPENDENCY")]
+        allow_dependencies: Vec<String>,
+        /// fail also when there are warnings
+        #[arg(long)]
+        fail_on_warning: bool,
+        /// validate the config files of this revision instead of the index
+        #[arg(long)]
+        rev: Option<String>,
+        /// format of validation results
+        #[arg(short, long, default_value = "text")]
+        output: ReportFormat,
+    },
+
+    /// diagnose common problems of repository and config files
+    Doctor,
+
+    /// print dependency graph of all config files in repository (Graphviz DOT format)
+    Graph,
+
+    /// list all calculable targets in repository
+    Targets {
+        /// print the description of each profile after a tab. always in json
+        #[arg(long)]
+        descriptions: bool,
+        /// format of targets
+        #[arg(short, long, default_value = "text")]
+        output: ReportFormat,
+    },
+
+    /// write versions of all targets to sver.lock at repository root
+    Lock {
+        /// compare versions with sver.lock instead of writing it
+        #[arg(long)]
+        check: bool,
+        /// format of sver.lock
+        #[arg(short, long, default_value = "toml")]
+        output: LockFormat,
+    },
+
+    /// list targets whose version differs between two revisions
+    Changed {
+        /// revision to compare from
+        #[arg(long)]
+        from: String,
+        /// revision to compare to
+        #[arg(long)]
+        to: String,
+        /// format of targets
+        #[arg(short, long, default_value = "text")]
+        output: ReportFormat,
+    },
+
+    /// list source files which differ between two revisions
+    Diff {
+        /// target path
+        #[arg(default_value = ".")]
+        path: String,
+        /// revision to compare from
+        #[arg(long)]
+        from: String,
+        /// revision to compare to
+        #[arg(long)]
+        to: String,
+        /// format of differences
+        #[arg(short, long, default_value = "text")]
+        output: ReportFormat,
+    },
 
     /// (experimental) list files accessed by a command
-    #[cfg(target_os = "linux")]
+    #[cfg(all(target_os = "linux", feature = "inspect"))]
     Inspect {
         /// command stdout target
         #[arg(short, long, default_value = "stdout")]
         output: StdoutTarget,
+        /// file events regarded as an access (comma separated)
+        #[arg(long, value_delimiter = ',', default_value = "access")]
+        events: Vec<InspectEventArg>,
+        /// print sver.toml depending on the accessed files instead of listing them
+        #[arg(long)]
+        emit_config: bool,
+        /// write sver.toml depending on the accessed files to the current directory
+        #[arg(long, conflicts_with = "emit_config")]
+        write_config: bool,
+        /// watch only the current directory and --include-dir directories
+        #[arg(long)]
+        subtree: bool,
+        /// directory watched with --subtree in addition (repeatable)
+        #[arg(long = "include-dir", value_name = "DIR", requires = "subtree")]
+        include_dirs: Vec<String>,
         /// inspect command
         command: String,
         /// inspect command arguments
@@ -51,13 +252,33 @@ pub(crate) enum Commands {
     },
 
     /// export package dependencies
+    #[cfg(feature = "export")]
     Export {
         /// target path
         #[arg(default_value = ".")]
         path: String,
+        /// profile of the target, overriding `:profile` of the path
+        #[arg(long)]
+        profile: Option<String>,
 
-        /// export directory
+        /// export directory. defaults to the temp directory named after the version, reused while the version is unchanged
         export_dir: Option<String>,
+
+        /// export into a new random directory in the temp directory every time
+        #[arg(long, conflicts_with = "export_dir")]
+        random_dir: bool,
+
+        /// print the files which would be kept and removed without exporting
+        #[arg(long, conflicts_with_all = ["export_dir", "random_dir"])]
+        dry_run: bool,
+
+        /// write the sources from the index instead of cloning the repository. faster, and submodules are written without .git
+        #[arg(long)]
+        from_index: bool,
+
+        /// include untracked files which are not ignored, copied from the working tree
+        #[arg(long, requires = "from_index")]
+        include_untracked: bool,
     },
 }
 
@@ -66,15 +287,79 @@ pub(crate) enum OutputFormat {
     VersionOnly,
     Toml,
     Json,
+    Yaml,
+}
+
+#[derive(Debug, Clone, ValueEnum)]
+pub(crate) enum ReportFormat {
+    Text,
+    Json,
 }
 
 #[derive(Debug, Clone, ValueEnum)]
+pub(crate) enum VersionEncoding {
+    /// lowercase hex (64 digits)
+    Hex,
+    /// lowercase base32 of RFC 4648 without padding (52 digits)
+    Base32,
+    /// base58 of the bitcoin alphabet (at most 44 digits)
+    Base58,
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+pub(crate) enum ColorMode {
+    /// colorize when stdout is a terminal, following NO_COLOR and CLICOLOR_FORCE
+    Auto,
+    Always,
+    Never,
+}
+
+impl ColorMode {
+    pub fn enabled(self) -> bool {
+        match self {
+            ColorMode::Auto => {
+                anstream::AutoStream::choice(&std::io::stdout()) != anstream::ColorChoice::Never
+            }
+            ColorMode::Always => true,
+            ColorMode::Never => false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, ValueEnum)]
+pub(crate) enum LockFormat {
+    Toml,
+    Json,
+}
+
+#[derive(Debug, Clone, ValueEnum)]
+pub(crate) enum SubmoduleModeArg {
+    /// pinned commit of submodules
+    Commit,
+    /// files checked out in submodules
+    Recurse,
+}
+
+/// `short`, `long` or number of hex digits.
+#[derive(Debug, Clone, PartialEq)]
 pub(crate) enum VersionLength {
     Short,
     Long,
+    Custom(usize),
+}
+
+#[cfg(all(target_os = "linux", feature = "inspect"))]
+#[derive(Debug, Clone, ValueEnum)]
+pub(crate) enum InspectEventArg {
+    /// the file was read
+    Access,
+    /// the file was opened, even if not read
+    Open,
+    /// the file was written
+    Modify,
 }
 
-#[cfg(target_os = "linux")]
+#[cfg(all(target_os = "linux", feature = "inspect"))]
 #[derive(Debug, Clone, ValueEnum)]
 pub(crate) enum StdoutTarget {
     /// send to parent process stdout
@@ -0,0 +1,261 @@
+use std::{
+    fs::File,
+    io::{BufRead, BufReader},
+};
+
+use anyhow::anyhow;
+use sver::{
+    sver_config::{is_valid_profile_name, CalculationTarget},
+    sver_repository::{CalcOptions, SverRepository},
+};
+
+/// Argument meaning "read targets from stdin".
+pub(crate) const STDIN_ARG: &str = "-";
+
+/// Replace `-` in `paths` with targets read from `reader`, one per line.
+///
+/// Leading and trailing whitespace is trimmed, and empty lines and lines starting with `#`
+/// are skipped.
+/// The order of targets is kept, so `a - b` becomes `a`, the lines of stdin, then `b`.
+/// `-` can be given only once because stdin can be read only once.
+pub(crate) fn expand_stdin_paths(
+    paths: Vec<String>,
+    reader: impl BufRead,
+) -> anyhow::Result<Vec<String>> {
+    if paths.iter().filter(|p| *p == STDIN_ARG).count() > 1 {
+        return Err(anyhow!("`-` can be specified only once"));
+    }
+    let Some(position) = paths.iter().position(|p| p == STDIN_ARG) else {
+        return Ok(paths);
+    };
+    let lines = read_path_lines(reader)?;
+    let mut paths = paths;
+    paths.splice(position..=position, lines);
+    Ok(paths)
+}
+
+/// Targets of `calc`: the positional paths with `-` expanded, followed by the targets of
+/// `paths_from`. The repository root when neither is given.
+pub(crate) fn calc_target_paths(
+    paths: Vec<String>,
+    paths_from: Option<&str>,
+    stdin: impl BufRead,
+) -> anyhow::Result<Vec<String>> {
+    if paths.is_empty() && paths_from.is_none() {
+        return Ok(vec![".".to_string()]);
+    }
+    let mut paths = expand_stdin_paths(paths, stdin)?;
+    if let Some(file) = paths_from {
+        let reader = File::open(file)
+            .map_err(|e| anyhow!("failed to read targets. path:{file}, error:{e}"))?;
+        paths.extend(read_path_lines(BufReader::new(reader))?);
+    }
+    Ok(paths)
+}
+
+// One target per line. Empty lines and comments are skipped.
+fn read_path_lines(reader: impl BufRead) -> anyhow::Result<Vec<String>> {
+    let mut lines = Vec::new();
+    for line in reader.lines() {
+        let line = line?;
+        let line = line.trim();
+        if !line.is_empty() && !line.starts_with('#') {
+            lines.push(line.to_string());
+        }
+    }
+    Ok(lines)
+}
+
+/// Read the target from `reader` if `path` is `-`. Exactly one target is required.
+pub(crate) fn read_stdin_path(path: String, reader: impl BufRead) -> anyhow::Result<String> {
+    let mut paths = expand_stdin_paths(vec![path], reader)?;
+    if paths.len() != 1 {
+        return Err(anyhow!(
+            "exactly one target is required from stdin. targets:{}",
+            paths.len()
+        ));
+    }
+    Ok(paths.remove(0))
+}
+
+/// Opens `path:profile` targets given on the command line.
+pub(crate) struct RepositoryOpener {
+    /// Root directory of the repository given by `--repo`.
+    /// Paths are relative from it instead of the current directory.
+    repository: Option<String>,
+    /// Profile given by `--profile`, which overrides `:profile` of paths.
+    profile: Option<String>,
+    options: CalcOptions,
+}
+
+impl RepositoryOpener {
+    pub(crate) fn new(repository: Option<String>) -> Self {
+        Self {
+            repository,
+            profile: None,
+            options: CalcOptions::default(),
+        }
+    }
+
+    /// Directory of the commands working on the whole repository, such as `doctor`.
+    /// `--repo` or the current directory.
+    pub(crate) fn repository_dir(&self) -> &str {
+        self.repository.as_deref().unwrap_or(".")
+    }
+
+    pub(crate) fn with_profile(mut self, profile: Option<String>) -> Self {
+        self.profile = profile;
+        self
+    }
+
+    pub(crate) fn with_options(mut self, options: CalcOptions) -> Self {
+        self.options = options;
+        self
+    }
+
+    pub(crate) fn open(&self, path: &str) -> anyhow::Result<SverRepository> {
+        let (CalculationTarget { path, profile }, overridden) = self.target(path)?;
+        if let Some(inline) = overridden {
+            eprintln!(
+                "warning: profile of the path is overridden by --profile. path:{path}, overridden_profile:{inline}"
+            );
+        }
+        let mut builder = SverRepository::builder()
+            .path(path)
+            .profile(profile)
+            .options(self.options.clone());
+        if let Some(repository) = &self.repository {
+            builder = builder.repository(repository.clone());
+        }
+        Ok(builder.build()?)
+    }
+
+    // The target of `path` and the inline profile overridden by `--profile` if any.
+    fn target(&self, path: &str) -> anyhow::Result<(CalculationTarget, Option<String>)> {
+        let Some(profile) = &self.profile else {
+            return Ok((CalculationTarget::parse(path), None));
+        };
+        if !is_valid_profile_name(profile) {
+            return Err(anyhow!("invalid profile name. profile:{profile}"));
+        }
+        let inline = CalculationTarget::parse(path);
+        // the path has `:profile` only when the rest is split off
+        let (path, overridden) = if inline.path != path {
+            (inline.path, Some(inline.profile))
+        } else {
+            (path.to_string(), None)
+        };
+        Ok((CalculationTarget::new(path, profile.clone()), overridden))
+    }
+}
+
+#[cfg(test)]
+mod inputs_tests {
+    use std::{env::temp_dir, io::Cursor};
+
+    use sver::sver_config::CalculationTarget;
+
+    use crate::cli::inputs::{
+        calc_target_paths, expand_stdin_paths, read_stdin_path, RepositoryOpener,
+    };
+
+    fn strings(values: &[&str]) -> Vec<String> {
+        values.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn expand_stdin_paths_test() {
+        let stdin = "service1\n\n  service2:prof \nlib1\n".as_bytes();
+        let paths = expand_stdin_paths(strings(&["a", "-", "b"]), stdin).unwrap();
+        assert_eq!(
+            paths,
+            strings(&["a", "service1", "service2:prof", "lib1", "b"])
+        );
+
+        // stdin is not read without `-`
+        let paths = expand_stdin_paths(strings(&["a"]), "ignored".as_bytes()).unwrap();
+        assert_eq!(paths, strings(&["a"]));
+
+        let paths = expand_stdin_paths(strings(&["-"]), "".as_bytes()).unwrap();
+        assert!(paths.is_empty());
+
+        assert!(expand_stdin_paths(strings(&["-", "-"]), "a".as_bytes()).is_err());
+    }
+
+    #[test]
+    fn expand_stdin_paths_with_comments_test() {
+        let stdin = Cursor::new("# release targets\nservice1\n\nservice2:prof\n  # lib1\nlib2\n");
+        let paths = expand_stdin_paths(strings(&["-"]), stdin).unwrap();
+        assert_eq!(paths, strings(&["service1", "service2:prof", "lib2"]));
+    }
+
+    #[test]
+    fn calc_target_paths_test() {
+        let mut file = temp_dir();
+        file.push(format!("sver-targets-{}.txt", std::process::id()));
+        std::fs::write(&file, "# release targets\nservice1\nservice2:prof\n").unwrap();
+        let file = file.to_str().unwrap();
+
+        let paths = calc_target_paths(strings(&["lib1"]), Some(file), "".as_bytes()).unwrap();
+        assert_eq!(paths, strings(&["lib1", "service1", "service2:prof"]));
+        let paths = calc_target_paths(vec![], Some(file), "".as_bytes()).unwrap();
+        assert_eq!(paths, strings(&["service1", "service2:prof"]));
+        let paths = calc_target_paths(vec![], None, "".as_bytes()).unwrap();
+        assert_eq!(paths, strings(&["."]));
+
+        let err = calc_target_paths(vec![], Some("not/found.txt"), "".as_bytes()).unwrap_err();
+        assert!(err.to_string().contains("path:not/found.txt"));
+        std::fs::remove_file(file).unwrap();
+    }
+
+    #[test]
+    fn read_stdin_path_test() {
+        assert_eq!(
+            read_stdin_path("-".to_string(), "service1\n".as_bytes()).unwrap(),
+            "service1"
+        );
+        assert_eq!(
+            read_stdin_path("service2".to_string(), "service1\n".as_bytes()).unwrap(),
+            "service2"
+        );
+        assert!(read_stdin_path("-".to_string(), "a\nb\n".as_bytes()).is_err());
+        assert!(read_stdin_path("-".to_string(), "".as_bytes()).is_err());
+    }
+
+    #[test]
+    fn target_with_profile_test() {
+        let target = |profile: Option<&str>, path: &str| {
+            RepositoryOpener::new(None)
+                .with_profile(profile.map(String::from))
+                .target(path)
+                .unwrap()
+        };
+        let new = |path: &str, profile: &str| CalculationTarget::new(path.into(), profile.into());
+
+        // flag alone
+        assert_eq!(
+            target(Some("ci"), "service1"),
+            (new("service1", "ci"), None)
+        );
+        assert_eq!(
+            target(Some("ci"), r"c:\hello"),
+            (new(r"c:\hello", "ci"), None)
+        );
+        // inline alone
+        assert_eq!(
+            target(None, "service1:prof1"),
+            (new("service1", "prof1"), None)
+        );
+        assert_eq!(target(None, "service1"), (new("service1", "default"), None));
+        // both together, the flag wins
+        assert_eq!(
+            target(Some("ci"), "service1:prof1"),
+            (new("service1", "ci"), Some("prof1".to_string()))
+        );
+
+        assert!(RepositoryOpener::new(None)
+            .with_profile(Some("with space".to_string()))
+            .target("service1")
+            .is_err());
+    }
+}
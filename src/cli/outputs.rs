@@ -1,51 +1,397 @@
+use std::{collections::BTreeMap, str::FromStr, sync::LazyLock};
+
+use anstyle::AnsiColor;
+use anyhow::anyhow;
+use data_encoding::{BASE32_NOPAD, HEXLOWER};
+use regex::Regex;
 use serde::Serialize;
-use sver::Version;
+use sver::{
+    lock::Lockfile,
+    sver_config::{CalculationTarget, ValidationResult},
+    sver_repository::{
+        CommitVersion, SourceDiff, SourceDigest, SourceProvenance, SourceStats, VersionPreview,
+    },
+    Version,
+};
 
-use super::args::{OutputFormat, VersionLength};
+use super::args::{LockFormat, OutputFormat, ReportFormat, VersionEncoding, VersionLength};
 
 #[derive(Serialize)]
-struct VersionOutput {
-    pub(crate) repository_root: String,
-    pub(crate) path: String,
-    pub(crate) version: String,
+struct VersionsOutput<'a, T> {
+    pub(crate) versions: &'a [T],
 }
 
 #[derive(Serialize)]
-struct VersionsOutput {
-    pub(crate) versions: Vec<VersionOutput>,
+struct VersionWithDetails {
+    #[serde(flatten)]
+    version: Version,
+    #[serde(flatten)]
+    stats: Option<SourceStats>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    contributing_targets: Option<Vec<String>>,
+}
+
+static PLACEHOLDER: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"\{([^{}]*)\}").unwrap());
+
+const SHORT_VERSION_LENGTH: usize = 12;
+const MIN_VERSION_LENGTH: usize = 4;
+// hex digits of SHA-256
+const FULL_VERSION_LENGTH: usize = 64;
+// warn if the probability of any two targets sharing a version exceeds this
+const COLLISION_WARNING_PROBABILITY: f64 = 0.001;
+
+impl FromStr for VersionLength {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "short" => Ok(VersionLength::Short),
+            "long" => Ok(VersionLength::Long),
+            _ => {
+                let length = s
+                    .parse::<usize>()
+                    .map_err(|_| anyhow!("length must be short, long or a number. length:{s}"))?;
+                if !(MIN_VERSION_LENGTH..=FULL_VERSION_LENGTH).contains(&length) {
+                    return Err(anyhow!(
+                        "length must be between {MIN_VERSION_LENGTH} and {FULL_VERSION_LENGTH}. length:{length}"
+                    ));
+                }
+                Ok(VersionLength::Custom(length))
+            }
+        }
+    }
+}
+
+impl VersionLength {
+    pub(crate) fn digits(&self) -> usize {
+        match self {
+            VersionLength::Short => SHORT_VERSION_LENGTH,
+            VersionLength::Long => FULL_VERSION_LENGTH,
+            VersionLength::Custom(length) => *length,
+        }
+    }
+
+    /// Approximate probability that some of `targets` share the same truncated version (birthday bound).
+    pub(crate) fn collision_probability(&self, targets: usize, encoding: &VersionEncoding) -> f64 {
+        let space = encoding.radix().powi(self.digits() as i32);
+        let pairs = (targets as f64) * (targets.saturating_sub(1) as f64) / 2.0;
+        (pairs / space).min(1.0)
+    }
+
+    pub(crate) fn risks_collision(&self, targets: usize, encoding: &VersionEncoding) -> bool {
+        self.collision_probability(targets, encoding) > COLLISION_WARNING_PROBABILITY
+    }
+}
+
+const BASE58_ALPHABET: &[u8] = b"123456789ABCDEFGHJKLMNPQRSTUVWXYZabcdefghijkmnopqrstuvwxyz";
+
+impl VersionEncoding {
+    /// Encode the digest bytes of the hex `version`.
+    pub(crate) fn encode(&self, version: &str) -> anyhow::Result<String> {
+        let digest = || {
+            HEXLOWER
+                .decode(version.as_bytes())
+                .map_err(|e| anyhow!("version is not a hex digest. version:{version}, error:{e}"))
+        };
+        let encoded = match self {
+            VersionEncoding::Hex => version.to_string(),
+            VersionEncoding::Base32 => BASE32_NOPAD.encode(&digest()?).to_ascii_lowercase(),
+            VersionEncoding::Base58 => encode_base58(&digest()?),
+        };
+        Ok(encoded)
+    }
+
+    fn radix(&self) -> f64 {
+        match self {
+            VersionEncoding::Hex => 16.0,
+            VersionEncoding::Base32 => 32.0,
+            VersionEncoding::Base58 => 58.0,
+        }
+    }
+}
+
+fn encode_base58(bytes: &[u8]) -> String {
+    // base58 digits in little endian
+    let mut digits: Vec<u8> = Vec::new();
+    for byte in bytes {
+        let mut carry = *byte as usize;
+        for digit in digits.iter_mut() {
+            carry += (*digit as usize) << 8;
+            *digit = (carry % 58) as u8;
+            carry /= 58;
+        }
+        while carry > 0 {
+            digits.push((carry % 58) as u8);
+            carry /= 58;
+        }
+    }
+    // each leading zero byte is encoded as the first character
+    let zeros = bytes.iter().take_while(|b| **b == 0).count();
+    std::iter::repeat_n(BASE58_ALPHABET[0], zeros)
+        .chain(digits.iter().rev().map(|d| BASE58_ALPHABET[*d as usize]))
+        .map(char::from)
+        .collect()
+}
+
+/// Format string of version. `{version}`, `{short}` and `{path}` are expanded.
+#[derive(Debug, Clone)]
+pub(crate) struct VersionTemplate(String);
+
+impl FromStr for VersionTemplate {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut placeholders = PLACEHOLDER.captures_iter(s).peekable();
+        if placeholders.peek().is_none() {
+            return Err(anyhow!(
+                "template must contain at least one of {{version}}, {{short}}, {{path}}"
+            ));
+        }
+        for caps in placeholders {
+            let name = &caps[1];
+            if !matches!(name, "version" | "short" | "path") {
+                return Err(anyhow!("unknown placeholder in template. {{{name}}}"));
+            }
+        }
+        Ok(Self(s.to_string()))
+    }
+}
+
+impl Default for VersionTemplate {
+    fn default() -> Self {
+        Self("{version}".to_string())
+    }
+}
+
+impl VersionTemplate {
+    fn expand(&self, version: &str, short: &str, path: &str) -> String {
+        PLACEHOLDER
+            .replace_all(&self.0, |caps: &regex::Captures| match &caps[1] {
+                "version" => version.to_string(),
+                "short" => short.to_string(),
+                "path" => path.to_string(),
+                _ => caps[0].to_string(),
+            })
+            .to_string()
+    }
 }
 
 pub(crate) fn format_versions(
     versions: &[Version],
     output_format: OutputFormat,
-    version_length: VersionLength,
+    version_length: &VersionLength,
+    template: &VersionTemplate,
+    encoding: &VersionEncoding,
+) -> anyhow::Result<String> {
+    let output = expand_versions(versions, version_length, template, encoding)?;
+    match output_format {
+        OutputFormat::VersionOnly => Ok(format_version_only(&output)),
+        output_format => serialize_versions(&output, output_format),
+    }
+}
+
+/// Same as [`format_versions`], and the details of each version are added.
+/// `stats` are added as `files` and `bytes` fields, or as a trailing line of each target in
+/// version only format. `contributing_targets` are added as a list of `path:profile` in
+/// structured formats.
+pub(crate) fn format_versions_with_details(
+    versions: &[Version],
+    stats: Option<&[SourceStats]>,
+    contributing_targets: Option<&[Vec<CalculationTarget>]>,
+    output_format: OutputFormat,
+    version_length: &VersionLength,
+    template: &VersionTemplate,
+    encoding: &VersionEncoding,
+) -> anyhow::Result<String> {
+    let output = expand_versions(versions, version_length, template, encoding)?;
+    match output_format {
+        OutputFormat::VersionOnly => {
+            let stats_lines =
+                output
+                    .iter()
+                    .zip(stats.unwrap_or_default())
+                    .map(|(version, stats)| {
+                        let path = if version.path.is_empty() {
+                            "."
+                        } else {
+                            &version.path
+                        };
+                        format!("{path}: {} files, {} bytes", stats.files, stats.bytes)
+                    });
+            Ok(std::iter::once(format_version_only(&output))
+                .chain(stats_lines)
+                .collect::<Vec<String>>()
+                .join("\n"))
+        }
+        output_format => {
+            let output = output
+                .into_iter()
+                .enumerate()
+                .map(|(i, version)| VersionWithDetails {
+                    version,
+                    stats: stats.map(|stats| stats[i].clone()),
+                    contributing_targets: contributing_targets
+                        .map(|targets| targets[i].iter().map(ToString::to_string).collect()),
+                })
+                .collect::<Vec<_>>();
+            serialize_versions(&output, output_format)
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct CommitVersionOutput<'a> {
+    commit: &'a str,
+    #[serde(flatten)]
+    version: Version,
+}
+
+/// Format versions at commits. Version only format prints `commit<TAB>version` lines.
+pub(crate) fn format_commit_versions(
+    commit_versions: &[CommitVersion],
+    output_format: OutputFormat,
+    version_length: &VersionLength,
+    template: &VersionTemplate,
+    encoding: &VersionEncoding,
+) -> anyhow::Result<String> {
+    let versions = commit_versions
+        .iter()
+        .map(|commit_version| commit_version.version.clone())
+        .collect::<Vec<_>>();
+    let output = expand_versions(&versions, version_length, template, encoding)?;
+    match output_format {
+        OutputFormat::VersionOnly => Ok(commit_versions
+            .iter()
+            .zip(output.chunks(1))
+            .map(|(commit_version, version)| {
+                format!(
+                    "{}\t{}",
+                    commit_version.commit,
+                    format_version_only(version)
+                )
+            })
+            .collect::<Vec<String>>()
+            .join("\n")),
+        output_format => {
+            let output = commit_versions
+                .iter()
+                .zip(output)
+                .map(|(commit_version, version)| CommitVersionOutput {
+                    commit: &commit_version.commit,
+                    version,
+                })
+                .collect::<Vec<_>>();
+            // always a list, even for a single commit
+            let output_string = match output_format {
+                OutputFormat::VersionOnly => unreachable!("version only format is not serialized"),
+                OutputFormat::Toml => toml::to_string(&VersionsOutput { versions: &output })?,
+                OutputFormat::Json => serde_json::to_string_pretty(&output)?,
+                OutputFormat::Yaml => serde_norway::to_string(&output)?.trim_end().to_string(),
+            };
+            Ok(output_string)
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct AggregateVersion<'a> {
+    repository_root: &'a str,
+    version: String,
+}
+
+/// Format the aggregate version of all targets in the repository.
+pub(crate) fn format_aggregate_version(
+    repository_root: &str,
+    version: &str,
+    output_format: OutputFormat,
+    version_length: &VersionLength,
+    encoding: &VersionEncoding,
+) -> anyhow::Result<String> {
+    let mut version = encoding.encode(version)?;
+    version.truncate(version_length.digits());
+    let output = AggregateVersion {
+        repository_root,
+        version,
+    };
+    let output_string = match output_format {
+        OutputFormat::VersionOnly => output.version,
+        OutputFormat::Toml => toml::to_string(&output)?,
+        OutputFormat::Json => serde_json::to_string_pretty(&output)?,
+        OutputFormat::Yaml => serde_norway::to_string(&output)?.trim_end().to_string(),
+    };
+    Ok(output_string)
+}
+
+/// Format versions of the profiles of one target. Structured formats are keyed by the profile.
+pub(crate) fn format_profile_versions(
+    versions: &[Version],
+    output_format: OutputFormat,
+    version_length: &VersionLength,
+    template: &VersionTemplate,
+    encoding: &VersionEncoding,
 ) -> anyhow::Result<String> {
-    let output: Vec<VersionOutput> = versions
+    let output = expand_versions(versions, version_length, template, encoding)?;
+    let by_profile = || {
+        output
+            .iter()
+            .map(|version| (version.profile.as_str(), version))
+            .collect::<BTreeMap<_, _>>()
+    };
+    let output_string = match output_format {
+        OutputFormat::VersionOnly => format_version_only(&output),
+        OutputFormat::Toml => toml::to_string(&by_profile())?,
+        OutputFormat::Json => serde_json::to_string_pretty(&by_profile())?,
+        OutputFormat::Yaml => serde_norway::to_string(&by_profile())?
+            .trim_end()
+            .to_string(),
+    };
+    Ok(output_string)
+}
+
+fn expand_versions(
+    versions: &[Version],
+    version_length: &VersionLength,
+    template: &VersionTemplate,
+    encoding: &VersionEncoding,
+) -> anyhow::Result<Vec<Version>> {
+    versions
         .iter()
         .map(|v| {
-            let mut version_string = v.version.clone();
-            match version_length {
-                VersionLength::Short => version_string.truncate(12),
-                VersionLength::Long => (),
-            };
-            VersionOutput {
-                repository_root: v.repository_root.clone(),
-                path: v.path.clone(),
-                version: version_string,
+            let encoded = encoding.encode(&v.version)?;
+            let mut version_string = encoded.clone();
+            version_string.truncate(version_length.digits());
+            let mut short_version = encoded;
+            short_version.truncate(SHORT_VERSION_LENGTH);
+            Ok(Version {
+                version: template.expand(&version_string, &short_version, &v.path),
+                ..v.clone()
+            })
+        })
+        .collect()
+}
+
+fn format_version_only(output: &[Version]) -> String {
+    // the default profile is omitted to keep the output of existing scripts
+    output
+        .iter()
+        .map(|o| {
+            if o.profile == "default" {
+                o.version.clone()
+            } else {
+                format!("{}:{}", o.version, o.profile)
             }
         })
-        .collect();
+        .collect::<Vec<String>>()
+        .join("\n")
+}
 
+// A single version is written as an object, and multiple versions as a list.
+fn serialize_versions<T: Serialize>(
+    output: &[T],
+    output_format: OutputFormat,
+) -> anyhow::Result<String> {
     let output_string = match output_format {
-        OutputFormat::VersionOnly => {
-            let out = output
-                .iter()
-                .map(|o| &o.version)
-                .cloned()
-                .collect::<Vec<String>>()
-                .join("\n");
-            out
-        }
+        OutputFormat::VersionOnly => unreachable!("version only format is not serialized"),
         OutputFormat::Toml => {
             if output.len() == 1 {
                 toml::to_string(&output[0])?
@@ -60,6 +406,649 @@ pub(crate) fn format_versions(
                 serde_json::to_string_pretty(&output)?
             }
         }
+        OutputFormat::Yaml => {
+            let out = if output.len() == 1 {
+                serde_norway::to_string(&output[0])?
+            } else {
+                serde_norway::to_string(&output)?
+            };
+            out.trim_end().to_string()
+        }
     };
     Ok(output_string)
 }
+
+pub(crate) fn format_previews(
+    previews: &[VersionPreview],
+    version_length: &VersionLength,
+) -> String {
+    let truncate = |version: &str| version[..version_length.digits()].to_string();
+    previews
+        .iter()
+        .map(|p| {
+            let head = p
+                .head
+                .as_ref()
+                .map(|head| truncate(&head.version))
+                .unwrap_or_else(|| "(none)".to_string());
+            let changed = if p.is_changed() {
+                "changed"
+            } else {
+                "unchanged"
+            };
+            let path = if p.index.path.is_empty() {
+                "."
+            } else {
+                &p.index.path
+            };
+            format!(
+                "{}\thead:{}\tindex:{}\t{}",
+                path,
+                head,
+                truncate(&p.index.version),
+                changed
+            )
+        })
+        .collect::<Vec<String>>()
+        .join("\n")
+}
+
+#[derive(Serialize)]
+struct TargetWithDescription<'a> {
+    #[serde(flatten)]
+    target: &'a CalculationTarget,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    description: Option<&'a String>,
+}
+
+pub(crate) fn format_targets(
+    targets: &[CalculationTarget],
+    descriptions: &BTreeMap<CalculationTarget, String>,
+    output_format: ReportFormat,
+) -> anyhow::Result<String> {
+    let output_string = match output_format {
+        ReportFormat::Text => targets
+            .iter()
+            .map(|t| match descriptions.get(t) {
+                Some(description) => format!("{t}\t{description}"),
+                None => t.to_string(),
+            })
+            .collect::<Vec<String>>()
+            .join("\n"),
+        ReportFormat::Json => {
+            let targets = targets
+                .iter()
+                .map(|target| TargetWithDescription {
+                    target,
+                    description: descriptions.get(target),
+                })
+                .collect::<Vec<_>>();
+            serde_json::to_string_pretty(&targets)?
+        }
+    };
+    Ok(output_string)
+}
+
+pub(crate) fn format_lockfile(
+    lockfile: &Lockfile,
+    output_format: LockFormat,
+) -> anyhow::Result<String> {
+    let output_string = match output_format {
+        LockFormat::Toml => toml::to_string(lockfile)?,
+        LockFormat::Json => format!("{}\n", serde_json::to_string_pretty(lockfile)?),
+    };
+    Ok(output_string)
+}
+
+pub(crate) fn format_sources(
+    sources: &[String],
+    output_format: ReportFormat,
+) -> anyhow::Result<String> {
+    let output_string = match output_format {
+        ReportFormat::Text => sources.join("\n"),
+        ReportFormat::Json => serde_json::to_string_pretty(sources)?,
+    };
+    Ok(output_string)
+}
+
+#[derive(Serialize)]
+struct SourceDigestOutput<'a> {
+    path: &'a str,
+    // octal as git prints it, e.g. `100644`
+    mode: String,
+    digest: &'a str,
+}
+
+pub(crate) fn format_source_digests(
+    digests: &[SourceDigest],
+    output_format: ReportFormat,
+) -> anyhow::Result<String> {
+    let output_string = match output_format {
+        ReportFormat::Text => digests
+            .iter()
+            .map(|SourceDigest { path, digest, .. }| format!("{path}\t{digest}"))
+            .collect::<Vec<String>>()
+            .join("\n"),
+        ReportFormat::Json => serde_json::to_string_pretty(
+            &digests
+                .iter()
+                .map(|SourceDigest { path, mode, digest }| SourceDigestOutput {
+                    path,
+                    mode: format!("{:06o}", u32::from(*mode)),
+                    digest,
+                })
+                .collect::<Vec<_>>(),
+        )?,
+    };
+    Ok(output_string)
+}
+
+pub(crate) fn format_source_provenance(
+    provenance: &[SourceProvenance],
+    output_format: ReportFormat,
+) -> anyhow::Result<String> {
+    let output_string = match output_format {
+        ReportFormat::Text => {
+            let width = provenance
+                .iter()
+                .map(|p| p.path.chars().count())
+                .max()
+                .unwrap_or(0);
+            provenance
+                .iter()
+                .map(|p| format!("{:width$}  {} ({})", p.path, p.target, p.reason))
+                .collect::<Vec<String>>()
+                .join("\n")
+        }
+        ReportFormat::Json => serde_json::to_string_pretty(provenance)?,
+    };
+    Ok(output_string)
+}
+
+pub(crate) fn format_source_diff(
+    diff: &SourceDiff,
+    output_format: ReportFormat,
+) -> anyhow::Result<String> {
+    let output_string = match output_format {
+        ReportFormat::Text => diff.to_string(),
+        ReportFormat::Json => format!("{}\n", serde_json::to_string_pretty(diff)?),
+    };
+    Ok(output_string)
+}
+
+/// Text of a validation result, with the label colored by the result when `color` is set.
+pub(crate) fn format_validation_result(result: &ValidationResult, color: bool) -> String {
+    let text = result.to_string();
+    if !color {
+        return text;
+    }
+    let style = match result {
+        ValidationResult::Valid { .. } => AnsiColor::Green,
+        ValidationResult::Warning { .. } | ValidationResult::UnmatchedIgnore { .. } => {
+            AnsiColor::Yellow
+        }
+        ValidationResult::Invalid { .. } | ValidationResult::SyntaxError(_) => AnsiColor::Red,
+    }
+    .on_default();
+    let label = result.label();
+    text.replacen(label, &format!("{style}{label}{style:#}"), 1)
+}
+
+#[cfg(test)]
+mod outputs_tests {
+    use data_encoding::{BASE32_NOPAD, HEXLOWER};
+    use sver::Version;
+
+    use crate::cli::{
+        args::{OutputFormat, ReportFormat, VersionEncoding, VersionLength},
+        outputs::{
+            format_commit_versions, format_profile_versions, format_source_digests, format_sources,
+            format_targets, format_validation_result, format_versions,
+            format_versions_with_details, VersionTemplate, BASE58_ALPHABET,
+        },
+    };
+    use sver::filemode::FileMode;
+    use sver::sver_config::{CalculationTarget, ValidationResult};
+    use sver::sver_repository::{CommitVersion, SourceDigest, SourceStats};
+
+    fn versions() -> Vec<Version> {
+        vec![Version {
+            repository_root: "/repo/".to_string(),
+            path: "service1".to_string(),
+            profile: "default".to_string(),
+            version: "d601cac0967b58cd86a3a0384709f81ada1db3a42060e4458b843a7c7613b6ea".to_string(),
+        }]
+    }
+
+    #[test]
+    fn template_test() {
+        let template = "v1.0.0-{short}@{path}".parse::<VersionTemplate>().unwrap();
+        let result = format_versions(
+            &versions(),
+            OutputFormat::VersionOnly,
+            &VersionLength::Long,
+            &template,
+            &VersionEncoding::Hex,
+        )
+        .unwrap();
+        assert_eq!(result, "v1.0.0-d601cac0967b@service1");
+
+        let result = format_versions(
+            &versions(),
+            OutputFormat::VersionOnly,
+            &VersionLength::Short,
+            &VersionTemplate::default(),
+            &VersionEncoding::Hex,
+        )
+        .unwrap();
+        assert_eq!(result, "d601cac0967b");
+    }
+
+    #[test]
+    fn invalid_template_test() {
+        let err = "{version}-{unknown}"
+            .parse::<VersionTemplate>()
+            .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "unknown placeholder in template. {unknown}"
+        );
+        assert!("no placeholder".parse::<VersionTemplate>().is_err());
+    }
+
+    #[test]
+    fn version_length_test() {
+        assert_eq!("short".parse::<VersionLength>().unwrap().digits(), 12);
+        assert_eq!("long".parse::<VersionLength>().unwrap().digits(), 64);
+        assert_eq!(
+            "4".parse::<VersionLength>().unwrap(),
+            VersionLength::Custom(4)
+        );
+        assert_eq!(
+            "64".parse::<VersionLength>().unwrap(),
+            VersionLength::Custom(64)
+        );
+
+        let result = format_versions(
+            &versions(),
+            OutputFormat::VersionOnly,
+            &"8".parse::<VersionLength>().unwrap(),
+            &VersionTemplate::default(),
+            &VersionEncoding::Hex,
+        )
+        .unwrap();
+        assert_eq!(result, "d601cac0");
+    }
+
+    #[test]
+    fn invalid_version_length_test() {
+        assert_eq!(
+            "3".parse::<VersionLength>().unwrap_err().to_string(),
+            "length must be between 4 and 64. length:3"
+        );
+        assert_eq!(
+            "65".parse::<VersionLength>().unwrap_err().to_string(),
+            "length must be between 4 and 64. length:65"
+        );
+        assert!("0".parse::<VersionLength>().is_err());
+        assert!("middle".parse::<VersionLength>().is_err());
+    }
+
+    fn decode_base58(encoded: &str) -> Vec<u8> {
+        let mut bytes: Vec<u8> = Vec::new();
+        for c in encoded.bytes() {
+            let mut carry = BASE58_ALPHABET.iter().position(|a| *a == c).unwrap();
+            for byte in bytes.iter_mut() {
+                carry += (*byte as usize) * 58;
+                *byte = (carry & 0xff) as u8;
+                carry >>= 8;
+            }
+            while carry > 0 {
+                bytes.push((carry & 0xff) as u8);
+                carry >>= 8;
+            }
+        }
+        let zeros = encoded.bytes().take_while(|c| *c == b'1').count();
+        std::iter::repeat_n(0, zeros)
+            .chain(bytes.into_iter().rev())
+            .collect()
+    }
+
+    #[test]
+    fn encoding_test() {
+        let version = &versions()[0].version;
+        let digest = HEXLOWER.decode(version.as_bytes()).unwrap();
+
+        assert_eq!(VersionEncoding::Hex.encode(version).unwrap(), *version);
+        let base32 = VersionEncoding::Base32.encode(version).unwrap();
+        assert_eq!(base32.len(), 52);
+        assert_eq!(
+            BASE32_NOPAD
+                .decode(base32.to_ascii_uppercase().as_bytes())
+                .unwrap(),
+            digest
+        );
+        let base58 = VersionEncoding::Base58.encode(version).unwrap();
+        assert!(base58.len() <= 44);
+        assert_eq!(decode_base58(&base58), digest);
+
+        // leading zero bytes are kept
+        let zeros = format!("0000{}", &version[4..]);
+        let base58 = VersionEncoding::Base58.encode(&zeros).unwrap();
+        assert!(base58.starts_with("11"));
+        assert_eq!(
+            decode_base58(&base58),
+            HEXLOWER.decode(zeros.as_bytes()).unwrap()
+        );
+
+        assert!(VersionEncoding::Base32.encode("not hex").is_err());
+    }
+
+    #[test]
+    fn encoding_with_length_test() {
+        let format = |length: VersionLength, encoding: VersionEncoding| {
+            format_versions(
+                &versions(),
+                OutputFormat::VersionOnly,
+                &length,
+                &VersionTemplate::default(),
+                &encoding,
+            )
+            .unwrap()
+        };
+        let version = &versions()[0].version;
+        for encoding in [
+            VersionEncoding::Hex,
+            VersionEncoding::Base32,
+            VersionEncoding::Base58,
+        ] {
+            let encoded = encoding.encode(version).unwrap();
+            assert_eq!(
+                format(VersionLength::Short, encoding.clone()),
+                encoded[..12]
+            );
+            assert_eq!(
+                format(VersionLength::Custom(8), encoding.clone()),
+                encoded[..8]
+            );
+            // the full length is shorter than 64 digits except hex
+            assert_eq!(format(VersionLength::Long, encoding), encoded);
+        }
+    }
+
+    #[test]
+    fn collision_risk_test() {
+        let short = VersionLength::Custom(4);
+        assert!(!short.risks_collision(1, &VersionEncoding::Hex));
+        assert!(short.risks_collision(100, &VersionEncoding::Hex));
+        assert!(!VersionLength::Short.risks_collision(10_000, &VersionEncoding::Hex));
+        // more characters make collisions less likely in the same length
+        assert!(!short.risks_collision(20, &VersionEncoding::Base58));
+        assert!(short.risks_collision(20, &VersionEncoding::Hex));
+    }
+
+    #[test]
+    fn versions_with_non_default_profile_test() {
+        let versions = vec![
+            Version {
+                profile: "prof2".to_string(),
+                ..versions()[0].clone()
+            },
+            versions()[0].clone(),
+        ];
+        let format = |output_format| {
+            format_versions(
+                &versions,
+                output_format,
+                &VersionLength::Short,
+                &VersionTemplate::default(),
+                &VersionEncoding::Hex,
+            )
+            .unwrap()
+        };
+
+        assert_eq!(
+            format(OutputFormat::VersionOnly),
+            "d601cac0967b:prof2\nd601cac0967b"
+        );
+        let json = serde_json::from_str::<serde_json::Value>(&format(OutputFormat::Json)).unwrap();
+        assert_eq!(json[0]["profile"], "prof2");
+        assert_eq!(json[1]["profile"], "default");
+        let toml = format(OutputFormat::Toml);
+        assert!(toml.contains("profile = \"prof2\"\n"));
+        assert!(toml.contains("profile = \"default\"\n"));
+        assert_eq!(
+            format(OutputFormat::Yaml),
+            "- repository_root: /repo/
+  path: service1
+  profile: prof2
+  version: d601cac0967b
+- repository_root: /repo/
+  path: service1
+  profile: default
+  version: d601cac0967b"
+        );
+    }
+
+    #[test]
+    fn versions_with_profile_test() {
+        let json = format_versions(
+            &versions(),
+            OutputFormat::Json,
+            &VersionLength::Short,
+            &VersionTemplate::default(),
+            &VersionEncoding::Hex,
+        )
+        .unwrap();
+        assert_eq!(
+            serde_json::from_str::<serde_json::Value>(&json).unwrap(),
+            serde_json::json!({
+                "repository_root": "/repo/",
+                "path": "service1",
+                "profile": "default",
+                "version": "d601cac0967b",
+            })
+        );
+
+        let toml = format_versions(
+            &versions(),
+            OutputFormat::Toml,
+            &VersionLength::Short,
+            &VersionTemplate::default(),
+            &VersionEncoding::Hex,
+        )
+        .unwrap();
+        assert!(toml.contains("profile = \"default\"\n"));
+    }
+
+    #[test]
+    fn versions_with_stats_test() {
+        let stats = [SourceStats {
+            files: 3,
+            bytes: 1024,
+        }];
+        let format = |output_format| {
+            format_versions_with_details(
+                &versions(),
+                Some(&stats),
+                None,
+                output_format,
+                &VersionLength::Short,
+                &VersionTemplate::default(),
+                &VersionEncoding::Hex,
+            )
+            .unwrap()
+        };
+
+        assert_eq!(
+            format(OutputFormat::VersionOnly),
+            "d601cac0967b\nservice1: 3 files, 1024 bytes"
+        );
+        let json = serde_json::from_str::<serde_json::Value>(&format(OutputFormat::Json)).unwrap();
+        assert_eq!(json["version"], "d601cac0967b");
+        assert_eq!(json["files"], 3);
+        assert_eq!(json["bytes"], 1024);
+        let toml = format(OutputFormat::Toml);
+        assert!(toml.contains("files = 3\n"));
+        assert!(toml.contains("bytes = 1024\n"));
+        assert!(!toml.contains("contributing_targets"));
+    }
+
+    #[test]
+    fn commit_versions_test() {
+        let commit_versions = [CommitVersion {
+            commit: "0123abcd".to_string(),
+            version: versions()[0].clone(),
+        }];
+        let format = |output_format| {
+            format_commit_versions(
+                &commit_versions,
+                output_format,
+                &VersionLength::Short,
+                &VersionTemplate::default(),
+                &VersionEncoding::Hex,
+            )
+            .unwrap()
+        };
+
+        assert_eq!(format(OutputFormat::VersionOnly), "0123abcd\td601cac0967b");
+        let json = serde_json::from_str::<serde_json::Value>(&format(OutputFormat::Json)).unwrap();
+        // a list even for a single commit
+        assert_eq!(json[0]["commit"], "0123abcd");
+        assert_eq!(json[0]["version"], "d601cac0967b");
+        assert_eq!(json[0]["path"], "service1");
+    }
+
+    #[test]
+    fn versions_with_contributing_targets_test() {
+        let contributing_targets = [vec![
+            CalculationTarget::new("".to_string(), "default".to_string()),
+            CalculationTarget::new("service1".to_string(), "prof1".to_string()),
+        ]];
+        let json = format_versions_with_details(
+            &versions(),
+            None,
+            Some(&contributing_targets),
+            OutputFormat::Json,
+            &VersionLength::Short,
+            &VersionTemplate::default(),
+            &VersionEncoding::Hex,
+        )
+        .unwrap();
+
+        let json = serde_json::from_str::<serde_json::Value>(&json).unwrap();
+        assert_eq!(json["version"], "d601cac0967b");
+        assert_eq!(
+            json["contributing_targets"],
+            serde_json::json!([".:default", "service1:prof1"])
+        );
+        assert!(json.get("files").is_none());
+    }
+
+    #[test]
+    fn profile_versions_test() {
+        let versions = vec![
+            versions()[0].clone(),
+            Version {
+                profile: "prof1".to_string(),
+                version: "a".repeat(64),
+                ..versions()[0].clone()
+            },
+        ];
+        let format = |output_format| {
+            format_profile_versions(
+                &versions,
+                output_format,
+                &VersionLength::Short,
+                &VersionTemplate::default(),
+                &VersionEncoding::Hex,
+            )
+            .unwrap()
+        };
+
+        assert_eq!(
+            format(OutputFormat::VersionOnly),
+            "d601cac0967b\naaaaaaaaaaaa:prof1"
+        );
+        let json = serde_json::from_str::<serde_json::Value>(&format(OutputFormat::Json)).unwrap();
+        assert_eq!(json["default"]["version"], "d601cac0967b");
+        assert_eq!(json["prof1"]["version"], "aaaaaaaaaaaa");
+        let toml = toml::from_str::<toml::Value>(&format(OutputFormat::Toml)).unwrap();
+        assert_eq!(toml["default"]["version"].as_str(), Some("d601cac0967b"));
+        assert_eq!(toml["prof1"]["version"].as_str(), Some("aaaaaaaaaaaa"));
+    }
+
+    #[test]
+    fn sources_json_test() {
+        let sources = vec!["hello.txt".to_string(), "service1/world.txt".to_string()];
+
+        assert_eq!(
+            format_sources(&sources, ReportFormat::Text).unwrap(),
+            "hello.txt\nservice1/world.txt"
+        );
+        let json = format_sources(&sources, ReportFormat::Json).unwrap();
+        assert_eq!(
+            serde_json::from_str::<serde_json::Value>(&json).unwrap(),
+            serde_json::json!(["hello.txt", "service1/world.txt"])
+        );
+
+        let digests = vec![SourceDigest {
+            path: "hello.txt".to_string(),
+            mode: FileMode::BlobExecutable,
+            digest: "0123abcd".to_string(),
+        }];
+        assert_eq!(
+            format_source_digests(&digests, ReportFormat::Text).unwrap(),
+            "hello.txt\t0123abcd"
+        );
+        let json = format_source_digests(&digests, ReportFormat::Json).unwrap();
+        assert_eq!(
+            serde_json::from_str::<serde_json::Value>(&json).unwrap(),
+            serde_json::json!([{ "path": "hello.txt", "mode": "100755", "digest": "0123abcd" }])
+        );
+    }
+
+    #[test]
+    fn validation_result_color_test() {
+        let result = ValidationResult::Valid {
+            calcuration_target: CalculationTarget::new(
+                "service1".to_string(),
+                "default".to_string(),
+            ),
+            config_file_name: "sver.toml".to_string(),
+        };
+
+        let plain = format_validation_result(&result, false);
+        assert_eq!(plain, "[OK]\tservice1/sver.toml:[default]\n");
+        assert!(!plain.contains('\x1b'));
+
+        let colored = format_validation_result(&result, true);
+        assert_eq!(
+            colored,
+            "\x1b[32m[OK]\x1b[0m\tservice1/sver.toml:[default]\n"
+        );
+    }
+
+    #[test]
+    fn targets_with_description_test() {
+        let targets = vec![
+            CalculationTarget::new("".to_string(), "build".to_string()),
+            CalculationTarget::new("service1".to_string(), "default".to_string()),
+        ];
+        let descriptions =
+            std::collections::BTreeMap::from([(targets[0].clone(), "release build".to_string())]);
+
+        let result = format_targets(&targets, &descriptions, ReportFormat::Text).unwrap();
+        assert_eq!(result, ".:build\trelease build\nservice1:default");
+
+        let result = format_targets(&targets, &descriptions, ReportFormat::Json).unwrap();
+        let json: serde_json::Value = serde_json::from_str(&result).unwrap();
+        assert_eq!(
+            json,
+            serde_json::json!([
+                {"path": "", "profile": "build", "description": "release build"},
+                {"path": "service1", "profile": "default"},
+            ])
+        );
+    }
+}
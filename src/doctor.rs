@@ -0,0 +1,149 @@
+use std::{fmt::Display, path::Path};
+
+use git2::{Repository, Status, StatusOptions};
+
+use crate::{
+    filemode::FileMode,
+    find_repository, match_samefile_or_include_dir, resolve_link_target,
+    sver_config::{config_file_name, CalculationTarget, SverConfig},
+    SverError,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+#[derive(Debug)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    pub message: String,
+}
+
+impl Diagnostic {
+    fn error(message: String) -> Self {
+        Self {
+            severity: Severity::Error,
+            message,
+        }
+    }
+
+    fn warning(message: String) -> Self {
+        Self {
+            severity: Severity::Warning,
+            message,
+        }
+    }
+}
+
+impl Display for Diagnostic {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.severity {
+            Severity::Error => writeln!(f, "[Error]\t{}", self.message),
+            Severity::Warning => writeln!(f, "[Warning]\t{}", self.message),
+        }
+    }
+}
+
+/// Diagnose common problems of the repository and config files.
+pub fn diagnose(path: &str) -> Result<Vec<Diagnostic>, SverError> {
+    let repo = find_repository(Path::new(path))?;
+    if repo.is_bare() {
+        return Ok(vec![Diagnostic::error(
+            "bare repository is not supported".to_string(),
+        )]);
+    }
+
+    let mut diagnostics = Vec::new();
+    if repo.index()?.is_empty() {
+        diagnostics.push(Diagnostic::warning(
+            "index is empty. sver calculates versions from staged files".to_string(),
+        ));
+    }
+    check_unstaged_configs(&repo, &mut diagnostics)?;
+    check_escaping_symlinks(&repo, &mut diagnostics)?;
+    check_configs(&repo, &mut diagnostics)?;
+    diagnostics.sort_by_key(|d| d.severity);
+    Ok(diagnostics)
+}
+
+fn check_unstaged_configs(
+    repo: &Repository,
+    diagnostics: &mut Vec<Diagnostic>,
+) -> Result<(), SverError> {
+    let mut options = StatusOptions::new();
+    options.include_untracked(true).recurse_untracked_dirs(true);
+    for status in repo.statuses(Some(&mut options))?.iter() {
+        let Some(path) = status.path() else {
+            continue;
+        };
+        if config_file_name(path.as_bytes()).is_none() {
+            continue;
+        }
+        if status.status().contains(Status::WT_NEW) {
+            diagnostics.push(Diagnostic::warning(format!(
+                "config file is not staged. path:{path}"
+            )));
+        } else if status.status().contains(Status::WT_MODIFIED) {
+            diagnostics.push(Diagnostic::warning(format!(
+                "config file has unstaged changes. path:{path}"
+            )));
+        }
+    }
+    Ok(())
+}
+
+fn check_escaping_symlinks(
+    repo: &Repository,
+    diagnostics: &mut Vec<Diagnostic>,
+) -> Result<(), SverError> {
+    for entry in repo.index()?.iter() {
+        if FileMode::from(entry.mode) != FileMode::Link {
+            continue;
+        }
+        let path = String::from_utf8_lossy(&entry.path).to_string();
+        let blob = repo.find_blob(entry.id)?;
+        let link_target = String::from_utf8_lossy(blob.content()).to_string();
+        if resolve_link_target(&path, &link_target).is_none() {
+            diagnostics.push(Diagnostic::warning(format!(
+                "symbolic link points outside of the repository. path:{path}, target:{link_target}"
+            )));
+        }
+    }
+    Ok(())
+}
+
+fn check_configs(repo: &Repository, diagnostics: &mut Vec<Diagnostic>) -> Result<(), SverError> {
+    let index = repo.index()?;
+    let configs = SverConfig::load_all_configs(repo, &index)?;
+    for sver_config in &configs {
+        if sver_config.get("default").is_none() {
+            diagnostics.push(Diagnostic::warning(format!(
+                "profile[default] is not found. path:{}",
+                sver_config.config_file_path()
+            )));
+        }
+        for (profile, config) in sver_config.iter() {
+            for dependency in &config.dependencies {
+                // dependencies escaping the repository root resolve to no files
+                let resolved =
+                    CalculationTarget::parse_dependency(&sver_config.target_path, dependency)
+                        .is_ok_and(|CalculationTarget { path, .. }| {
+                            index.iter().any(|entry| {
+                                match_samefile_or_include_dir(&entry.path, path.as_bytes())
+                            })
+                        });
+                if !resolved {
+                    diagnostics.push(Diagnostic::error(format!(
+                        "dependency resolves to no files. path:{}:[{}], dependency:{}",
+                        sver_config.config_file_path(),
+                        profile,
+                        dependency
+                    )));
+                }
+            }
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,45 @@
+#[derive(Debug, thiserror::Error)]
+pub enum SverError {
+    #[error("repository was not found")]
+    RepositoryNotFound,
+    /// The search of the repository stopped at a directory of `GIT_CEILING_DIRECTORIES` or
+    /// at a filesystem boundary.
+    #[error("repository was not found below the boundary. path:{path}, boundary:{boundary}")]
+    RepositoryNotFoundBelow { path: String, boundary: String },
+    #[error("bare repository is not supported")]
+    BareRepositoryUnsupported,
+    #[error("profile[{0}] is not found")]
+    ProfileNotFound(String),
+    #[error("{0}")]
+    InvalidConfig(String),
+    #[error("invalid lockfile. {0}")]
+    InvalidLockfile(String),
+    #[error("path is not valid UTF-8. path:{0}")]
+    NonUtf8Path(String),
+    #[error("too many levels of symbolic links. path:{0}")]
+    TooManyLinks(String),
+    #[error("path is not inside the repository. path:{path}, repository:{repository}")]
+    OutsideRepository { path: String, repository: String },
+    #[error(transparent)]
+    Git(#[from] git2::Error),
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+}
+
+impl SverError {
+    pub(crate) fn non_utf8_path(path: &[u8]) -> Self {
+        SverError::NonUtf8Path(String::from_utf8_lossy(path).to_string())
+    }
+}
+
+impl From<toml::de::Error> for SverError {
+    fn from(value: toml::de::Error) -> Self {
+        SverError::InvalidConfig(value.to_string())
+    }
+}
+
+impl From<toml::ser::Error> for SverError {
+    fn from(value: toml::ser::Error) -> Self {
+        SverError::InvalidConfig(value.to_string())
+    }
+}
@@ -0,0 +1,308 @@
+use globset::{GlobBuilder, GlobMatcher};
+
+use crate::{
+    match_samefile_or_include_dir, normalize_setting_path, SverError, SEPARATOR_BYTE, SEPARATOR_STR,
+};
+
+// Excludes of a target.
+// Rules are evaluated in order and the last matched rule wins, like gitignore.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct Excludes {
+    rules: Vec<ExcludeRule>,
+}
+
+impl Excludes {
+    pub(crate) fn new(target_path: &str, excludes: &[String]) -> Result<Self, SverError> {
+        let rules = excludes
+            .iter()
+            .map(|exclude| ExcludeRule::new(target_path, exclude))
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(Self { rules })
+    }
+
+    pub(crate) fn is_excluded(&self, test_path: &[u8]) -> bool {
+        self.excluded_by(test_path).is_some()
+    }
+
+    // Index of the rule which excluded the path, if any.
+    pub(crate) fn excluded_by(&self, test_path: &[u8]) -> Option<usize> {
+        let mut excluded_by = None;
+        for (index, rule) in self.rules.iter().enumerate() {
+            if excluded_by.is_some() == rule.negated && rule.matches(test_path) {
+                excluded_by = if rule.negated { None } else { Some(index) };
+            }
+        }
+        excluded_by
+    }
+
+    pub(crate) fn rules(&self) -> &[ExcludeRule] {
+        &self.rules
+    }
+}
+
+// Characters which make an exclude a glob pattern.
+const GLOB_CHARS: [char; 4] = ['*', '?', '[', '{'];
+// Characters which can be escaped with `\` to be matched literally.
+const ESCAPABLE_CHARS: [char; 7] = ['*', '?', '[', ']', '{', '}', '\\'];
+
+#[derive(Clone, Debug)]
+pub(crate) struct ExcludeRule {
+    // exclude as written in the config file
+    source: String,
+    negated: bool,
+    pattern: Pattern,
+}
+
+#[derive(Clone, Debug)]
+enum Pattern {
+    // same file or files under the directory
+    Path(Vec<u8>),
+    // glob pattern. `*` does not match the separator, `**` does.
+    Glob(GlobMatcher),
+}
+
+impl ExcludeRule {
+    // exclude is a relative path from the target path. `!` prefix negates the rule, and `\!`
+    // prefix is a literal `!`.
+    pub(crate) fn new(target_path: &str, exclude: &str) -> Result<Self, SverError> {
+        let source = exclude.to_string();
+        let (negated, exclude) = match exclude.strip_prefix('!') {
+            Some(exclude) => (true, exclude),
+            None if exclude.starts_with("\\!") => (false, &exclude[1..]),
+            None => (false, exclude),
+        };
+        // `./dir` and `dir/` are the same as `dir`. Otherwise files under the directory would
+        // be compared with `dir//`, and the exclude would match nothing.
+        let normalized = normalize_setting_path(exclude);
+        let exclude = match normalized.as_str() {
+            "" => exclude,
+            normalized => normalized,
+        };
+        // the target path is literal even if it has glob characters, like `lib[1]`
+        let join = |target_path: &str, exclude: &str| {
+            if target_path.is_empty() {
+                exclude.to_string()
+            } else {
+                [target_path, exclude].join(SEPARATOR_STR)
+            }
+        };
+        let pattern = if is_glob(exclude) {
+            let glob = GlobBuilder::new(&join(&escape_glob(target_path), exclude))
+                .literal_separator(true)
+                .backslash_escape(true)
+                .build()
+                .map_err(|e| {
+                    SverError::InvalidConfig(format!(
+                        "invalid exclude pattern. exclude:{exclude}, {e}"
+                    ))
+                })?;
+            Pattern::Glob(glob.compile_matcher())
+        } else {
+            Pattern::Path(join(target_path, &unescape(exclude)).into_bytes())
+        };
+        Ok(Self {
+            source,
+            negated,
+            pattern,
+        })
+    }
+
+    pub(crate) fn source(&self) -> &str {
+        &self.source
+    }
+
+    pub(crate) fn is_negated(&self) -> bool {
+        self.negated
+    }
+
+    pub(crate) fn is_glob(&self) -> bool {
+        matches!(self.pattern, Pattern::Glob(_))
+    }
+
+    pub(crate) fn matches(&self, test_path: &[u8]) -> bool {
+        match &self.pattern {
+            Pattern::Path(path) => match_samefile_or_include_dir(test_path, path),
+            Pattern::Glob(glob) => {
+                // A matched directory excludes all files under it.
+                let dir_ends = test_path
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, b)| SEPARATOR_BYTE.contains(b))
+                    .map(|(i, _)| i);
+                dir_ends
+                    .chain(std::iter::once(test_path.len()))
+                    .any(|end| glob.is_match(String::from_utf8_lossy(&test_path[..end]).as_ref()))
+            }
+        }
+    }
+}
+
+// Whether the exclude has a glob character which is not escaped.
+fn is_glob(exclude: &str) -> bool {
+    let mut chars = exclude.chars();
+    while let Some(c) = chars.next() {
+        if c == '\\' {
+            chars.next();
+        } else if GLOB_CHARS.contains(&c) {
+            return true;
+        }
+    }
+    false
+}
+
+// Remove `\` before the escapable characters.
+fn unescape(path: &str) -> String {
+    let mut unescaped = String::with_capacity(path.len());
+    let mut chars = path.chars().peekable();
+    while let Some(c) = chars.next() {
+        match chars.peek() {
+            Some(next) if c == '\\' && ESCAPABLE_CHARS.contains(next) => {
+                unescaped.push(*next);
+                chars.next();
+            }
+            _ => unescaped.push(c),
+        }
+    }
+    unescaped
+}
+
+// Exclude which matches `exclude` as a literal path, with the glob characters escaped.
+pub(crate) fn escape_exclude(exclude: &str) -> String {
+    let (negation, exclude) = match exclude.strip_prefix('!') {
+        Some(exclude) => ("!", exclude),
+        None => ("", exclude),
+    };
+    format!("{negation}{}", escape_glob(exclude))
+}
+
+// `\` before the escapable characters, so that a glob matches `path` literally.
+fn escape_glob(path: &str) -> String {
+    let mut escaped = String::with_capacity(path.len());
+    for c in path.chars() {
+        if ESCAPABLE_CHARS.contains(&c) {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+// Exclude relative from the repository root of a line of a gitignore style file.
+// Like gitignore, a pattern without separator matches at any depth, and a leading separator
+// anchors the pattern to the root.
+pub(crate) fn ignore_pattern_to_exclude(line: &str) -> String {
+    let (negation, pattern) = match line.strip_prefix('!') {
+        Some(pattern) => ("!", pattern),
+        None => ("", line),
+    };
+    let pattern = match pattern.strip_prefix(SEPARATOR_STR) {
+        Some(anchored) => anchored.to_string(),
+        None if pattern
+            .trim_end_matches(SEPARATOR_STR)
+            .contains(SEPARATOR_STR) =>
+        {
+            pattern.to_string()
+        }
+        None => format!("**/{pattern}"),
+    };
+    format!("{negation}{pattern}")
+}
+
+#[cfg(test)]
+mod exclude_tests {
+    use crate::exclude::{escape_exclude, ignore_pattern_to_exclude, Excludes};
+
+    fn excludes(target_path: &str, excludes: &[&str]) -> Excludes {
+        let excludes = excludes.iter().map(|s| s.to_string()).collect::<Vec<_>>();
+        Excludes::new(target_path, &excludes).unwrap()
+    }
+
+    #[test]
+    fn path_exclude() {
+        let excludes = excludes("service1", &["doc"]);
+        assert!(excludes.is_excluded(b"service1/doc"));
+        assert!(excludes.is_excluded(b"service1/doc/README.md"));
+        assert!(!excludes.is_excluded(b"service1/docs/README.md"));
+        assert!(!excludes.is_excluded(b"service1/src/doc"));
+    }
+
+    #[test]
+    fn sibling_prefix_exclude() {
+        for exclude in ["lib", "lib/"] {
+            let excludes = excludes("", &[exclude]);
+            assert!(excludes.is_excluded(b"lib"));
+            assert!(excludes.is_excluded(b"lib/x.txt"));
+            assert!(!excludes.is_excluded(b"lib-extra/y.txt"));
+            assert!(!excludes.is_excluded(b"lib2"));
+
+            let excludes = self::excludes("service1", &[exclude]);
+            assert!(excludes.is_excluded(b"service1/lib/x.txt"));
+            assert!(!excludes.is_excluded(b"service1/lib-extra/y.txt"));
+        }
+    }
+
+    #[test]
+    fn glob_exclude() {
+        let excludes = excludes("", &["docs/**", "*.log", "build*"]);
+        assert!(excludes.is_excluded(b"docs/api.md"));
+        assert!(excludes.is_excluded(b"docs/sub/api.md"));
+        assert!(excludes.is_excluded(b"error.log"));
+        assert!(!excludes.is_excluded(b"src/error.log"));
+        assert!(excludes.is_excluded(b"build-out/main.o"));
+        assert!(!excludes.is_excluded(b"src/main.rs"));
+    }
+
+    #[test]
+    fn negated_exclude() {
+        let excludes = excludes("lib", &["docs/**", "!docs/api.md"]);
+        assert!(excludes.is_excluded(b"lib/docs/guide.md"));
+        assert!(!excludes.is_excluded(b"lib/docs/api.md"));
+
+        // later rules override earlier rules
+        let excludes = self::excludes("lib", &["!docs/api.md", "docs"]);
+        assert!(excludes.is_excluded(b"lib/docs/api.md"));
+    }
+
+    #[test]
+    fn escaped_exclude() {
+        let excludes = excludes("", &["\\[draft\\].md", "a\\*/b?.txt", "\\!important"]);
+        assert!(excludes.is_excluded(b"[draft].md"));
+        assert!(!excludes.is_excluded(b"d.md"));
+        assert!(excludes.is_excluded(b"a*/b1.txt"));
+        assert!(!excludes.is_excluded(b"ab/b1.txt"));
+        assert!(excludes.is_excluded(b"!important"));
+
+        let excludes = self::excludes("", &[&escape_exclude("[draft]*.md")]);
+        assert!(excludes.is_excluded(b"[draft]*.md"));
+        assert!(!excludes.is_excluded(b"d.md"));
+        assert_eq!(escape_exclude("!a[1].txt"), "!a\\[1\\].txt");
+    }
+
+    #[test]
+    fn glob_target_path_exclude() {
+        let excludes = excludes("lib[1]", &["*.log", "doc", "a\\*.txt"]);
+        assert!(excludes.is_excluded(b"lib[1]/error.log"));
+        assert!(!excludes.is_excluded(b"lib1/error.log"));
+        assert!(excludes.is_excluded(b"lib[1]/doc/README.md"));
+        assert!(excludes.is_excluded(b"lib[1]/a*.txt"));
+        assert!(!excludes.is_excluded(b"lib[1]/ab.txt"));
+    }
+
+    #[test]
+    fn ignore_pattern_exclude() {
+        let patterns = ["*.log", "/dist", "docs/tmp", "target/", "!keep.log"]
+            .iter()
+            .map(|line| ignore_pattern_to_exclude(line))
+            .collect::<Vec<_>>();
+        let excludes = Excludes::new("", &patterns).unwrap();
+        assert!(excludes.is_excluded(b"error.log"));
+        assert!(excludes.is_excluded(b"service1/sub/error.log"));
+        assert!(!excludes.is_excluded(b"service1/keep.log"));
+        assert!(excludes.is_excluded(b"dist/main.js"));
+        assert!(!excludes.is_excluded(b"service1/dist/main.js"));
+        assert!(excludes.is_excluded(b"docs/tmp/a.md"));
+        assert!(!excludes.is_excluded(b"service1/docs/tmp/a.md"));
+        assert!(excludes.is_excluded(b"service1/target/main.o"));
+        assert!(!excludes.is_excluded(b"service1/src/main.rs"));
+    }
+}
@@ -1,9 +1,18 @@
 use anyhow::anyhow;
-use git2::build::RepoBuilder;
+use git2::{build::RepoBuilder, AttrCheckFlags, AttrValue, Oid, Repository, Tree};
 use log::debug;
-use std::{env::temp_dir, path::PathBuf};
+use std::{
+    collections::BTreeSet,
+    env::temp_dir,
+    fmt::Display,
+    fs,
+    path::{Path, PathBuf},
+};
 
-use crate::sver_repository::SverRepository;
+use crate::{filemode::FileMode, sver_repository::SverRepository};
+
+// same as the short version
+const VERSIONED_EXPORT_DIR_DIGITS: usize = 12;
 
 pub fn create_export_dir(export_dir: Option<String>) -> anyhow::Result<PathBuf> {
     let export_dir = if let Some(export_dir) = export_dir {
@@ -22,31 +31,110 @@ pub fn create_export_dir(export_dir: Option<String>) -> anyhow::Result<PathBuf>
     Ok(export_dir)
 }
 
+/// Export directory in the temp directory named after the version of the target, such as
+/// `sver-export-3f1bec06015e`, so exports of the same sources share the directory.
+///
+/// The clone has the files of `HEAD`, so the version is calculated at `HEAD` unless exported
+/// from the index. Otherwise staged changes would name a directory of other contents.
+pub fn versioned_export_dir(repo: &SverRepository, from_index: bool) -> anyhow::Result<PathBuf> {
+    let version = if from_index {
+        repo.calc_version()?
+    } else {
+        repo.calc_version_at("HEAD")?
+    }
+    .version;
+    // cloned submodules have `.git`, so the content differs from the export from the index
+    let suffix = if from_index { "-index" } else { "" };
+    let mut dir = temp_dir();
+    dir.push(format!(
+        "sver-export-{}{suffix}",
+        &version[..VERSIONED_EXPORT_DIR_DIGITS]
+    ));
+    Ok(dir)
+}
+
+/// Run `export` into `export_dir` unless it already exists, and return whether exported.
+///
+/// The sources are exported into a sibling directory and renamed to `export_dir`, so an
+/// existing `export_dir` is always a complete export.
+pub fn export_if_absent(
+    export_dir: &Path,
+    export: impl FnOnce(PathBuf) -> anyhow::Result<()>,
+) -> anyhow::Result<bool> {
+    if export_dir.exists() {
+        debug!("already exported. dir[{}]", export_dir.display());
+        return Ok(false);
+    }
+    let mut work_dir = export_dir.as_os_str().to_owned();
+    work_dir.push(format!(".tmp-{}", uuid::Uuid::now_v7()));
+    let work_dir = PathBuf::from(work_dir);
+    if let Err(e) = export(work_dir.clone()) {
+        let _ = fs::remove_dir_all(&work_dir);
+        return Err(e);
+    }
+    if let Err(e) = fs::rename(&work_dir, export_dir) {
+        fs::remove_dir_all(&work_dir)?;
+        // another export of the same version finished first
+        if export_dir.exists() {
+            return Ok(false);
+        }
+        return Err(anyhow!(
+            "Failed to move export directory. dir[{}], err[{}]",
+            export_dir.display(),
+            e
+        ));
+    }
+    Ok(true)
+}
+
+/// Clone the repository into `export_dir` and remove all files except for the sources.
+///
+/// Submodules in the sources are cloned with their `.git`, even if they are not checked out.
+/// See [`export_from_index`] for a faster way without cloning.
 pub fn export(path: &str, export_dir: PathBuf) -> Result<(), anyhow::Error> {
-    let repo = SverRepository::new(path)?;
-    let sources = repo.list_sources()?;
+    export_with_clone(&SverRepository::new(path)?, export_dir)
+}
 
-    {
+/// [`export`] for a repository opened with a profile and options.
+pub fn export_with_clone(repo: &SverRepository, export_dir: PathBuf) -> Result<(), anyhow::Error> {
+    let sources = repo.sources_iter()?;
+
+    let sources = {
         // If you don't drop exported_repo after cloning, the process will hold
         // the file and you won't be able to delete it in some cases on Windows,
         // so I'm making the scope clear.
         let exported_repo = RepoBuilder::new()
             .clone(repo.work_dir(), &export_dir)
             .map_err(|e| anyhow!("Failed to clone repository. err[{}]", e))?;
+        // Sources still affect the version, but are not exported like `git archive`.
+        let mut exported_sources = Vec::new();
+        for source in sources {
+            let source = source?;
+            if is_export_ignored(&exported_repo, &source, AttrCheckFlags::FILE_THEN_INDEX)? {
+                debug!("export-ignore: {:?}", source);
+            } else {
+                exported_sources.push(source);
+            }
+        }
         let mut submodules = exported_repo.submodules()?;
         for submodule in submodules.iter_mut() {
             if let Some(submodule_path) = submodule.name() {
                 debug!("submodule name: {:?}", submodule.name());
                 // If it is included in sources, clone the submodule
-                if sources.contains(&submodule_path.to_string()) {
+                if exported_sources.contains(&submodule_path.to_string()) {
                     debug!("submodule update: {:?}", submodule_path);
                     submodule.update(true, None)?;
                 }
             }
         }
-    }
+        exported_sources
+    };
 
     // Remove all files and directories except for those in sources from exported_dir and below
+    let sources = sources
+        .iter()
+        .map(|s| (PathBuf::from(s), export_dir.join(s).is_dir()))
+        .collect::<Vec<_>>();
     let walker = walkdir::WalkDir::new(&export_dir);
     walker
         .sort_by(|a, b| a.path().cmp(b.path()))
@@ -54,13 +142,9 @@ pub fn export(path: &str, export_dir: PathBuf) -> Result<(), anyhow::Error> {
         .filter_map(|e| e.ok())
         .filter(|e| e.path() != export_dir)
         .filter(|e| {
-            !sources.iter().map(|s| export_dir.join(s)).any(|s| {
-                if s.is_dir() && e.path().starts_with(&s) {
-                    // If the dependent file is a directory (≒ in the case of git submodule), all directories under it are left.
-                    return true;
-                }
-                s.starts_with(e.path())
-            })
+            e.path()
+                .strip_prefix(&export_dir)
+                .is_ok_and(|path| !is_kept(path, &sources))
         })
         .for_each(|e| {
             if !e.path().exists() {
@@ -76,3 +160,204 @@ pub fn export(path: &str, export_dir: PathBuf) -> Result<(), anyhow::Error> {
 
     Ok(())
 }
+
+// Whether `path` relative from the export directory is left: a source or a directory of
+// sources. If the source is a directory (≒ in the case of git submodule), all files under it
+// are left.
+fn is_kept(path: &Path, sources: &[(PathBuf, bool)]) -> bool {
+    sources
+        .iter()
+        .any(|(source, is_dir)| (*is_dir && path.starts_with(source)) || source.starts_with(path))
+}
+
+/// Files of the repository kept and removed by an export.
+#[derive(Debug, Default, PartialEq)]
+pub struct ExportPlan {
+    /// Sources written into the export directory.
+    pub kept: Vec<String>,
+    /// Files in the index which are not exported, including sources marked `export-ignore`.
+    pub removed: Vec<String>,
+}
+
+impl Display for ExportPlan {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for path in &self.kept {
+            writeln!(f, "keep\t{path}")?;
+        }
+        for path in &self.removed {
+            writeln!(f, "remove\t{path}")?;
+        }
+        Ok(())
+    }
+}
+
+/// List the files an export would keep and remove, without cloning or writing anything.
+/// The files are listed from the index, sorted by the path.
+pub fn plan_export(repo: &SverRepository) -> Result<ExportPlan, anyhow::Error> {
+    let git_repo = Repository::open(repo.work_dir())?;
+    let mut plan = ExportPlan::default();
+    let mut sources = Vec::new();
+    for entry in repo.list_source_entries()? {
+        if is_export_ignored(&git_repo, &entry.path, AttrCheckFlags::INDEX_ONLY)? {
+            continue;
+        }
+        sources.push((PathBuf::from(&entry.path), entry.mode == FileMode::Commit));
+        plan.kept.push(entry.path);
+    }
+    let index = git_repo.index()?;
+    let mut removed = BTreeSet::new();
+    for entry in index.iter() {
+        let path = String::from_utf8(entry.path).map_err(|e| {
+            anyhow!(
+                "Path is not valid UTF-8. path[{}]",
+                String::from_utf8_lossy(e.as_bytes())
+            )
+        })?;
+        if !is_kept(Path::new(&path), &sources) {
+            removed.insert(path);
+        }
+    }
+    plan.removed = removed.into_iter().collect();
+    Ok(plan)
+}
+
+/// Write only the sources into `export_dir`, reading their content from the index.
+///
+/// Only the blobs of the sources are read, so this is much faster than [`export`] for large
+/// repositories, and the files are exactly those the version is calculated from.
+/// Untracked sources included by `include_untracked` are copied from the working tree.
+/// Submodules are written from the tree of their pinned commit in the checked out submodule,
+/// without `.git`. Use [`export`] for submodules which are not checked out.
+pub fn export_from_index(repo: &SverRepository, export_dir: PathBuf) -> Result<(), anyhow::Error> {
+    let git_repo = Repository::open(repo.work_dir())?;
+    let odb = git_repo.odb()?;
+    for entry in repo.list_source_entries()? {
+        if is_export_ignored(&git_repo, &entry.path, AttrCheckFlags::INDEX_ONLY)? {
+            debug!("export-ignore: {:?}", entry.path);
+            continue;
+        }
+        let file_path = export_dir.join(&entry.path);
+        if let Some(parent) = file_path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        if entry.mode == FileMode::Commit {
+            let submodule_path = Path::new(repo.work_dir()).join(&entry.path);
+            let submodule_repo = Repository::open(&submodule_path).map_err(|e| {
+                anyhow!(
+                    "Submodule is not checked out. Export with clone instead. path[{}], err[{}]",
+                    entry.path,
+                    e
+                )
+            })?;
+            let tree = submodule_repo.find_commit(entry.oid)?.tree()?;
+            write_tree(&submodule_repo, &tree, &file_path)?;
+        } else if odb.exists(entry.oid) {
+            write_file(&git_repo, entry.oid, entry.mode, &file_path)?;
+        } else {
+            // untracked files are not in the object database
+            copy_untracked(&Path::new(repo.work_dir()).join(&entry.path), &file_path)?;
+        }
+    }
+    Ok(())
+}
+
+fn copy_untracked(source: &Path, file_path: &Path) -> Result<(), anyhow::Error> {
+    if fs::symlink_metadata(source)?.is_symlink() {
+        debug!("copy symlink[{}]", file_path.display());
+        let link_target = fs::read_link(source)?;
+        write_symlink(link_target.as_os_str().as_encoded_bytes(), file_path)?;
+    } else {
+        debug!("copy file[{}]", file_path.display());
+        // the permissions are copied with the content
+        fs::copy(source, file_path)?;
+    }
+    Ok(())
+}
+
+fn write_tree(repo: &Repository, tree: &Tree, dir: &Path) -> Result<(), anyhow::Error> {
+    fs::create_dir_all(dir)?;
+    for entry in tree.iter() {
+        let name = entry
+            .name()
+            .ok_or_else(|| anyhow!("Path is not valid UTF-8. dir[{}]", dir.display()))?;
+        let file_path = dir.join(name);
+        match FileMode::from(entry.filemode() as u32) {
+            FileMode::Tree => write_tree(repo, &repo.find_tree(entry.id())?, &file_path)?,
+            // nested submodules are left empty as `git clone` without `--recursive`
+            FileMode::Commit => fs::create_dir_all(&file_path)?,
+            mode => write_file(repo, entry.id(), mode, &file_path)?,
+        }
+    }
+    Ok(())
+}
+
+fn write_file(
+    repo: &Repository,
+    oid: Oid,
+    mode: FileMode,
+    file_path: &Path,
+) -> Result<(), anyhow::Error> {
+    match mode {
+        FileMode::Blob | FileMode::BlobExecutable => {
+            debug!("write file[{}]", file_path.display());
+            fs::write(file_path, repo.find_blob(oid)?.content())?;
+            if mode == FileMode::BlobExecutable {
+                set_executable(file_path)?;
+            }
+        }
+        FileMode::Link => {
+            debug!("write symlink[{}]", file_path.display());
+            write_symlink(repo.find_blob(oid)?.content(), file_path)?;
+        }
+        _ => debug!(
+            "unsupported mode. skipped. path[{}], mode[{:?}]",
+            file_path.display(),
+            mode
+        ),
+    }
+    Ok(())
+}
+
+#[cfg(unix)]
+fn set_executable(file_path: &Path) -> std::io::Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+    let mut permissions = fs::metadata(file_path)?.permissions();
+    permissions.set_mode(permissions.mode() | 0o111);
+    fs::set_permissions(file_path, permissions)
+}
+
+#[cfg(not(unix))]
+fn set_executable(_file_path: &Path) -> std::io::Result<()> {
+    Ok(())
+}
+
+#[cfg(unix)]
+fn write_symlink(link_target: &[u8], file_path: &Path) -> std::io::Result<()> {
+    use std::os::unix::ffi::OsStrExt;
+    std::os::unix::fs::symlink(std::ffi::OsStr::from_bytes(link_target), file_path)
+}
+
+// the link target is written as a plain file as git does with `core.symlinks=false`
+#[cfg(not(unix))]
+fn write_symlink(link_target: &[u8], file_path: &Path) -> std::io::Result<()> {
+    fs::write(file_path, link_target)
+}
+
+// Whether the source or one of its parent directories has `export-ignore` attribute.
+// `git archive` skips a directory marked `export-ignore` with all files in it.
+fn is_export_ignored(
+    repo: &Repository,
+    source: &str,
+    flags: AttrCheckFlags,
+) -> Result<bool, git2::Error> {
+    for path in Path::new(source)
+        .ancestors()
+        .filter(|path| !path.as_os_str().is_empty())
+    {
+        let value = repo.get_attr(path, "export-ignore", flags)?;
+        if matches!(AttrValue::from_string(value), AttrValue::True) {
+            return Ok(true);
+        }
+    }
+    Ok(false)
+}
@@ -1,7 +1,10 @@
 use anyhow::{anyhow, Context};
-use inotify::{Inotify, WatchDescriptor};
+use git2::Repository;
+use inotify::{EventMask, Inotify, WatchDescriptor, WatchMask, Watches};
 use log::debug;
-use std::collections::{BTreeMap, BTreeSet};
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::io::ErrorKind;
 use std::path::Path;
 use std::process::Stdio;
 use std::sync::atomic::{AtomicBool, Ordering};
@@ -11,21 +14,150 @@ use std::time::Duration;
 
 use crate::sver_repository::SverRepository;
 
+/// Class of file events regarded as an access to the file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InspectEvent {
+    /// The file was read.
+    Access,
+    /// The file was opened, even if it was not read, such as mmap.
+    Open,
+    /// The file was written.
+    Modify,
+}
+
+impl InspectEvent {
+    fn watch_mask(self) -> WatchMask {
+        match self {
+            InspectEvent::Access => WatchMask::ACCESS,
+            InspectEvent::Open => WatchMask::OPEN,
+            InspectEvent::Modify => WatchMask::MODIFY,
+        }
+    }
+
+    fn event_mask(self) -> EventMask {
+        match self {
+            InspectEvent::Access => EventMask::ACCESS,
+            InspectEvent::Open => EventMask::OPEN,
+            InspectEvent::Modify => EventMask::MODIFY,
+        }
+    }
+}
+
+/// Files accessed by the command.
+#[derive(Debug, Default, Serialize)]
+pub struct InspectResult {
+    /// Sorted by the path.
+    pub files: Vec<InspectedFile>,
+}
+
+impl InspectResult {
+    /// Sorted paths of the accessed files.
+    pub fn paths(&self) -> Vec<String> {
+        self.files.iter().map(|file| file.path.clone()).collect()
+    }
+}
+
+#[derive(Debug, PartialEq, Serialize)]
+pub struct InspectedFile {
+    /// Relative path from the repository root.
+    pub path: String,
+    /// Whether the file is in the index.
+    pub tracked: bool,
+    /// Watched directory which has the file. Relative path from the repository root, and `""`
+    /// for the root.
+    pub directory: String,
+    /// Number of the events on the file.
+    pub access_count: usize,
+}
+
+/// List files read by the command.
 pub fn inspect(
     path: &str,
     command: String,
     args: Vec<String>,
     output: Stdio,
-) -> Result<Vec<String>, anyhow::Error> {
+) -> Result<InspectResult, anyhow::Error> {
+    inspect_with_events(path, command, args, output, &[InspectEvent::Access])
+}
+
+/// List files the command caused any of `events` on.
+/// A file is listed once even if multiple events happened, and the events are counted.
+pub fn inspect_with_events(
+    path: &str,
+    command: String,
+    args: Vec<String>,
+    output: Stdio,
+    events: &[InspectEvent],
+) -> Result<InspectResult, anyhow::Error> {
+    inspect_within(path, command, args, output, events, None)
+}
+
+/// Same as [`inspect_with_events`], but only `path` and `include_dirs` are watched with their
+/// subdirectories. `include_dirs` are relative from `path`.
+/// Accesses to other files are not listed, and far fewer inotify watches are used in a large
+/// repository.
+pub fn inspect_subtree(
+    path: &str,
+    command: String,
+    args: Vec<String>,
+    output: Stdio,
+    events: &[InspectEvent],
+    include_dirs: &[String],
+) -> Result<InspectResult, anyhow::Error> {
+    let include_dirs = std::iter::once(path.to_string())
+        .chain(
+            include_dirs
+                .iter()
+                .map(|dir| Path::new(path).join(dir).to_string_lossy().to_string()),
+        )
+        .collect::<Vec<_>>();
+    inspect_within(path, command, args, output, events, Some(&include_dirs))
+}
+
+// Directories in `scope` and their subdirectories are watched, or all directories without it.
+fn inspect_within(
+    path: &str,
+    command: String,
+    args: Vec<String>,
+    output: Stdio,
+    events: &[InspectEvent],
+    scope: Option<&[String]>,
+) -> Result<InspectResult, anyhow::Error> {
+    if events.is_empty() {
+        return Err(anyhow!("At least one event is required"));
+    }
     let repo = SverRepository::new(path).context("repository not found")?;
+    let scope = scope
+        .map(|dirs| {
+            dirs.iter()
+                .map(|dir| relative_dir(repo.work_dir(), dir))
+                .collect::<anyhow::Result<Vec<_>>>()
+        })
+        .transpose()?;
+    let in_scope = |dir: &str| {
+        scope.as_ref().is_none_or(|scope| {
+            scope.iter().any(|root| {
+                root.is_empty()
+                    || dir == root
+                    || dir
+                        .strip_prefix(root.as_str())
+                        .is_some_and(|rest| rest.starts_with('/'))
+            })
+        })
+    };
 
-    let subdirs = list_subdirectories_rel(repo.work_dir());
+    let subdirs = list_subdirectories_rel(repo.work_dir())
+        .into_iter()
+        .filter(|dir| in_scope(dir))
+        .collect::<Vec<_>>();
     debug!("subdirs:{:?}", subdirs);
     let mut git_repo_dirs = repo.contain_directories(subdirs)?;
-    git_repo_dirs.push(repo.work_dir().to_string());
+    if in_scope("") {
+        git_repo_dirs.push(repo.work_dir().to_string());
+    }
     debug!("contain_dirs:{:?}", git_repo_dirs);
 
-    let thread = InotifyThread::new(&git_repo_dirs)?;
+    let thread = InotifyThread::new(&git_repo_dirs, events)?;
 
     std::process::Command::new(command)
         .args(args)
@@ -35,8 +167,38 @@ pub fn inspect(
         .status()
         .map_err(|e| anyhow!("Failed to spawn command: {}", e))?;
 
-    let result = thread.terminate(repo.work_dir());
-    Ok(result)
+    let accessed_files = thread.terminate()?;
+    let index = Repository::open(repo.work_dir())?.index()?;
+    let relative = |path: &str| path.trim_start_matches(repo.work_dir()).to_owned();
+    let files = accessed_files
+        .into_iter()
+        .map(|(path, (directory, access_count))| {
+            let path = relative(&path);
+            InspectedFile {
+                tracked: index.get_path(Path::new(&path), 0).is_some(),
+                path,
+                directory: relative(&directory),
+                access_count,
+            }
+        })
+        .collect();
+    Ok(InspectResult { files })
+}
+
+// Relative path of the directory from the repository root, and `""` for the root.
+fn relative_dir(work_dir: &str, dir: &str) -> anyhow::Result<String> {
+    let work_dir = Path::new(work_dir).canonicalize()?;
+    let dir = Path::new(dir)
+        .canonicalize()
+        .with_context(|| format!("directory is not found. path:{dir}"))?;
+    match dir.strip_prefix(&work_dir) {
+        Ok(relative) => Ok(relative.to_string_lossy().to_string()),
+        Err(_) => Err(anyhow!(
+            "directory is outside the repository. path:{}, repository:{}",
+            dir.display(),
+            work_dir.display()
+        )),
+    }
 }
 
 fn list_subdirectories_rel<P: AsRef<Path>>(path: P) -> Vec<String> {
@@ -65,82 +227,246 @@ fn list_subdirectories<P: AsRef<Path>>(path: P) -> Vec<String> {
     subdirectories
 }
 
+// Large enough for any single event (16 bytes and a name up to 256 bytes) and a few hundreds
+// of events. Pending events are read until drained on each poll.
+const EVENT_BUFFER_SIZE: usize = 16 * 1024;
+
+#[derive(Default)]
+struct AccessedFiles {
+    // (watched directory, number of events) of each file
+    files: BTreeMap<String, (String, usize)>,
+    // the kernel dropped events, so `files` may be incomplete
+    overflowed: bool,
+}
+
 struct InotifyThread {
-    thread: JoinHandle<BTreeSet<String>>,
+    thread: JoinHandle<std::io::Result<AccessedFiles>>,
     thread_terminator: Arc<AtomicBool>,
 }
 
 impl InotifyThread {
-    fn new(dirs: &[String]) -> anyhow::Result<Self> {
-        let thread_ready = Arc::new(AtomicBool::new(false));
+    fn new(dirs: &[String], events: &[InspectEvent]) -> anyhow::Result<Self> {
         let thread_terminator = Arc::new(AtomicBool::new(false));
 
+        // directories created during the command are also watched
+        let watch_mask = events
+            .iter()
+            .fold(WatchMask::CREATE | WatchMask::MOVED_TO, |mask, event| {
+                mask | event.watch_mask()
+            });
+        let event_mask = events
+            .iter()
+            .fold(EventMask::empty(), |mask, event| mask | event.event_mask());
+
+        // watches are added before the command starts, and its events are queued until read
+        let mut inotify = Inotify::init().context("failed to initialize inotify")?;
+        let mut watches = inotify.watches();
+        let mut wd_path_map = BTreeMap::new();
+        for dir in dirs {
+            let wd = watches.add(dir, watch_mask).map_err(|e| {
+                if e.kind() == ErrorKind::StorageFull {
+                    anyhow!(
+                        "inotify watch limit reached while watching {} directories. Raise fs.inotify.max_user_watches or watch fewer directories with --subtree. path:{dir}",
+                        dirs.len()
+                    )
+                } else {
+                    anyhow!("failed to watch directory. path:{dir}, error:{e}")
+                }
+            })?;
+            wd_path_map.insert(wd, dir.clone());
+        }
+
         let thread = {
-            let dirs = dirs.to_owned();
-            let thread_ready = thread_ready.clone();
             let thread_terminator = thread_terminator.clone();
             std::thread::spawn(move || {
-                let mut inotify = inotify::Inotify::init().unwrap();
-                let mut wd_path_map = BTreeMap::new();
-                let mut accessed_files = BTreeSet::new();
-
-                let mut watches = inotify.watches();
-                dirs.iter().for_each(|d| {
-                    let wd = watches.add(d, inotify::WatchMask::ACCESS).unwrap();
-                    wd_path_map.insert(wd, d.clone());
-                });
-                thread_ready.store(true, Ordering::Relaxed);
+                let mut accessed_files = AccessedFiles::default();
+                let mut buffer = vec![0; EVENT_BUFFER_SIZE];
 
                 loop {
                     sleep(Duration::from_millis(1));
-                    Self::read_events(&mut inotify, &mut accessed_files, &wd_path_map);
-                    if thread_terminator.load(Ordering::Relaxed) {
-                        inotify.close().unwrap();
+                    // events queued before the termination are still read
+                    let terminating = thread_terminator.load(Ordering::Relaxed);
+                    Self::read_events(
+                        &mut inotify,
+                        &mut buffer,
+                        &mut watches,
+                        &mut accessed_files,
+                        &mut wd_path_map,
+                        watch_mask,
+                        event_mask,
+                    )?;
+                    if terminating {
+                        inotify.close()?;
                         break;
                     }
                 }
-                accessed_files
+                Ok(accessed_files)
             })
         };
-        while !thread_ready.load(Ordering::Relaxed) {
-            sleep(Duration::from_millis(1));
-        }
         Ok(Self {
             thread,
             thread_terminator,
         })
     }
 
-    fn terminate(self, work_dir: &str) -> Vec<String> {
+    fn terminate(self) -> anyhow::Result<BTreeMap<String, (String, usize)>> {
         self.thread_terminator.store(true, Ordering::Relaxed);
-        let result = self.thread.join().unwrap();
-        let mut result = result
-            .iter()
-            .map(|f| f.trim_start_matches(work_dir).to_owned())
-            .collect::<Vec<String>>();
-        result.sort();
-        result
+        let result = self
+            .thread
+            .join()
+            .map_err(|_| anyhow!("inotify thread panicked"))?
+            .context("failed to read inotify events")?;
+        if result.overflowed {
+            return Err(anyhow!(
+                "inotify event queue overflowed, so the accessed files may be incomplete. Raise fs.inotify.max_queued_events and try again"
+            ));
+        }
+        Ok(result.files)
     }
 
+    // Read all pending events.
     fn read_events(
         inotify: &mut Inotify,
-        accessed_files: &mut BTreeSet<String>,
-        wd_path_map: &BTreeMap<WatchDescriptor, String>,
-    ) {
-        let mut buffer = [0; 2048];
-        if let Ok(events) = inotify.read_events(&mut buffer) {
+        buffer: &mut [u8],
+        watches: &mut Watches,
+        accessed_files: &mut AccessedFiles,
+        wd_path_map: &mut BTreeMap<WatchDescriptor, String>,
+        watch_mask: WatchMask,
+        event_mask: EventMask,
+    ) -> std::io::Result<()> {
+        loop {
+            let events = match inotify.read_events(buffer) {
+                Ok(events) => events,
+                // no events are pending
+                Err(e) if e.kind() == ErrorKind::WouldBlock => return Ok(()),
+                Err(e) => return Err(e),
+            };
+            let mut drained = true;
             for event in events {
-                if let Some(name) = event.name {
-                    if event.mask.contains(inotify::EventMask::ACCESS)
-                        && !event.mask.contains(inotify::EventMask::ISDIR)
+                drained = false;
+                if event.mask.contains(EventMask::Q_OVERFLOW) {
+                    debug!("inotify event queue overflowed");
+                    accessed_files.overflowed = true;
+                    continue;
+                }
+                let Some(name) = event.name else {
+                    continue;
+                };
+                let Some(dir) = wd_path_map.get(&event.wd) else {
+                    continue;
+                };
+                let path = Path::new(dir).join(name.to_string_lossy().to_string());
+                let path = path.to_string_lossy().to_string();
+                if event.mask.contains(EventMask::ISDIR) {
+                    if event
+                        .mask
+                        .intersects(EventMask::CREATE | EventMask::MOVED_TO)
                     {
-                        let wd = event.wd;
-                        let path = wd_path_map.get(&wd).unwrap();
-                        let path = Path::new(path).join(name.to_string_lossy().to_string());
-                        accessed_files.insert(path.to_string_lossy().to_string());
+                        // subdirectories may be created before the directory is watched
+                        let mut dirs = list_subdirectories(&path);
+                        dirs.insert(0, path);
+                        for dir in dirs {
+                            Self::add_watch(watches, wd_path_map, dir, watch_mask);
+                        }
                     }
+                } else if event.mask.intersects(event_mask) {
+                    let (_, count) = accessed_files
+                        .files
+                        .entry(path)
+                        .or_insert_with(|| (dir.clone(), 0));
+                    *count += 1;
                 }
             }
+            if drained {
+                return Ok(());
+            }
         }
     }
+
+    fn add_watch(
+        watches: &mut Watches,
+        wd_path_map: &mut BTreeMap<WatchDescriptor, String>,
+        dir: String,
+        watch_mask: WatchMask,
+    ) {
+        match watches.add(&dir, watch_mask) {
+            Ok(wd) => {
+                debug!("watch created dir:{dir}");
+                wd_path_map.insert(wd, dir);
+            }
+            // the directory may be removed already
+            Err(e) => debug!("failed to watch created dir:{dir}, error:{e}"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod inspect_tests {
+    use super::{AccessedFiles, InotifyThread, InspectEvent, EVENT_BUFFER_SIZE};
+    use inotify::{EventMask, Inotify, WatchMask};
+    use std::collections::BTreeMap;
+
+    #[test]
+    fn watch_failure_test() {
+        let result = InotifyThread::new(&["/sver/not/exist".to_string()], &[InspectEvent::Access]);
+
+        match result {
+            Err(e) => assert!(e.to_string().contains("path:/sver/not/exist")),
+            Ok(_) => unreachable!("this line will not be execute"),
+        }
+    }
+
+    #[test]
+    fn terminate_test() {
+        let dir = std::env::temp_dir();
+        let thread = InotifyThread::new(
+            &[dir.to_string_lossy().to_string()],
+            &[InspectEvent::Access],
+        )
+        .unwrap();
+
+        assert!(thread.terminate().is_ok());
+    }
+
+    #[test]
+    fn created_directory_test() {
+        let dir = std::env::temp_dir().join(format!("sver-{}", uuid::Uuid::now_v7()));
+        std::fs::create_dir(&dir).unwrap();
+        let dir = dir.to_string_lossy().to_string();
+        let watch_mask = WatchMask::CREATE | WatchMask::MOVED_TO | WatchMask::ACCESS;
+        let mut inotify = Inotify::init().unwrap();
+        let mut watches = inotify.watches();
+        let mut wd_path_map = BTreeMap::new();
+        wd_path_map.insert(watches.add(&dir, watch_mask).unwrap(), dir.clone());
+        let mut buffer = vec![0; EVENT_BUFFER_SIZE];
+        let mut accessed_files = AccessedFiles::default();
+        let mut read_events = |accessed_files: &mut AccessedFiles| {
+            InotifyThread::read_events(
+                &mut inotify,
+                &mut buffer,
+                &mut watches,
+                accessed_files,
+                &mut wd_path_map,
+                watch_mask,
+                EventMask::ACCESS,
+            )
+            .unwrap()
+        };
+
+        // the created directory is watched once its event is read
+        std::fs::create_dir_all(format!("{dir}/new/nested")).unwrap();
+        read_events(&mut accessed_files);
+        std::fs::write(format!("{dir}/new/nested/test.txt"), "hello").unwrap();
+        std::fs::read(format!("{dir}/new/nested/test.txt")).unwrap();
+        read_events(&mut accessed_files);
+
+        assert_eq!(
+            accessed_files.files.keys().collect::<Vec<_>>(),
+            vec![&format!("{dir}/new/nested/test.txt")]
+        );
+        assert_eq!(
+            accessed_files.files[&format!("{dir}/new/nested/test.txt")],
+            (format!("{dir}/new/nested"), 1)
+        );
+    }
 }
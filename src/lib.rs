@@ -1,63 +1,214 @@
+pub mod attestation;
+mod blob_cache;
+pub mod doctor;
+mod error;
+mod exclude;
+#[cfg(feature = "export")]
 pub mod export;
 pub mod filemode;
-#[cfg(target_os = "linux")]
+#[cfg(all(target_os = "linux", feature = "inspect"))]
 pub mod inspect;
+pub mod lock;
 pub mod sver_config;
 pub mod sver_repository;
+#[cfg(feature = "watch")]
+pub mod watch;
 
 use std::{
     collections::HashMap,
-    path::{Path, PathBuf},
+    ffi::OsStr,
+    path::{Component, Path, PathBuf},
 };
 
-use self::filemode::FileMode;
-use anyhow::{anyhow, Context};
+use self::{exclude::Excludes, filemode::FileMode};
 use git2::{Oid, Repository};
+use serde::{Deserialize, Serialize};
 use sver_config::CalculationTarget;
+use sver_repository::InclusionReason;
 
+pub use self::error::SverError;
+
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
 pub struct Version {
     pub repository_root: String,
     pub path: String,
+    pub profile: String,
     pub version: String,
 }
 
-fn relative_path(repo: &Repository, path: &Path) -> anyhow::Result<PathBuf> {
+fn relative_path(repo: &Repository, path: &Path) -> Result<PathBuf, SverError> {
     let repo_path = repo
         .workdir()
         .and_then(|p| p.canonicalize().ok())
-        .with_context(|| "bare repository is not supported")?;
-    let current_path = path.canonicalize()?;
-    let result = current_path.strip_prefix(repo_path)?.to_path_buf();
+        .ok_or(SverError::BareRepositoryUnsupported)?;
+    let (existing_path, missing_path) = split_missing_path(path)?;
+    let current_path = existing_path.join(&missing_path);
+    let result = match current_path.strip_prefix(&repo_path) {
+        Ok(result) => result.to_path_buf(),
+        Err(_) => {
+            return Err(SverError::OutsideRepository {
+                path: current_path.display().to_string(),
+                repository: repo_path.display().to_string(),
+            })
+        }
+    };
+    if !missing_path.as_os_str().is_empty() && !is_in_index(repo, &result)? {
+        // report the same error as the path on disk
+        path.canonicalize()?;
+    }
     Ok(result)
 }
 
+// Split the path into its nearest existing ancestor (canonicalized) and the rest which does not
+// exist on disk. In sparse checkouts, directories outside of the checkout exist only in the index.
+fn split_missing_path(path: &Path) -> Result<(PathBuf, PathBuf), SverError> {
+    let error = match path.canonicalize() {
+        Ok(path) => return Ok((path, PathBuf::new())),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => e,
+        Err(e) => return Err(e.into()),
+    };
+    let absolute_path = std::path::absolute(path)?;
+    for existing_path in absolute_path.ancestors().skip(1) {
+        let Ok(canonical_path) = existing_path.canonicalize() else {
+            continue;
+        };
+        let missing_path = absolute_path
+            .strip_prefix(existing_path)
+            .expect("an ancestor is a prefix of the path");
+        // `..` can not be resolved without the directory on disk
+        if missing_path
+            .components()
+            .any(|c| !matches!(c, Component::Normal(_) | Component::CurDir))
+        {
+            break;
+        }
+        return Ok((canonical_path, missing_path.to_path_buf()));
+    }
+    Err(error.into())
+}
+
+// Whether the file or the directory `path` (relative from the repository root) is in the index.
+fn is_in_index(repo: &Repository, path: &Path) -> Result<bool, SverError> {
+    let path = path
+        .to_str()
+        .ok_or_else(|| SverError::non_utf8_path(path.as_os_str().as_encoded_bytes()))?
+        .as_bytes();
+    Ok(repo
+        .index()?
+        .iter()
+        .any(|entry| match_samefile_or_include_dir(&entry.path, path)))
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
 struct OidAndMode {
     oid: Oid,
     mode: FileMode,
 }
 
+// Remove cosmetic differences of text content.
+fn normalize_text(content: &[u8], normalize_eol: bool, strip_trailing_whitespace: bool) -> Vec<u8> {
+    let mut result = Vec::with_capacity(content.len());
+    let mut lines = content.split(|b| *b == b'\n').peekable();
+    while let Some(line) = lines.next() {
+        let (line, has_cr) = match line.strip_suffix(b"\r") {
+            Some(line) => (line, true),
+            None => (line, false),
+        };
+        if strip_trailing_whitespace {
+            result.extend_from_slice(line.trim_ascii_end());
+        } else {
+            result.extend_from_slice(line);
+        }
+        if has_cr && !normalize_eol {
+            result.push(b'\r');
+        }
+        if lines.peek().is_some() {
+            result.push(b'\n');
+        }
+    }
+    result
+}
+
 const SEPARATOR_STR: &str = "/";
 const SEPARATOR_BYTE: &[u8] = SEPARATOR_STR.as_bytes();
 
-fn containable(test_path: &[u8], path_set: &HashMap<CalculationTarget, Vec<String>>) -> bool {
-    path_set.iter().any(|(include, excludes)| {
-        let include_file = match_samefile_or_include_dir(test_path, include.path.as_bytes());
-        let exclude_file = excludes.iter().any(|exclude| {
-            if include.path.is_empty() {
-                match_samefile_or_include_dir(test_path, exclude.as_bytes())
-            } else {
-                match_samefile_or_include_dir(
-                    test_path,
-                    [include.path.as_bytes(), SEPARATOR_BYTE, exclude.as_bytes()]
-                        .concat()
-                        .as_slice(),
-                )
-            }
-        });
-        include_file && !exclude_file
+// Settings of a resolved calculation target.
+#[derive(Clone, Debug, Default)]
+struct TargetSetting {
+    excludes: Excludes,
+    exclude_modes: Vec<FileMode>,
+    env: Vec<String>,
+    // how the target was reached first
+    reason: InclusionReason,
+    // reached only by non-transitive dependencies, so its dependencies are not collected
+    dependencies_skipped: bool,
+}
+
+// Canonical form of a path written in config files: relative without `.` segments, empty
+// segments and a trailing separator. `service1`, `./service1`, `service1/` and `service1//`
+// are the same, and `.` is `""`.
+fn normalize_setting_path(path: &str) -> String {
+    path.split(SEPARATOR_STR)
+        .filter(|segment| !segment.is_empty() && *segment != ".")
+        .collect::<Vec<_>>()
+        .join(SEPARATOR_STR)
+}
+
+// Dependencies starting with `..` are relative from the target path, and the others are
+// relative from the repository root. `None` when the dependency escapes the repository root.
+// `dependency` is in the canonical form of `normalize_setting_path`.
+fn resolve_dependency_path(target_path: &str, dependency: &str) -> Option<String> {
+    let mut segments = Vec::new();
+    if dependency.split(SEPARATOR_STR).next() == Some("..") {
+        segments.extend(target_path.split(SEPARATOR_STR).filter(|s| !s.is_empty()));
+    }
+    for segment in dependency.split(SEPARATOR_STR).filter(|s| !s.is_empty()) {
+        if segment == ".." {
+            segments.pop()?;
+        } else {
+            segments.push(segment);
+        }
+    }
+    Some(segments.join(SEPARATOR_STR))
+}
+
+// Paths of the working tree use `/` as the separator like the index. A backslash is a
+// separator only on Windows, elsewhere it is a legal byte of a file name.
+#[cfg(windows)]
+fn normalize_working_tree_path(mut path: Vec<u8>) -> Vec<u8> {
+    path.iter_mut()
+        .filter(|byte| **byte == b'\\')
+        .for_each(|byte| *byte = SEPARATOR_BYTE[0]);
+    path
+}
+
+#[cfg(not(windows))]
+fn normalize_working_tree_path(path: Vec<u8>) -> Vec<u8> {
+    path
+}
+
+fn containable(test_path: &[u8], path_set: &HashMap<CalculationTarget, TargetSetting>) -> bool {
+    path_set
+        .iter()
+        .any(|(include, setting)| is_source_of(test_path, include, setting))
+}
+
+// Same as `containable`, and files of the modes excluded by a target are not its sources.
+fn containable_with_mode(
+    test_path: &[u8],
+    mode: FileMode,
+    path_set: &HashMap<CalculationTarget, TargetSetting>,
+) -> bool {
+    path_set.iter().any(|(include, setting)| {
+        is_source_of(test_path, include, setting) && !setting.exclude_modes.contains(&mode)
     })
 }
 
+fn is_source_of(test_path: &[u8], target: &CalculationTarget, setting: &TargetSetting) -> bool {
+    match_samefile_or_include_dir(test_path, target.path.as_bytes())
+        && !setting.excludes.is_excluded(test_path)
+}
+
 fn match_samefile_or_include_dir(test_path: &[u8], path: &[u8]) -> bool {
     is_samefile(test_path, path) || is_contain_path(test_path, path)
 }
@@ -70,11 +221,134 @@ fn is_contain_path(test_path: &[u8], path: &[u8]) -> bool {
     path.is_empty() || test_path.starts_with([path, SEPARATOR_BYTE].concat().as_slice())
 }
 
-fn find_repository(from_path: &Path) -> anyhow::Result<Repository> {
-    for target_path in from_path.canonicalize()?.ancestors() {
+// Search the repository from `from_path` to its ancestors like git. The search does not enter
+// the directories of `GIT_CEILING_DIRECTORIES`, and does not cross a filesystem boundary unless
+// `GIT_DISCOVERY_ACROSS_FILESYSTEM` is set, so a repository far above the project (e.g. mounted
+// at `/` in a container) is not found by accident.
+fn find_repository(from_path: &Path) -> Result<Repository, SverError> {
+    let ceilings = ceiling_directories(std::env::var_os("GIT_CEILING_DIRECTORIES").as_deref());
+    let across_filesystem = std::env::var("GIT_DISCOVERY_ACROSS_FILESYSTEM")
+        .is_ok_and(|value| matches!(value.to_lowercase().as_str(), "1" | "true" | "yes" | "on"));
+    find_repository_within(from_path, &ceilings, across_filesystem)
+}
+
+// Directories of the `GIT_CEILING_DIRECTORIES` value separated like `PATH`. Empty entries and
+// directories which do not exist are ignored.
+fn ceiling_directories(value: Option<&OsStr>) -> Vec<PathBuf> {
+    value
+        .map(|value| {
+            std::env::split_paths(value)
+                .filter(|path| !path.as_os_str().is_empty())
+                .filter_map(|path| path.canonicalize().ok())
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+fn find_repository_within(
+    from_path: &Path,
+    ceilings: &[PathBuf],
+    across_filesystem: bool,
+) -> Result<Repository, SverError> {
+    // a path outside of a sparse checkout is searched from its nearest existing ancestor
+    let (from_path, _) = split_missing_path(from_path)?;
+    let device = device_of(&from_path);
+    for target_path in from_path.ancestors() {
         if let Ok(repo) = Repository::open(target_path) {
             return Ok(repo);
         }
+        let Some(parent) = target_path.parent() else {
+            break;
+        };
+        let crosses_filesystem = !across_filesystem && device_of(parent) != device;
+        if crosses_filesystem || ceilings.iter().any(|ceiling| ceiling == parent) {
+            return Err(SverError::RepositoryNotFoundBelow {
+                path: from_path.display().to_string(),
+                boundary: target_path.display().to_string(),
+            });
+        }
+    }
+    Err(SverError::RepositoryNotFound)
+}
+
+// Device of the filesystem the path is on. `None` where it is unknown, which never differs.
+#[cfg(unix)]
+fn device_of(path: &Path) -> Option<u64> {
+    use std::os::unix::fs::MetadataExt;
+    std::fs::metadata(path).ok().map(|metadata| metadata.dev())
+}
+
+#[cfg(not(unix))]
+fn device_of(_path: &Path) -> Option<u64> {
+    None
+}
+
+// Resolve the target of a symbolic link relative to the directory of the link.
+// Returns None if the target points outside of the repository.
+fn resolve_link_target(link_path: &str, link_target: &str) -> Option<String> {
+    let mut buf = PathBuf::from(link_path);
+    buf.pop();
+    for component in Path::new(link_target).components() {
+        match component {
+            Component::ParentDir => {
+                if !buf.pop() {
+                    return None;
+                }
+            }
+            Component::Normal(path) => buf.push(path),
+            Component::CurDir => {}
+            Component::RootDir | Component::Prefix(_) => return None,
+        }
+    }
+    Some(
+        buf.iter()
+            .flat_map(|os| os.to_str())
+            .collect::<Vec<_>>()
+            .join(SEPARATOR_STR),
+    )
+}
+
+#[cfg(test)]
+mod find_repository_tests {
+    use std::{
+        env::{join_paths, temp_dir},
+        fs::create_dir_all,
+    };
+
+    use git2::Repository;
+    use uuid::Uuid;
+
+    use crate::{ceiling_directories, find_repository_within, SverError};
+
+    #[test]
+    fn ceiling_directories_test() {
+        let outer = temp_dir().join(format!("sver-{}", Uuid::now_v7()));
+        let project = outer.join("project");
+        let inner = project.join("src");
+        create_dir_all(&inner).unwrap();
+        Repository::init(&outer).unwrap();
+
+        // found above the project without ceiling
+        let repo = find_repository_within(&inner, &[], false).unwrap();
+        assert_eq!(
+            repo.workdir().unwrap().canonicalize().unwrap(),
+            outer.canonicalize().unwrap()
+        );
+
+        let value = join_paths([project.as_path(), "".as_ref(), "/not/exist".as_ref()]).unwrap();
+        let ceilings = ceiling_directories(Some(&value));
+        assert_eq!(ceilings, vec![project.canonicalize().unwrap()]);
+        let result = find_repository_within(&inner, &ceilings, false);
+        match result {
+            Err(SverError::RepositoryNotFoundBelow { path, boundary }) => {
+                assert_eq!(path, inner.canonicalize().unwrap().display().to_string());
+                assert_eq!(
+                    boundary,
+                    inner.canonicalize().unwrap().display().to_string()
+                );
+            }
+            _ => unreachable!("this line will not be execute"),
+        }
+        assert!(ceiling_directories(None).is_empty());
     }
-    Err(anyhow!("repository was not found"))
 }
@@ -0,0 +1,113 @@
+use std::{collections::BTreeMap, fmt::Display};
+
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::SverError;
+
+/// File name of the lockfile written at the repository root.
+/// It is not a source of targets since it holds their versions, unless it can not be parsed
+/// as a lockfile.
+pub const LOCK_FILE_NAME: &str = "sver.lock";
+
+/// Versions of all targets in the repository.
+///
+/// ```toml
+/// [versions]
+/// ".:default" = "ef5d3d3db6d5..."
+/// "service2:prof1" = "fd0053eab4b8..."
+/// ```
+#[derive(Serialize, Deserialize, Default, Debug, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct Lockfile {
+    /// Full versions keyed by `path:profile`, sorted by the key.
+    #[serde(default)]
+    pub versions: BTreeMap<String, String>,
+}
+
+impl Lockfile {
+    /// Parse a lockfile written in json or toml.
+    pub fn parse(content: &str) -> Result<Self, SverError> {
+        if content.trim_start().starts_with('{') {
+            serde_json::from_str(content).map_err(|e| SverError::InvalidLockfile(e.to_string()))
+        } else {
+            toml::from_str(content).map_err(|e| SverError::InvalidLockfile(e.to_string()))
+        }
+    }
+
+    /// A single version of all targets, which changes when the version of any target changes.
+    /// Versions are folded in the order of the key, so it does not depend on the order they
+    /// were added.
+    pub fn aggregate_version(&self) -> String {
+        let mut hasher = Sha256::default();
+        for (target, version) in &self.versions {
+            hasher.update(format!("{target}\t{version}\n").as_bytes());
+        }
+        format!("{:#x}", hasher.finalize())
+    }
+
+    /// Targets whose version in `actual` differs from this lockfile.
+    pub fn drift(&self, actual: &Lockfile) -> LockDrift {
+        let mut drift = LockDrift::default();
+        for (target, version) in &actual.versions {
+            match self.versions.get(target) {
+                None => drift.added.push(target.clone()),
+                Some(locked) if locked != version => drift.changed.push(ChangedVersion {
+                    target: target.clone(),
+                    locked: locked.clone(),
+                    actual: version.clone(),
+                }),
+                Some(_) => {}
+            }
+        }
+        drift.removed = self
+            .versions
+            .keys()
+            .filter(|target| !actual.versions.contains_key(*target))
+            .cloned()
+            .collect();
+        drift
+    }
+}
+
+/// Difference between a lockfile and the current versions.
+#[derive(Debug, Default, PartialEq, Serialize)]
+pub struct LockDrift {
+    /// Targets not in the lockfile.
+    pub added: Vec<String>,
+    /// Targets in the lockfile which no longer exist.
+    pub removed: Vec<String>,
+    pub changed: Vec<ChangedVersion>,
+}
+
+#[derive(Debug, PartialEq, Serialize)]
+pub struct ChangedVersion {
+    pub target: String,
+    pub locked: String,
+    pub actual: String,
+}
+
+impl LockDrift {
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.changed.is_empty()
+    }
+}
+
+impl Display for LockDrift {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for target in &self.added {
+            writeln!(f, "A\t{target}")?;
+        }
+        for target in &self.removed {
+            writeln!(f, "D\t{target}")?;
+        }
+        for changed in &self.changed {
+            writeln!(
+                f,
+                "M\t{}\tlocked:{}, actual:{}",
+                changed.target, changed.locked, changed.actual
+            )?;
+        }
+        Ok(())
+    }
+}
@@ -1,38 +1,202 @@
 mod cli;
 
 use anyhow::anyhow;
-use std::process::ExitCode;
+use std::{collections::BTreeMap, fs, path::Path, process::ExitCode};
 
-use crate::cli::outputs::format_versions;
+use crate::cli::inputs::{calc_target_paths, read_stdin_path, RepositoryOpener};
+use crate::cli::outputs::{
+    format_aggregate_version, format_commit_versions, format_lockfile, format_previews,
+    format_profile_versions, format_source_diff, format_source_digests, format_source_provenance,
+    format_sources, format_targets, format_validation_result, format_versions,
+    format_versions_with_details, VersionTemplate,
+};
 
-use self::cli::args::{Args, Commands, OutputFormat, VersionLength};
+use self::cli::args::{
+    Args, Commands, LockFormat, OutputFormat, ReportFormat, SubmoduleModeArg, VersionEncoding,
+    VersionLength,
+};
 use clap::Parser;
 use log::debug;
 use sver::{
-    sver_repository::{SverRepository, ValidationResults},
-    Version,
+    doctor::Severity,
+    lock::{Lockfile, LOCK_FILE_NAME},
+    sver_config::ValidationResult,
+    sver_repository::{CalcOptions, InitOptions, SubmoduleMode, ValidationResults},
 };
 
+// Wait for the editor or git to finish writing before recalculating.
+#[cfg(feature = "watch")]
+const WATCH_DEBOUNCE: std::time::Duration = std::time::Duration::from_millis(200);
+
 fn main() -> ExitCode {
     env_logger::init();
     let args = Args::parse();
+    let opener = RepositoryOpener::new(args.repo);
+    let color = args.color.enabled();
 
     let result = match args.command {
         Commands::Calc {
             paths,
+            paths_from,
+            profile,
             output,
             length,
-        } => calc(paths, output, length),
-        Commands::List { path } => list(&path),
-        Commands::Init { path } => init(&path),
-        Commands::Validate => validate(),
-        #[cfg(target_os = "linux")]
+            encoding,
+            template,
+            normalize,
+            hash_graph,
+            path_independent,
+            ignore_config_metadata,
+            symlinks_as_blobs,
+            include_untracked,
+            global_excludes,
+            lenient,
+            hash_content,
+            submodule_mode,
+            submodule_max_depth,
+            report_excludes,
+            stats,
+            all_profiles,
+            aggregate,
+            rev_range,
+            preview,
+            #[cfg(feature = "watch")]
+            watch,
+        } => {
+            let mut options = if normalize {
+                CalcOptions::normalized()
+            } else {
+                CalcOptions::default()
+            };
+            options.hash_graph = hash_graph;
+            options.path_independent = path_independent;
+            options.ignore_config_metadata = ignore_config_metadata;
+            options.symlinks_as_blobs = symlinks_as_blobs;
+            options.include_untracked = include_untracked;
+            options.global_excludes = global_excludes;
+            options.lenient = lenient;
+            options.hash_content = hash_content;
+            options.submodule_mode = match submodule_mode {
+                SubmoduleModeArg::Commit => SubmoduleMode::Commit,
+                SubmoduleModeArg::Recurse => SubmoduleMode::Recurse {
+                    max_depth: submodule_max_depth,
+                },
+            };
+            calc_target_paths(paths, paths_from.as_deref(), std::io::stdin().lock()).and_then(
+                |paths| {
+                    let opener = opener.with_profile(profile).with_options(options);
+                    if preview {
+                        return calc_preview(paths, length, opener);
+                    }
+                    if aggregate {
+                        return calc_aggregate(paths, output, length, encoding, opener);
+                    }
+                    let format = VersionFormat {
+                        output,
+                        length,
+                        encoding,
+                        template,
+                    };
+                    if all_profiles {
+                        return calc_all_profiles(paths, format, opener);
+                    }
+                    if let Some(range) = rev_range {
+                        return calc_rev_range(paths, &range, format, opener);
+                    }
+                    #[cfg(feature = "watch")]
+                    if watch {
+                        return calc_watch(paths, format, opener);
+                    }
+                    calc(paths, format, opener, report_excludes, stats)
+                },
+            )
+        }
+        Commands::List {
+            path,
+            profile,
+            digests,
+            why,
+            output,
+        } => list(&opener.with_profile(profile), &path, digests, why, output),
+        Commands::Report { path } => report(&opener, &path),
+        Commands::Verify { path, expected } => verify(&opener, &path, expected.as_deref()),
+        Commands::Init {
+            path,
+            scan,
+            profiles,
+        } => init(&opener, &path, InitOptions { profiles, scan }),
+        Commands::Config { path } => config(&opener, &path),
+        Commands::Schema => schema(),
+        Commands::Validate {
+            allow_dependencies,
+            fail_on_warning,
+            rev,
+            output,
+        } => validate(
+            &opener,
+            &allow_dependencies,
+            fail_on_warning,
+            rev.as_deref(),
+            output,
+            color,
+        ),
+        Commands::Doctor => doctor(&opener),
+        Commands::Graph => graph(&opener),
+        Commands::Targets {
+            descriptions,
+            output,
+        } => targets(&opener, descriptions, output),
+        Commands::Lock { check, output } => lock(&opener, check, output),
+        Commands::Changed { from, to, output } => changed(&opener, &from, &to, output),
+        Commands::Diff {
+            path,
+            from,
+            to,
+            output,
+        } => diff(&opener, &path, &from, &to, output),
+        #[cfg(all(target_os = "linux", feature = "inspect"))]
         Commands::Inspect {
             command,
             args,
             output,
-        } => inspect(command, args, output),
-        Commands::Export { path, export_dir } => export(&path, export_dir),
+            events,
+            emit_config,
+            write_config,
+            subtree,
+            include_dirs,
+        } => inspect(
+            &opener,
+            command,
+            args,
+            output,
+            &events,
+            if write_config {
+                Some(InspectConfig::Write)
+            } else {
+                emit_config.then_some(InspectConfig::Emit)
+            },
+            subtree.then_some(include_dirs),
+        ),
+        #[cfg(feature = "export")]
+        Commands::Export {
+            path,
+            profile,
+            export_dir,
+            random_dir,
+            dry_run,
+            from_index,
+            include_untracked,
+        } => {
+            let opener = opener.with_profile(profile).with_options(CalcOptions {
+                include_untracked,
+                ..Default::default()
+            });
+            if dry_run {
+                export_plan(&opener, &path)
+            } else {
+                export(&opener, &path, export_dir, from_index, random_dir)
+            }
+        }
     };
     match result {
         Ok(_) => ExitCode::SUCCESS,
@@ -43,65 +207,469 @@ fn main() -> ExitCode {
     }
 }
 
-fn calc(paths: Vec<String>, output: OutputFormat, length: VersionLength) -> anyhow::Result<()> {
-    let paths = if paths.is_empty() {
-        vec![".".to_string()]
+// How `sver calc` prints versions.
+struct VersionFormat {
+    output: OutputFormat,
+    length: VersionLength,
+    encoding: VersionEncoding,
+    template: VersionTemplate,
+}
+
+fn calc(
+    paths: Vec<String>,
+    format: VersionFormat,
+    opener: RepositoryOpener,
+    report_excludes: bool,
+    stats: bool,
+) -> anyhow::Result<()> {
+    debug!("paths:{:?}", paths);
+    let VersionFormat {
+        output,
+        length,
+        encoding,
+        template,
+    } = format;
+    let contributing = matches!(output, OutputFormat::Json);
+    let mut versions = Vec::new();
+    let mut source_stats = Vec::new();
+    let mut contributing_targets = Vec::new();
+    for p in &paths {
+        let repo = opener.open(p)?;
+        if report_excludes {
+            repo.report_excludes()?
+                .iter()
+                .for_each(|report| eprint!("{report}"));
+        }
+        if stats || contributing {
+            let details = repo.calc_version_with_details()?;
+            versions.push(details.version);
+            source_stats.push(details.stats);
+            contributing_targets.push(details.contributing_targets);
+        } else {
+            versions.push(repo.calc_version()?);
+        }
+    }
+    // no targets, e.g. `calc -` with empty stdin, can not collide
+    if let (VersionLength::Custom(digits), Some(path)) = (&length, paths.first()) {
+        let targets = opener.open(path)?.list_targets()?.len();
+        if length.risks_collision(targets, &encoding) {
+            eprintln!(
+                "warning: {digits} digits version may collide among {targets} targets. probability:{:.4}",
+                length.collision_probability(targets, &encoding)
+            );
+        }
+    }
+    let formatted = if stats || contributing {
+        format_versions_with_details(
+            &versions,
+            stats.then_some(source_stats.as_slice()),
+            contributing.then_some(contributing_targets.as_slice()),
+            output,
+            &length,
+            &template,
+            &encoding,
+        )?
     } else {
-        paths
+        format_versions(&versions, output, &length, &template, &encoding)?
     };
+    println!("{formatted}");
+    Ok(())
+}
+
+fn calc_preview(
+    paths: Vec<String>,
+    length: VersionLength,
+    opener: RepositoryOpener,
+) -> anyhow::Result<()> {
     debug!("paths:{:?}", paths);
-    let versions = paths
+    let previews = paths
         .iter()
-        .map(|p| SverRepository::new(p)?.calc_version())
-        .collect::<anyhow::Result<Vec<Version>>>()?;
-    println!("{}", format_versions(&versions, output, length)?);
+        .map(|p| Ok(opener.open(p)?.preview_version()?))
+        .collect::<anyhow::Result<Vec<_>>>()?;
+    println!("{}", format_previews(&previews, &length));
     Ok(())
 }
 
-fn list(path: &str) -> anyhow::Result<()> {
-    SverRepository::new(path)?
-        .list_sources()?
-        .iter()
-        .for_each(|s| println!("{s}"));
+fn calc_all_profiles(
+    paths: Vec<String>,
+    format: VersionFormat,
+    opener: RepositoryOpener,
+) -> anyhow::Result<()> {
+    let VersionFormat {
+        output,
+        length,
+        encoding,
+        template,
+    } = format;
+    let [path] = paths.as_slice() else {
+        return Err(anyhow!(
+            "--all-profiles requires exactly one target. targets:{}",
+            paths.len()
+        ));
+    };
+    let versions = opener.open(path)?.calc_versions_of_all_profiles()?;
+    println!(
+        "{}",
+        format_profile_versions(&versions, output, &length, &template, &encoding)?
+    );
     Ok(())
 }
 
-fn init(path: &str) -> anyhow::Result<()> {
-    println!("{}", SverRepository::new(path)?.init_sver_config()?);
+fn calc_aggregate(
+    paths: Vec<String>,
+    output: OutputFormat,
+    length: VersionLength,
+    encoding: VersionEncoding,
+    opener: RepositoryOpener,
+) -> anyhow::Result<()> {
+    let [path] = paths.as_slice() else {
+        return Err(anyhow!(
+            "--aggregate requires exactly one path in the repository. targets:{}",
+            paths.len()
+        ));
+    };
+    let repo = opener.open(path)?;
+    let version = repo.calc_aggregate_version()?;
+    println!(
+        "{}",
+        format_aggregate_version(repo.work_dir(), &version, output, &length, &encoding)?
+    );
+    Ok(())
+}
+
+fn calc_rev_range(
+    paths: Vec<String>,
+    range: &str,
+    format: VersionFormat,
+    opener: RepositoryOpener,
+) -> anyhow::Result<()> {
+    let VersionFormat {
+        output,
+        length,
+        encoding,
+        template,
+    } = format;
+    let [path] = paths.as_slice() else {
+        return Err(anyhow!(
+            "--rev-range requires exactly one target. targets:{}",
+            paths.len()
+        ));
+    };
+    let versions = opener.open(path)?.calc_versions_in_range(range)?;
+    let formatted = format_commit_versions(&versions, output, &length, &template, &encoding)?;
+    if !formatted.is_empty() {
+        println!("{formatted}");
+    }
+    Ok(())
+}
+
+#[cfg(feature = "watch")]
+fn calc_watch(
+    paths: Vec<String>,
+    format: VersionFormat,
+    opener: RepositoryOpener,
+) -> anyhow::Result<()> {
+    let VersionFormat {
+        output,
+        length,
+        encoding,
+        template,
+    } = format;
+    let [path] = paths.as_slice() else {
+        return Err(anyhow!(
+            "--watch requires exactly one target. targets:{}",
+            paths.len()
+        ));
+    };
+    let repo = opener.open(path)?;
+    sver::watch::watch_version(&repo, WATCH_DEBOUNCE, |version| {
+        let formatted = version.map_err(|e| anyhow!("{e}")).and_then(|version| {
+            format_versions(
+                std::slice::from_ref(version),
+                output.clone(),
+                &length,
+                &template,
+                &encoding,
+            )
+        });
+        match formatted {
+            Ok(version) => println!("{version}"),
+            Err(e) => eprintln!("{e}"),
+        }
+        std::ops::ControlFlow::Continue(())
+    })?;
+    Ok(())
+}
+
+fn list(
+    opener: &RepositoryOpener,
+    path: &str,
+    digests: bool,
+    why: bool,
+    output: ReportFormat,
+) -> anyhow::Result<()> {
+    let path = read_stdin_path(path.to_string(), std::io::stdin().lock())?;
+    let repo = opener.open(&path)?;
+    let sources = if digests {
+        format_source_digests(&repo.source_digests()?, output)?
+    } else if why {
+        format_source_provenance(&repo.source_provenance()?, output)?
+    } else {
+        format_sources(&repo.list_sources()?, output)?
+    };
+    if !sources.is_empty() {
+        println!("{sources}");
+    }
+    Ok(())
+}
+
+fn report(opener: &RepositoryOpener, path: &str) -> anyhow::Result<()> {
+    let report = opener.open(path)?.calc_report()?;
+    println!("{}", serde_json::to_string_pretty(&report)?);
     Ok(())
 }
 
-fn validate() -> anyhow::Result<()> {
-    let ValidationResults {
-        has_invalid,
-        results,
-    } = SverRepository::new(".")?.validate_sver_config()?;
-    results.iter().for_each(|s| print!("{s}"));
-    if has_invalid {
+fn verify(opener: &RepositoryOpener, path: &str, expected: Option<&str>) -> anyhow::Result<()> {
+    let verification = opener.open(path)?.verify_version(expected)?;
+    print!("{verification}");
+    if !verification.is_verified() {
+        return Err(anyhow!("Verification failed"));
+    }
+    Ok(())
+}
+
+fn config(opener: &RepositoryOpener, path: &str) -> anyhow::Result<()> {
+    print!("{}", opener.open(path)?.resolved_config()?);
+    Ok(())
+}
+
+fn init(opener: &RepositoryOpener, path: &str, options: InitOptions) -> anyhow::Result<()> {
+    println!("{}", opener.open(path)?.init_sver_config_with(&options)?);
+    Ok(())
+}
+
+fn schema() -> anyhow::Result<()> {
+    println!(
+        "{}",
+        serde_json::to_string_pretty(&sver::sver_config::config_schema())?
+    );
+    Ok(())
+}
+
+fn validate(
+    opener: &RepositoryOpener,
+    allow_dependencies: &[String],
+    fail_on_warning: bool,
+    rev: Option<&str>,
+    output: ReportFormat,
+    color: bool,
+) -> anyhow::Result<()> {
+    let repo = opener.open(".")?;
+    let results = match output {
+        ReportFormat::Text => {
+            let mut results = Vec::new();
+            let callback = |result: ValidationResult| {
+                let result = result.allow_dependencies(allow_dependencies);
+                print!("{}", format_validation_result(&result, color));
+                results.push(result);
+            };
+            match rev {
+                Some(rev) => repo.validate_streaming_at(rev, callback)?,
+                None => repo.validate_streaming(callback)?,
+            }
+            ValidationResults::new(results)
+        }
+        ReportFormat::Json => {
+            let results = match rev {
+                Some(rev) => repo.validate_sver_config_at(rev)?,
+                None => repo.validate_sver_config()?,
+            }
+            .allow_dependencies(allow_dependencies);
+            println!("{}", serde_json::to_string_pretty(&results)?);
+            results
+        }
+    };
+    if results.has_invalid {
         return Err(anyhow!("There are some invalid configs"));
     }
+    if results.is_failure(fail_on_warning) {
+        return Err(anyhow!("There are some warnings"));
+    }
+    Ok(())
+}
+
+fn doctor(opener: &RepositoryOpener) -> anyhow::Result<()> {
+    let diagnostics = sver::doctor::diagnose(opener.repository_dir())?;
+    if diagnostics.is_empty() {
+        println!("No problems found");
+        return Ok(());
+    }
+    diagnostics.iter().for_each(|d| print!("{d}"));
+    if diagnostics.iter().any(|d| d.severity == Severity::Error) {
+        return Err(anyhow!("There are some problems"));
+    }
+    Ok(())
+}
+
+fn graph(opener: &RepositoryOpener) -> anyhow::Result<()> {
+    print!("{}", opener.open(".")?.dependency_graph()?);
+    Ok(())
+}
+
+fn targets(
+    opener: &RepositoryOpener,
+    descriptions: bool,
+    output: ReportFormat,
+) -> anyhow::Result<()> {
+    let repo = opener.open(".")?;
+    let targets = repo.list_targets()?;
+    // plain targets can be piped to `calc -`
+    let descriptions = match output {
+        ReportFormat::Text if !descriptions => BTreeMap::new(),
+        _ => repo.target_descriptions()?,
+    };
+    println!("{}", format_targets(&targets, &descriptions, output)?);
+    Ok(())
+}
+
+fn lock(opener: &RepositoryOpener, check: bool, output: LockFormat) -> anyhow::Result<()> {
+    let repo = opener.open(".")?;
+    let lock_file = Path::new(repo.work_dir()).join(LOCK_FILE_NAME);
+    let lockfile = repo.lock_versions()?;
+    if check {
+        let content = fs::read_to_string(&lock_file).map_err(|e| {
+            anyhow!(
+                "failed to read lockfile. path:{}, error:{e}",
+                lock_file.display()
+            )
+        })?;
+        let drift = Lockfile::parse(&content)?.drift(&lockfile);
+        if drift.is_empty() {
+            println!("Lockfile is up to date");
+            return Ok(());
+        }
+        print!("{drift}");
+        return Err(anyhow!("Lockfile is out of date"));
+    }
+    fs::write(&lock_file, format_lockfile(&lockfile, output)?)?;
+    println!("{}", lock_file.display());
     Ok(())
 }
 
-#[cfg(target_os = "linux")]
+fn changed(
+    opener: &RepositoryOpener,
+    from: &str,
+    to: &str,
+    output: ReportFormat,
+) -> anyhow::Result<()> {
+    let targets = opener.open(".")?.changed_targets(from, to)?;
+    println!("{}", format_targets(&targets, &BTreeMap::new(), output)?);
+    Ok(())
+}
+
+fn diff(
+    opener: &RepositoryOpener,
+    path: &str,
+    from: &str,
+    to: &str,
+    output: ReportFormat,
+) -> anyhow::Result<()> {
+    let diff = opener.open(path)?.diff_sources(from, to)?;
+    print!("{}", format_source_diff(&diff, output)?);
+    Ok(())
+}
+
+// What `sver inspect` does with a config of the accessed files instead of printing them.
+#[cfg(all(target_os = "linux", feature = "inspect"))]
+enum InspectConfig {
+    Emit,
+    Write,
+}
+
+#[cfg(all(target_os = "linux", feature = "inspect"))]
 fn inspect(
+    opener: &RepositoryOpener,
     command: String,
     args: Vec<String>,
     output: cli::args::StdoutTarget,
+    events: &[cli::args::InspectEventArg],
+    config: Option<InspectConfig>,
+    subtree_include_dirs: Option<Vec<String>>,
 ) -> Result<(), anyhow::Error> {
+    use cli::args::InspectEventArg;
+    use sver::inspect::InspectEvent;
+
     let output = match output {
         cli::args::StdoutTarget::Stdout => std::process::Stdio::inherit(),
         cli::args::StdoutTarget::Devnull => std::process::Stdio::null(),
     };
-
-    sver::inspect::inspect(".", command, args, output)?
+    let events = events
         .iter()
-        .for_each(|s| println!("{s}"));
+        .map(|event| match event {
+            InspectEventArg::Access => InspectEvent::Access,
+            InspectEventArg::Open => InspectEvent::Open,
+            InspectEventArg::Modify => InspectEvent::Modify,
+        })
+        .collect::<Vec<_>>();
+
+    // the command runs in the repository given by `--repo`
+    let dir = opener.repository_dir();
+    let result = match subtree_include_dirs {
+        Some(include_dirs) => {
+            sver::inspect::inspect_subtree(dir, command, args, output, &events, &include_dirs)?
+        }
+        None => sver::inspect::inspect_with_events(dir, command, args, output, &events)?,
+    };
+    if let Some(config) = config {
+        // untracked files can not be dependencies
+        let files = result
+            .files
+            .iter()
+            .filter(|file| file.tracked)
+            .map(|file| file.path.clone())
+            .collect::<Vec<_>>();
+        let repo = opener.open(".")?;
+        match config {
+            InspectConfig::Write => println!("{}", repo.init_sver_config_from_files(&files)?),
+            InspectConfig::Emit => print!("{}", repo.config_from_files(&files)?),
+        }
+        return Ok(());
+    }
+    result.paths().iter().for_each(|s| println!("{s}"));
+    Ok(())
+}
+
+#[cfg(feature = "export")]
+fn export_plan(opener: &RepositoryOpener, path: &str) -> Result<(), anyhow::Error> {
+    print!("{}", sver::export::plan_export(&opener.open(path)?)?);
     Ok(())
 }
 
-fn export(path: &str, export_dir: Option<String>) -> Result<(), anyhow::Error> {
-    let export_dir = sver::export::create_export_dir(export_dir)?;
+#[cfg(feature = "export")]
+fn export(
+    opener: &RepositoryOpener,
+    path: &str,
+    export_dir: Option<String>,
+    from_index: bool,
+    random_dir: bool,
+) -> Result<(), anyhow::Error> {
+    let repo = opener.open(path)?;
+    let export = |export_dir| {
+        if from_index {
+            sver::export::export_from_index(&repo, export_dir)
+        } else {
+            sver::export::export_with_clone(&repo, export_dir)
+        }
+    };
+    if export_dir.is_some() || random_dir {
+        let export_dir = sver::export::create_export_dir(export_dir)?;
+        println!("export-dir: {}", export_dir.display());
+        return export(export_dir);
+    }
+    let export_dir = sver::export::versioned_export_dir(&repo, from_index)?;
     println!("export-dir: {}", export_dir.display());
-    sver::export::export(path, export_dir)
+    if !sver::export::export_if_absent(&export_dir, export)? {
+        eprintln!("already exported. skipped");
+    }
+    Ok(())
 }
@@ -1,5 +1,5 @@
 use std::{
-    collections::{btree_map::Iter, BTreeMap},
+    collections::{btree_map::Iter, BTreeMap, HashSet},
     fmt::Display,
     fs::File,
     io::Write,
@@ -7,22 +7,52 @@ use std::{
     sync::LazyLock,
 };
 
-use anyhow::Context;
 use git2::{Index, IndexEntry, Repository};
 use log::debug;
 use regex::Regex;
+use schemars::{JsonSchema, Schema};
 use serde::{Deserialize, Serialize};
 
-use crate::{is_samefile, match_samefile_or_include_dir, SEPARATOR_BYTE, SEPARATOR_STR};
+use crate::{
+    blob_cache::blob_content,
+    exclude::{escape_exclude, ExcludeRule, Excludes},
+    filemode::FileMode,
+    is_samefile, match_samefile_or_include_dir, normalize_setting_path, resolve_dependency_path,
+    SverError, SEPARATOR_BYTE,
+};
 
-#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+#[derive(Clone, Debug, Eq, Hash, PartialEq, PartialOrd, Ord, Serialize)]
 pub struct CalculationTarget {
     pub path: String,
     pub profile: String,
 }
 
+// `path:profile` format. The repository root is shown as ".".
+impl Display for CalculationTarget {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let path = if self.path.is_empty() {
+            "."
+        } else {
+            &self.path
+        };
+        write!(f, "{}:{}", path, self.profile)
+    }
+}
+
+// Only the last `:` separates the profile, and only when the rest is a valid profile name,
+// so paths containing `:` (e.g. `c:\hello`) are kept as is.
+// A profile name is segments of `[a-zA-Z0-9-_.]` separated by `/` (e.g. `v1.2`, `group/sub`).
+// A segment must not start with `.` so that `c:.` or `c:..` stay paths.
+const PROFILE_FORMAT: &str = r"[a-zA-Z0-9-_][a-zA-Z0-9-_.]*(?:/[a-zA-Z0-9-_][a-zA-Z0-9-_.]*)*";
 static TARGET_FORMAT: LazyLock<Regex> =
-    LazyLock::new(|| Regex::new("(.+):([a-zA-Z0-9-_]+)").unwrap());
+    LazyLock::new(|| Regex::new(&format!("^(.+):({PROFILE_FORMAT})$")).unwrap());
+static PROFILE_NAME: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(&format!("^{PROFILE_FORMAT}$")).unwrap());
+
+/// Whether `profile` can be used as a profile name of `path:profile`.
+pub fn is_valid_profile_name(profile: &str) -> bool {
+    PROFILE_NAME.is_match(profile)
+}
 
 impl CalculationTarget {
     pub fn new(path: String, profile: String) -> Self {
@@ -40,107 +70,391 @@ impl CalculationTarget {
         .unwrap_or_else(|| CalculationTarget::new(value.to_string(), "default".to_string()))
     }
 
+    /// Parse a dependency of config files. The path is normalized, so `./service1/` is
+    /// `service1`.
     pub fn parse_from_setting(value: &str) -> Self {
         let CalculationTarget { path, profile } = CalculationTarget::parse(value);
         CalculationTarget {
-            path: path.trim_end_matches(SEPARATOR_STR).to_string(),
+            path: normalize_setting_path(&path),
             profile,
         }
     }
+
+    /// Parse a dependency of the config file of `target_path`. A dependency starting with `../`
+    /// is relative from `target_path`, and it is an error to escape the repository root.
+    /// The non-transitive suffix `!` is ignored.
+    pub fn parse_dependency(target_path: &str, value: &str) -> Result<Self, SverError> {
+        let CalculationTarget { path, profile } = CalculationTarget::parse_from_setting(
+            value.strip_suffix(NON_TRANSITIVE_SUFFIX).unwrap_or(value),
+        );
+        let path = resolve_dependency_path(target_path, &path).ok_or_else(|| {
+            SverError::InvalidConfig(format!(
+                "dependency escapes the repository root. path:{target_path}, dependency:{value}"
+            ))
+        })?;
+        Ok(CalculationTarget { path, profile })
+    }
+}
+
+// Suffix of a dependency which includes only its own files, like `lib1:prof1!`.
+const NON_TRANSITIVE_SUFFIX: char = '!';
+
+// Whether the dependencies of the dependency `value` are also included. They are by default.
+pub(crate) fn is_transitive_dependency(value: &str) -> bool {
+    !value.ends_with(NON_TRANSITIVE_SUFFIX)
 }
 
-#[derive(Serialize, Deserialize, Clone, Default, Debug, PartialEq)]
+#[derive(Serialize, Deserialize, JsonSchema, Clone, Default, Debug, PartialEq)]
 pub(crate) struct ProfileConfig {
+    /// Description of the profile. It is metadata, and not used for the calculation.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) description: Option<String>,
+    /// Excluded files or directories. Relative path from the target directory.
     #[serde(default)]
     pub(crate) excludes: Vec<String>,
+    /// Dependent files or directories (`path` or `path:profile`). Relative path from the repository root.
+    /// With the suffix `!`, the dependencies of the dependency are not included.
     #[serde(default)]
     pub(crate) dependencies: Vec<String>,
+    /// Environment variables whose values are included in the version.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub(crate) env: Vec<String>,
+    /// Symbolic link targets to follow. Relative path from the repository root.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub(crate) allow_symlink_targets: Vec<String>,
+    /// Whether symbolic links are followed. Unset follows them.
+    /// Links which are not followed are hashed as the links themselves.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) follow_symlinks: Option<bool>,
+    /// File modes excluded from the sources of the target.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub(crate) exclude_modes: Vec<ExcludeMode>,
+}
+
+/// File mode of `exclude_modes`.
+#[derive(Serialize, Deserialize, JsonSchema, Clone, Copy, Debug, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum ExcludeMode {
+    /// Regular files.
+    Blob,
+    /// Executable files.
+    BlobExecutable,
+    /// Symbolic links. Excluded links are not followed.
+    Link,
+    /// Submodules.
+    Commit,
+}
+
+impl From<ExcludeMode> for FileMode {
+    fn from(value: ExcludeMode) -> Self {
+        match value {
+            ExcludeMode::Blob => FileMode::Blob,
+            ExcludeMode::BlobExecutable => FileMode::BlobExecutable,
+            ExcludeMode::Link => FileMode::Link,
+            ExcludeMode::Commit => FileMode::Commit,
+        }
+    }
+}
+
+/// File names of config files. A directory can have only one of them.
+///
+/// The format is chosen by the extension and the content is the same in all formats.
+pub const CONFIG_FILE_NAMES: [&str; 4] = ["sver.toml", "sver.yaml", "sver.yml", "sver.json"];
+
+// The file name if `path` is a config file.
+pub(crate) fn config_file_name(path: &[u8]) -> Option<&'static str> {
+    CONFIG_FILE_NAMES.into_iter().find(|name| {
+        path == name.as_bytes()
+            || path.ends_with([SEPARATOR_BYTE, name.as_bytes()].concat().as_slice())
+    })
+}
+
+// The config file of the directory `target_path` in the index.
+pub(crate) fn find_config_entry(
+    index: &Index,
+    target_path: &str,
+) -> Result<Option<IndexEntry>, SverError> {
+    let mut entries = CONFIG_FILE_NAMES
+        .iter()
+        .filter_map(|name| index.get_path(&Path::new(target_path).join(name), 0));
+    let Some(entry) = entries.next() else {
+        return Ok(None);
+    };
+    if let Some(other) = entries.next() {
+        return Err(multiple_config_files(&entry.path, &other.path));
+    }
+    Ok(Some(entry))
+}
+
+fn multiple_config_files(path: &[u8], other: &[u8]) -> SverError {
+    SverError::InvalidConfig(format!(
+        "multiple config files in a directory. paths:{}, {}",
+        String::from_utf8_lossy(path),
+        String::from_utf8_lossy(other)
+    ))
 }
 
-#[derive(Serialize, Deserialize, Clone, Default, Debug, PartialEq)]
+/// Profiles of `sver.toml`.
+#[derive(Serialize, Deserialize, JsonSchema, Clone, Default, Debug, PartialEq)]
+#[schemars(title = "sver.toml")]
 pub(crate) struct SverConfig {
     #[serde(skip)]
     pub(crate) target_path: String,
+    /// Path of the config file from the repository root.
+    #[serde(skip)]
+    pub(crate) config_file: String,
     #[serde(default, flatten)]
     profiles: BTreeMap<String, ProfileConfig>,
 }
 
+/// JSON Schema of `sver.toml`, derived from the config types.
+pub fn config_schema() -> Schema {
+    schemars::schema_for!(SverConfig)
+}
+
 impl SverConfig {
     pub(crate) fn get(&self, key: &str) -> Option<ProfileConfig> {
         self.profiles.get(key).cloned()
     }
 
-    pub(crate) fn add(&mut self, profile: &str, config: ProfileConfig) -> Option<ProfileConfig> {
-        self.profiles.insert(profile.to_owned(), config)
-    }
-
-    pub(crate) fn iter(&self) -> Iter<String, ProfileConfig> {
+    pub(crate) fn iter(&self) -> Iter<'_, String, ProfileConfig> {
         self.profiles.iter()
     }
 
-    pub(crate) fn write_initial_config(path: &Path) -> anyhow::Result<bool> {
-        let mut config = Self::default();
-        config.add("default", ProfileConfig::default());
+    // The config file in the canonical TOML form without metadata such as `description`, so
+    // editing only metadata, comments or formatting keeps the content. `None` when the config
+    // file can not be parsed.
+    pub(crate) fn content_without_metadata(config_file: &str, content: &[u8]) -> Option<Vec<u8>> {
+        let mut config = Self::parse(config_file, content).ok()?;
+        config
+            .profiles
+            .values_mut()
+            .for_each(|profile| profile.description = None);
+        toml::to_string(&config).ok().map(String::into_bytes)
+    }
 
+    // Write the initial config unless the file exists.
+    pub(crate) fn write_initial_config(path: &Path, content: &str) -> Result<bool, SverError> {
         if File::open(path).is_ok() {
             return Ok(false);
         }
 
         let mut file = File::create(path)?;
-        file.write_all(toml::to_string_pretty(&config)?.as_bytes())?;
+        file.write_all(content.as_bytes())?;
         file.flush()?;
         Ok(true)
     }
 
-    fn entry_parent(path: &str) -> anyhow::Result<String> {
+    // The initial config with a section of each profile. `suggestions` are written as
+    // commented out dependencies, so the file has no dependencies until the user enables them.
+    pub(crate) fn initial_config(profiles: &[String], suggestions: &[String]) -> String {
+        let mut config = String::new();
+        for (i, profile) in profiles.iter().enumerate() {
+            if i > 0 {
+                config.push('\n');
+            }
+            if profile.contains(['.', '/']) {
+                config.push_str(&format!("[\"{profile}\"]\n"));
+            } else {
+                config.push_str(&format!("[{profile}]\n"));
+            }
+            // explain the keys only once
+            if i == 0 {
+                config.push_str(
+                    "# Files or directories this directory depends on. Relative path from the repository root.
+# `path:profile` refers to a profile of another sver.toml.
+",
+                );
+            }
+            config.push_str("dependencies = [\n");
+            for suggestion in suggestions {
+                config.push_str(&format!("  # \"{suggestion}\",\n"));
+            }
+            config.push_str("]\n");
+            if i == 0 {
+                config.push_str(
+                    "# Files or directories excluded from this directory. Relative path from this directory.
+",
+                );
+            }
+            config.push_str("excludes = [\n]\n");
+        }
+        config
+    }
+
+    // A config whose `default` profile depends on `dependencies`.
+    pub(crate) fn dependencies_config(dependencies: &[String]) -> String {
+        let mut config = String::from("[default]\ndependencies = [\n");
+        for dependency in dependencies {
+            let quoted = toml::Value::String(dependency.clone()).to_string();
+            config.push_str(&format!("  {quoted},\n"));
+        }
+        config.push_str("]\nexcludes = [\n]\n");
+        config
+    }
+
+    fn entry_parent(path: &str) -> Result<String, SverError> {
         let mut path_buf = PathBuf::new();
         path_buf.push(path);
         let result = path_buf.parent().and_then(|path| path.to_str());
         let result = result.map(|s| s.to_string());
-        result.with_context(|| "invalid path")
+        result.ok_or_else(|| SverError::InvalidConfig(format!("invalid path. path:{path}")))
     }
 
     pub(crate) fn config_file_path(&self) -> String {
-        if self.target_path.is_empty() {
-            "sver.toml".to_owned()
-        } else {
-            format!("{}/sver.toml", &self.target_path)
+        self.config_file.clone()
+    }
+
+    // The file name of the config file, like `sver.toml`.
+    pub(crate) fn config_file_name(&self) -> &'static str {
+        config_file_name(self.config_file.as_bytes()).unwrap_or(CONFIG_FILE_NAMES[0])
+    }
+
+    // Parse a config file in the format of its extension. `config_file` is the path of the file
+    // used in the error.
+    pub(crate) fn parse(config_file: &str, content: &[u8]) -> Result<Self, ConfigSyntaxError> {
+        let content_str = String::from_utf8(content.to_vec()).map_err(|e| ConfigSyntaxError {
+            config_file: config_file.to_string(),
+            line: 1,
+            column: 1,
+            message: format!("config file is not valid UTF-8. {e}"),
+        })?;
+        debug!("content:{}", content_str);
+        match Path::new(config_file).extension().and_then(|e| e.to_str()) {
+            Some("yaml" | "yml") => serde_norway::from_str::<Self>(&content_str)
+                .map_err(|e| ConfigSyntaxError::from_yaml(config_file, &e)),
+            Some("json") => serde_json::from_str::<Self>(&content_str)
+                .map_err(|e| ConfigSyntaxError::from_json(config_file, &e)),
+            _ => toml::from_str::<Self>(&content_str)
+                .map_err(|e| ConfigSyntaxError::from_toml(config_file, &content_str, &e)),
         }
     }
 
-    pub(crate) fn load_all_configs(repo: &Repository) -> anyhow::Result<Vec<Self>> {
-        let mut result: Vec<Self> = Vec::new();
-        for entry in repo.index()?.iter() {
-            let is_sver_config_in_root_directory = entry.path == "sver.toml".as_bytes();
-            let is_sver_config_in_sub_directory = entry
-                .path
-                .ends_with([SEPARATOR_BYTE, "sver.toml".as_bytes()].concat().as_slice());
+    pub(crate) fn load_all_configs(
+        repo: &Repository,
+        index: &Index,
+    ) -> Result<Vec<Self>, SverError> {
+        Self::load_all_configs_with_errors(repo, index)?
+            .into_iter()
+            .map(|config| config.map_err(SverError::from))
+            .collect()
+    }
+
+    // Like `load_all_configs`, but config files which can not be parsed are returned as errors
+    // in place instead of aborting, so that the other config files can still be checked.
+    pub(crate) fn load_all_configs_with_errors(
+        repo: &Repository,
+        index: &Index,
+    ) -> Result<Vec<Result<Self, ConfigSyntaxError>>, SverError> {
+        let mut result = Vec::new();
+        // config file of each directory, to reject directories with multiple config files
+        let mut config_files: BTreeMap<String, String> = BTreeMap::new();
+        for entry in index.iter() {
+            let file_name = config_file_name(&entry.path);
             debug!(
-                "path:{}, is_root:{}, is_sub:{}",
-                String::from_utf8(entry.path.clone())?,
-                is_sver_config_in_root_directory,
-                is_sver_config_in_sub_directory
+                "path:{}, config_file:{:?}",
+                String::from_utf8_lossy(&entry.path),
+                file_name
             );
-            if is_sver_config_in_root_directory || is_sver_config_in_sub_directory {
-                debug!("load sver. path:{}", String::from_utf8(entry.path.clone())?);
-                let target_path = Self::entry_parent(&String::from_utf8(entry.path.clone())?)?;
-                let blob = repo.find_blob(entry.id)?;
-
-                let content_str = String::from_utf8(blob.content().to_vec())?;
-                debug!("content:{}", content_str);
+            if file_name.is_some() {
+                let path = String::from_utf8(entry.path.clone())
+                    .map_err(|_| SverError::non_utf8_path(&entry.path))?;
+                debug!("load sver. path:{}", path);
+                let target_path = Self::entry_parent(&path)?;
+                if let Some(other) = config_files.insert(target_path.clone(), path.clone()) {
+                    return Err(multiple_config_files(other.as_bytes(), path.as_bytes()));
+                }
+                let content = blob_content(repo, entry.id)?;
 
-                let mut config = toml::from_str::<Self>(&content_str)?;
-                config.target_path = target_path;
-                result.push(config);
+                result.push(Self::parse(&path, &content).map(|mut config| {
+                    config.target_path = target_path;
+                    config.config_file = path;
+                    config
+                }));
             }
         }
         Ok(result)
     }
 }
 
+pub(crate) fn config_content_str(content: &[u8]) -> Result<String, SverError> {
+    String::from_utf8(content.to_vec())
+        .map_err(|e| SverError::InvalidConfig(format!("config file is not valid UTF-8. {e}")))
+}
+
+/// A config file which can not be parsed, with the position of the error.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct ConfigSyntaxError {
+    pub config_file: String,
+    /// 1-based line number.
+    pub line: usize,
+    /// 1-based column number in characters.
+    pub column: usize,
+    pub message: String,
+}
+
+impl ConfigSyntaxError {
+    fn from_toml(config_file: &str, content: &str, error: &toml::de::Error) -> Self {
+        let offset = error.span().map(|span| span.start).unwrap_or_default();
+        let before = &content[..offset.min(content.len())];
+        let line = before.matches('\n').count() + 1;
+        let column = before
+            .rsplit('\n')
+            .next()
+            .map(|line| line.chars().count())
+            .unwrap_or_default()
+            + 1;
+        Self {
+            config_file: config_file.to_string(),
+            line,
+            column,
+            message: error.message().to_string(),
+        }
+    }
+
+    fn from_yaml(config_file: &str, error: &serde_norway::Error) -> Self {
+        let (line, column) = error
+            .location()
+            .map(|location| (location.line(), location.column()))
+            .unwrap_or((1, 1));
+        Self {
+            config_file: config_file.to_string(),
+            line,
+            column,
+            message: error.to_string(),
+        }
+    }
+
+    fn from_json(config_file: &str, error: &serde_json::Error) -> Self {
+        Self {
+            config_file: config_file.to_string(),
+            line: error.line().max(1),
+            column: error.column().max(1),
+            message: error.to_string(),
+        }
+    }
+}
+
+impl Display for ConfigSyntaxError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "invalid config file. path:{}, line:{}, column:{}, {}",
+            self.config_file, self.line, self.column, self.message
+        )
+    }
+}
+
+impl From<ConfigSyntaxError> for SverError {
+    fn from(value: ConfigSyntaxError) -> Self {
+        SverError::InvalidConfig(value.to_string())
+    }
+}
+
 #[derive(Default, Debug)]
 struct InnerValidationResult {
-    pub(crate) invalid_excludes: Vec<String>,
+    // An exclude that can not be parsed has no rule and is always invalid.
+    pub(crate) invalid_excludes: Vec<(String, Option<ExcludeRule>)>,
     pub(crate) invalid_dependencies: Vec<String>,
 }
 
@@ -150,16 +464,98 @@ impl InnerValidationResult {
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Serialize)]
+#[serde(tag = "result", rename_all = "snake_case")]
 pub enum ValidationResult {
     Valid {
         calcuration_target: CalculationTarget,
+        /// File name of the config file, like `sver.toml`.
+        config_file_name: String,
     },
     Invalid {
         calcuration_target: CalculationTarget,
+        config_file_name: String,
         invalid_excludes: Vec<String>,
         invalid_dependencies: Vec<String>,
     },
+    /// Valid, but likely a mistake of the configuration. Not regarded as invalid.
+    Warning {
+        calcuration_target: CalculationTarget,
+        config_file_name: String,
+        /// The exclude which excludes the config file itself from the sources, so changes of
+        /// the config file do not change the version.
+        #[serde(skip_serializing_if = "Option::is_none")]
+        config_file_exclude: Option<String>,
+        /// Targets of the dependency cycle starting from and returning to this target.
+        #[serde(skip_serializing_if = "Vec::is_empty")]
+        cyclic_dependencies: Vec<String>,
+        /// Glob excludes which also name a file of the index literally, like `[draft].md`.
+        /// They are matched as globs, so the file itself may not be excluded.
+        #[serde(skip_serializing_if = "Vec::is_empty")]
+        ambiguous_excludes: Vec<String>,
+    },
+    /// The config file can not be parsed, so its profiles are not validated.
+    SyntaxError(ConfigSyntaxError),
+    /// Patterns of the ignore file which match no file in the repository. Not regarded as
+    /// invalid.
+    UnmatchedIgnore {
+        ignore_file: String,
+        unmatched_patterns: Vec<String>,
+    },
+}
+
+impl ValidationResult {
+    pub fn is_invalid(&self) -> bool {
+        matches!(
+            self,
+            ValidationResult::Invalid { .. } | ValidationResult::SyntaxError(_)
+        )
+    }
+
+    pub fn is_warning(&self) -> bool {
+        matches!(
+            self,
+            ValidationResult::Warning { .. } | ValidationResult::UnmatchedIgnore { .. }
+        )
+    }
+
+    /// Label at the head of the text output, like `[OK]`.
+    pub fn label(&self) -> &'static str {
+        match self {
+            ValidationResult::Valid { .. } => "[OK]",
+            ValidationResult::Warning { .. } | ValidationResult::UnmatchedIgnore { .. } => "[Warn]",
+            ValidationResult::Invalid { .. } | ValidationResult::SyntaxError(_) => "[Fail]",
+        }
+    }
+
+    /// Drop the dependencies listed in `allowed` from `invalid_dependencies`.
+    /// The result becomes `Valid` when nothing invalid remains.
+    pub fn allow_dependencies(self, allowed: &[String]) -> Self {
+        match self {
+            ValidationResult::Invalid {
+                calcuration_target,
+                config_file_name,
+                invalid_excludes,
+                mut invalid_dependencies,
+            } => {
+                invalid_dependencies.retain(|dependency| !allowed.contains(dependency));
+                if invalid_dependencies.is_empty() && invalid_excludes.is_empty() {
+                    ValidationResult::Valid {
+                        calcuration_target,
+                        config_file_name,
+                    }
+                } else {
+                    ValidationResult::Invalid {
+                        calcuration_target,
+                        config_file_name,
+                        invalid_excludes,
+                        invalid_dependencies,
+                    }
+                }
+            }
+            valid => valid,
+        }
+    }
 }
 
 impl Display for ValidationResult {
@@ -167,30 +563,133 @@ impl Display for ValidationResult {
         match self {
             ValidationResult::Valid {
                 calcuration_target: CalculationTarget { path, profile },
+                config_file_name,
             } => {
-                writeln!(f, "[OK]\t{path}/sver.toml:[{profile}]")
+                writeln!(f, "{}\t{path}/{config_file_name}:[{profile}]", self.label())
+            }
+            ValidationResult::Warning {
+                calcuration_target: CalculationTarget { path, profile },
+                config_file_name,
+                config_file_exclude,
+                cyclic_dependencies,
+                ambiguous_excludes,
+            } => {
+                writeln!(f, "{}\t{path}/{config_file_name}:[{profile}]", self.label())?;
+                if let Some(config_file_exclude) = config_file_exclude {
+                    writeln!(f, "\t\texcluded_config_file:{config_file_exclude:?}")?;
+                }
+                if !cyclic_dependencies.is_empty() {
+                    writeln!(f, "\t\tcyclic_dependency:{cyclic_dependencies:?}")?;
+                }
+                if !ambiguous_excludes.is_empty() {
+                    writeln!(f, "\t\tambiguous_exclude:{ambiguous_excludes:?}")?;
+                }
+                Ok(())
             }
             ValidationResult::Invalid {
                 calcuration_target: CalculationTarget { path, profile },
+                config_file_name,
                 invalid_dependencies,
                 invalid_excludes,
             } => {
-                writeln!(f, "[Fail]\t{path}/sver.toml:[{profile}]")?;
+                writeln!(f, "{}\t{path}/{config_file_name}:[{profile}]", self.label())?;
                 writeln!(f, "\t\tinvalid_dependency:{invalid_dependencies:?}")?;
                 writeln!(f, "\t\tinvalid_exclude:{invalid_excludes:?}")
             }
+            ValidationResult::SyntaxError(ConfigSyntaxError {
+                config_file,
+                line,
+                column,
+                message,
+            }) => {
+                writeln!(f, "{}\t{config_file}", self.label())?;
+                writeln!(
+                    f,
+                    "\t\tsyntax_error:line {line}, column {column}, {message}"
+                )
+            }
+            ValidationResult::UnmatchedIgnore {
+                ignore_file,
+                unmatched_patterns,
+            } => {
+                writeln!(f, "{}\t{ignore_file}", self.label())?;
+                writeln!(f, "\t\tunmatched_pattern:{unmatched_patterns:?}")
+            }
         }
     }
 }
 
+/// Profile of a target as sver uses it. Excludes are in the canonical form, and dependencies
+/// are resolved to `path:profile` from the repository root.
+#[derive(Debug)]
+pub struct ResolvedConfig {
+    pub calculation_target: CalculationTarget,
+    config: ProfileConfig,
+}
+
+impl ResolvedConfig {
+    pub(crate) fn new(
+        calculation_target: CalculationTarget,
+        mut config: ProfileConfig,
+    ) -> Result<Self, SverError> {
+        config.dependencies = config
+            .dependencies
+            .iter()
+            .map(|dependency| {
+                let resolved =
+                    CalculationTarget::parse_dependency(&calculation_target.path, dependency)?;
+                Ok(if is_transitive_dependency(dependency) {
+                    resolved.to_string()
+                } else {
+                    format!("{resolved}{NON_TRANSITIVE_SUFFIX}")
+                })
+            })
+            .collect::<Result<_, SverError>>()?;
+        config.excludes = config
+            .excludes
+            .iter()
+            .map(|exclude| {
+                let (negation, path) = match exclude.strip_prefix('!') {
+                    Some(path) => ("!", path),
+                    None => ("", exclude.as_str()),
+                };
+                // same as `ExcludeRule`, an exclude normalized to empty is kept as is
+                match normalize_setting_path(path) {
+                    normalized if normalized.is_empty() => exclude.clone(),
+                    normalized => format!("{negation}{normalized}"),
+                }
+            })
+            .collect();
+        Ok(Self {
+            calculation_target,
+            config,
+        })
+    }
+}
+
+// A section of the profile in TOML.
+impl Display for ResolvedConfig {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let section = BTreeMap::from([(&self.calculation_target.profile, &self.config)]);
+        write!(
+            f,
+            "{}",
+            toml::to_string(&section).map_err(|_| std::fmt::Error)?
+        )
+    }
+}
+
 impl ProfileConfig {
-    pub(crate) fn load_profile(content: &[u8], profile: &str) -> anyhow::Result<ProfileConfig> {
-        let content_str = String::from_utf8(content.to_vec())?;
-        let config = toml::from_str::<SverConfig>(&content_str)?;
+    pub(crate) fn load_profile(
+        config_file: &str,
+        content: &[u8],
+        profile: &str,
+    ) -> Result<ProfileConfig, SverError> {
+        let config = SverConfig::parse(config_file, content)?;
         debug!("loaded_config:{:?}, profile:{}", config, profile);
         config
             .get(profile)
-            .with_context(|| format!("profile[{profile}] is not found"))
+            .ok_or_else(|| SverError::ProfileNotFound(profile.to_string()))
     }
 
     pub(crate) fn validate(
@@ -202,44 +701,152 @@ impl ProfileConfig {
         configs: &[SverConfig],
     ) -> ValidationResult {
         let mut result = InnerValidationResult::default();
+        let config_file_name = configs
+            .iter()
+            .find(|c| c.target_path == path)
+            .map_or(CONFIG_FILE_NAMES[0], SverConfig::config_file_name)
+            .to_string();
 
         result
             .invalid_dependencies
             .extend(self.dependencies.clone());
-        result.invalid_excludes.extend(self.excludes.clone());
+        result.invalid_excludes.extend(
+            self.excludes
+                .iter()
+                .map(|exclude| (exclude.clone(), ExcludeRule::new(path, exclude).ok())),
+        );
 
         for entry in index.iter() {
-            result
-                .invalid_dependencies
-                .retain(|dependency| Self::is_valid_dependency(dependency, &entry, repo, configs));
+            result.invalid_dependencies.retain(|dependency| {
+                Self::is_valid_dependency(path, dependency, &entry, repo, configs)
+            });
             result
                 .invalid_excludes
-                .retain(|exclude| Self::is_valid_exclude(exclude, &entry, path));
+                .retain(|(_, rule)| Self::is_valid_exclude(rule, &entry));
             if result.is_empty() {
-                return ValidationResult::Valid {
-                    calcuration_target: CalculationTarget::new(
-                        path.to_string(),
-                        profile.to_string(),
-                    ),
+                let calcuration_target =
+                    CalculationTarget::new(path.to_string(), profile.to_string());
+                let config_file_exclude = self.config_file_exclude(path, configs);
+                let cyclic_dependencies = Self::find_cycle(&calcuration_target, configs);
+                let ambiguous_excludes = self.ambiguous_excludes(path, index);
+                if config_file_exclude.is_none()
+                    && cyclic_dependencies.is_empty()
+                    && ambiguous_excludes.is_empty()
+                {
+                    return ValidationResult::Valid {
+                        calcuration_target,
+                        config_file_name,
+                    };
+                }
+                return ValidationResult::Warning {
+                    calcuration_target,
+                    config_file_name,
+                    config_file_exclude,
+                    cyclic_dependencies,
+                    ambiguous_excludes,
                 };
             }
         }
 
         ValidationResult::Invalid {
             calcuration_target: CalculationTarget::new(path.to_string(), profile.to_string()),
-            invalid_excludes: result.invalid_excludes.clone(),
-            invalid_dependencies: result.invalid_dependencies.clone(),
+            config_file_name,
+            invalid_excludes: result
+                .invalid_excludes
+                .into_iter()
+                .map(|(exclude, _)| exclude)
+                .collect(),
+            invalid_dependencies: result.invalid_dependencies,
+        }
+    }
+
+    // Glob excludes which also match a file of the index as a literal path. Such an exclude was
+    // a plain path before globs were supported, and needs escaping to keep excluding the file.
+    fn ambiguous_excludes(&self, path: &str, index: &Index) -> Vec<String> {
+        self.excludes
+            .iter()
+            .filter(|exclude| {
+                ExcludeRule::new(path, exclude).is_ok_and(|rule| rule.is_glob())
+                    && ExcludeRule::new(path, &escape_exclude(exclude))
+                        .is_ok_and(|literal| index.iter().any(|entry| literal.matches(&entry.path)))
+            })
+            .cloned()
+            .collect()
+    }
+
+    // The exclude which excludes the config file of `path` itself, if any.
+    fn config_file_exclude(&self, path: &str, configs: &[SverConfig]) -> Option<String> {
+        let config_file = configs
+            .iter()
+            .find(|c| c.target_path == path)?
+            .config_file_path();
+        let excludes = Excludes::new(path, &self.excludes).ok()?;
+        let index = excludes.excluded_by(config_file.as_bytes())?;
+        Some(excludes.rules()[index].source().to_string())
+    }
+
+    // Returns the targets of a dependency cycle which starts from and returns to `start`
+    // (e.g. `["a:default", "b:default", "a:default"]`), or empty if there is no such cycle.
+    // Only dependencies to profiles of config files can form a cycle.
+    fn find_cycle(start: &CalculationTarget, configs: &[SverConfig]) -> Vec<String> {
+        let mut stack = Vec::new();
+        let mut visited = HashSet::new();
+        if Self::visit_dependencies(start, start, configs, &mut stack, &mut visited) {
+            stack.push(start.clone());
+            stack.iter().map(|target| target.to_string()).collect()
+        } else {
+            Vec::new()
+        }
+    }
+
+    fn visit_dependencies(
+        current: &CalculationTarget,
+        start: &CalculationTarget,
+        configs: &[SverConfig],
+        stack: &mut Vec<CalculationTarget>,
+        visited: &mut HashSet<CalculationTarget>,
+    ) -> bool {
+        let Some(config) = configs
+            .iter()
+            .find(|c| c.target_path == current.path)
+            .and_then(|c| c.profiles.get(&current.profile))
+        else {
+            return false;
+        };
+        stack.push(current.clone());
+        for dependency in &config.dependencies {
+            // dependencies escaping the repository root are reported as invalid
+            let Ok(dependency) = CalculationTarget::parse_dependency(&current.path, dependency)
+            else {
+                continue;
+            };
+            if &dependency == start {
+                return true;
+            }
+            if visited.insert(dependency.clone())
+                && Self::visit_dependencies(&dependency, start, configs, stack, visited)
+            {
+                return true;
+            }
         }
+        stack.pop();
+        false
     }
 
     #[inline]
     fn is_valid_dependency(
+        target_path: &str,
         dependency: &str,
         entry: &IndexEntry,
         repo: &Repository,
         configs: &[SverConfig],
     ) -> bool {
-        let CalculationTarget { path, profile } = CalculationTarget::parse_from_setting(dependency);
+        let Ok(CalculationTarget { path, profile }) =
+            CalculationTarget::parse_dependency(target_path, dependency)
+        else {
+            // escapes the repository root
+            return true;
+        };
         let config_file = configs.iter().find(|c| c.target_path == path);
         if profile == "default" && config_file.is_none() {
             !match_samefile_or_include_dir(&entry.path, path.as_bytes())
@@ -249,13 +856,18 @@ impl ProfileConfig {
                 return false;
             }
 
-            let mut config_file_path: Vec<u8> = Vec::new();
-            config_file_path.extend_from_slice(path.as_bytes());
-            config_file_path.extend_from_slice(SEPARATOR_BYTE);
-            config_file_path.extend_from_slice("sver.toml".as_bytes());
-            if is_samefile(&entry.path, config_file_path.as_slice()) {
-                return if let Ok(blob) = &repo.find_blob(entry.id) {
-                    ProfileConfig::load_profile(blob.content(), &profile).is_err()
+            let is_config_file = config_file_name(&entry.path).is_some_and(|name| {
+                is_samefile(
+                    &entry.path,
+                    [path.as_bytes(), SEPARATOR_BYTE, name.as_bytes()]
+                        .concat()
+                        .as_slice(),
+                )
+            });
+            if is_config_file {
+                return if let Ok(content) = blob_content(repo, entry.id) {
+                    let config_file = String::from_utf8_lossy(&entry.path);
+                    ProfileConfig::load_profile(&config_file, &content, &profile).is_err()
                 } else {
                     true
                 };
@@ -265,19 +877,12 @@ impl ProfileConfig {
     }
 
     #[inline]
-    fn is_valid_exclude(exclude: &str, entry: &IndexEntry, path: &str) -> bool {
-        let normalized_path = if path.is_empty() {
-            exclude.as_bytes().to_vec()
-        } else {
-            [path.as_bytes(), SEPARATOR_BYTE, exclude.as_bytes()].concat()
-        };
-
-        let is_match = match_samefile_or_include_dir(&entry.path, &normalized_path);
-
+    fn is_valid_exclude(rule: &Option<ExcludeRule>, entry: &IndexEntry) -> bool {
+        let is_match = rule.as_ref().is_some_and(|rule| rule.matches(&entry.path));
         debug!(
-            "exclude {}, {}, match:{}",
-            String::from_utf8(entry.path.clone().to_vec()).unwrap(),
-            String::from_utf8(normalized_path).unwrap(),
+            "exclude {:?}, {}, match:{}",
+            rule,
+            String::from_utf8_lossy(&entry.path),
             is_match,
         );
         !is_match
@@ -286,7 +891,7 @@ impl ProfileConfig {
 
 #[cfg(test)]
 mod sver_config_tests {
-    use crate::sver_config::{ProfileConfig, SverConfig};
+    use crate::sver_config::{config_schema, ExcludeMode, ProfileConfig, SverConfig};
 
     #[test]
     fn sver_configs_test() {
@@ -306,8 +911,13 @@ excludes = ["exclude2"]
         assert_eq!(
             configs.get("default").unwrap(),
             ProfileConfig {
+                description: None,
                 dependencies: vec!["dep1".to_owned()],
                 excludes: vec!["exclude1".to_owned()],
+                env: vec![],
+                allow_symlink_targets: vec![],
+                follow_symlinks: None,
+                exclude_modes: vec![],
             }
         );
         assert!(configs.target_path.is_empty());
@@ -315,6 +925,95 @@ excludes = ["exclude2"]
         let toml_str = toml::to_string_pretty(&configs).unwrap();
         println!("{toml_str}");
     }
+
+    #[test]
+    fn content_without_metadata_test() {
+        let content = |description: &str| {
+            format!(
+                "# comment\n[default]\ndescription = \"{description}\"\ndependencies = [\"dep1\"]\n"
+            )
+        };
+        let without_metadata = |content: &str| {
+            SverConfig::content_without_metadata("sver.toml", content.as_bytes()).unwrap()
+        };
+        assert_eq!(
+            without_metadata(&content("build for release")),
+            without_metadata(&content("build for production"))
+        );
+        assert_eq!(
+            without_metadata(&content("build for release")),
+            without_metadata("[default]\ndependencies = [\"dep1\"]\n")
+        );
+        assert_ne!(
+            without_metadata(&content("build for release")),
+            without_metadata("[default]\ndependencies = [\"dep2\"]\n")
+        );
+        assert!(SverConfig::content_without_metadata("sver.toml", b"[default").is_none());
+    }
+
+    #[test]
+    fn exclude_modes_test() {
+        let test = r#"[default]
+exclude_modes = ["link", "blob_executable"]
+"#;
+        let configs = toml::from_str::<SverConfig>(test).unwrap();
+        assert_eq!(
+            configs.get("default").unwrap().exclude_modes,
+            vec![ExcludeMode::Link, ExcludeMode::BlobExecutable]
+        );
+        assert!(toml::from_str::<SverConfig>("[default]\nexclude_modes = [\"tree\"]\n").is_err());
+    }
+
+    #[test]
+    fn initial_config_test() {
+        let profiles = vec!["default".to_string()];
+        for suggestions in [vec![], vec!["lib1".to_string(), "lib2".to_string()]] {
+            let content = SverConfig::initial_config(&profiles, &suggestions);
+            let config = toml::from_str::<SverConfig>(&content).unwrap();
+            assert_eq!(config.get("default").unwrap(), ProfileConfig::default());
+            for suggestion in suggestions {
+                assert!(content.contains(&format!("  # \"{suggestion}\",\n")));
+            }
+        }
+    }
+
+    #[test]
+    fn initial_config_with_profiles_test() {
+        let profiles = ["default", "ci", "v1.2", "group/sub"].map(String::from);
+        let content = SverConfig::initial_config(&profiles, &[]);
+        let config = toml::from_str::<SverConfig>(&content).unwrap();
+        for profile in &profiles {
+            assert_eq!(config.get(profile).unwrap(), ProfileConfig::default());
+        }
+    }
+
+    #[test]
+    fn dependencies_config_test() {
+        let dependencies = ["lib1", "src/main.rs", "with \"quote\""].map(String::from);
+        let content = SverConfig::dependencies_config(&dependencies);
+        let config = toml::from_str::<SverConfig>(&content).unwrap();
+        assert_eq!(
+            config.get("default").unwrap(),
+            ProfileConfig {
+                dependencies: dependencies.to_vec(),
+                ..Default::default()
+            }
+        );
+    }
+
+    #[test]
+    fn config_schema_test() {
+        let schema = serde_json::to_string(&config_schema()).unwrap();
+        let schema = serde_json::from_str::<serde_json::Value>(&schema).unwrap();
+
+        let profile = &schema["$defs"]["ProfileConfig"];
+        assert_eq!(
+            schema["additionalProperties"]["$ref"],
+            "#/$defs/ProfileConfig"
+        );
+        assert_eq!(profile["properties"]["dependencies"]["type"], "array");
+        assert_eq!(profile["properties"]["excludes"]["type"], "array");
+    }
 }
 
 #[cfg(test)]
@@ -340,4 +1039,93 @@ mod calculation_target_tests {
             CalculationTarget::new(r"c:\hello".to_string(), "world-wide".to_string())
         );
     }
+
+    #[test]
+    fn test_parse_from_setting() {
+        assert_eq!(
+            CalculationTarget::parse_from_setting("service1/"),
+            CalculationTarget::new("service1".to_string(), "default".to_string())
+        );
+        assert_eq!(
+            CalculationTarget::parse_from_setting("service1/:prof1"),
+            CalculationTarget::new("service1".to_string(), "prof1".to_string())
+        );
+        for spelling in ["./lib//core/", "lib/./core", "lib/core//"] {
+            assert_eq!(
+                CalculationTarget::parse_from_setting(spelling),
+                CalculationTarget::new("lib/core".to_string(), "default".to_string())
+            );
+        }
+        assert_eq!(
+            CalculationTarget::parse_from_setting("./:prof1"),
+            CalculationTarget::new("".to_string(), "prof1".to_string())
+        );
+        // the compiled pattern is shared, so repeated parses give the same result
+        for _ in 0..3 {
+            assert_eq!(
+                CalculationTarget::parse_from_setting("lib/core:v1.2"),
+                CalculationTarget::new("lib/core".to_string(), "v1.2".to_string())
+            );
+        }
+    }
+
+    #[test]
+    fn test_parse_dependency() {
+        for (target_path, dependency, expected) in [
+            ("services/app", "../shared", "services/shared"),
+            ("services/app", "./../shared/", "services/shared"),
+            ("services/app", "../../lib", "lib"),
+            ("services/app", "../..", ""),
+            ("services/app", "lib/../shared", "shared"),
+            ("", "lib", "lib"),
+        ] {
+            assert_eq!(
+                CalculationTarget::parse_dependency(target_path, dependency).unwrap(),
+                CalculationTarget::new(expected.to_string(), "default".to_string())
+            );
+        }
+        assert_eq!(
+            CalculationTarget::parse_dependency("services/app", "../shared:prof1").unwrap(),
+            CalculationTarget::new("services/shared".to_string(), "prof1".to_string())
+        );
+        for (target_path, dependency) in [("services/app", "../../.."), ("", ".."), ("", "a/../..")]
+        {
+            assert!(CalculationTarget::parse_dependency(target_path, dependency).is_err());
+        }
+    }
+
+    #[test]
+    fn test_split_profile_with_dots_and_slashes() {
+        assert_eq!(
+            CalculationTarget::parse("service:v1.2"),
+            CalculationTarget::new("service".to_string(), "v1.2".to_string())
+        );
+        assert_eq!(
+            CalculationTarget::parse("service:group/sub"),
+            CalculationTarget::new("service".to_string(), "group/sub".to_string())
+        );
+        assert_eq!(
+            CalculationTarget::parse(r"c:\hello:prof"),
+            CalculationTarget::new(r"c:\hello".to_string(), "prof".to_string())
+        );
+        assert_eq!(
+            CalculationTarget::parse("dir:a:v1.2"),
+            CalculationTarget::new("dir:a".to_string(), "v1.2".to_string())
+        );
+        // the last segment is not a profile name
+        for path in [
+            r"c:\hello",
+            "c:/hello",
+            "c:.",
+            "c:..",
+            "dir:with space",
+            "dir:group/",
+            "dir:",
+        ] {
+            assert_eq!(
+                CalculationTarget::parse(path),
+                CalculationTarget::new(path.to_string(), "default".to_string())
+            );
+        }
+    }
 }
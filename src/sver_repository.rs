@@ -1,65 +1,289 @@
 use std::{
     collections::{BTreeMap, BTreeSet, HashMap},
+    fmt::Display,
+    fs, io,
     path::{Component, Path, PathBuf},
 };
 
-use anyhow::Context;
-use git2::Repository;
-use log::{debug, log_enabled, Level};
+use git2::{ErrorCode, Index, ObjectType, Oid, Repository, Sort, Status, StatusOptions};
+use log::{debug, log_enabled, warn, Level};
+use rayon::iter::{IntoParallelIterator, ParallelIterator};
+use serde::Serialize;
 use sha2::{Digest, Sha256};
 
 use crate::{
-    containable,
+    attestation::{Attestations, Verification},
+    blob_cache::blob_content,
+    containable, containable_with_mode,
+    exclude::{ignore_pattern_to_exclude, Excludes},
     filemode::FileMode,
-    find_repository, relative_path,
-    sver_config::{CalculationTarget, ProfileConfig, SverConfig, ValidationResult},
-    OidAndMode, Version, SEPARATOR_BYTE, SEPARATOR_STR,
+    find_repository, is_source_of,
+    lock::{Lockfile, LOCK_FILE_NAME},
+    match_samefile_or_include_dir, normalize_text, normalize_working_tree_path, relative_path,
+    resolve_link_target,
+    sver_config::{
+        config_file_name, find_config_entry, is_transitive_dependency, is_valid_profile_name,
+        CalculationTarget, ProfileConfig, ResolvedConfig, SverConfig, ValidationResult,
+    },
+    OidAndMode, SverError, TargetSetting, Version, SEPARATOR_BYTE, SEPARATOR_STR,
 };
 
+/// Builder of [`SverRepository`] taking the path and the profile separately.
+///
+/// The version is always SHA-256, as lockfiles, attestations and shortened versions compare
+/// it as is, so the hash algorithm is not configurable.
+#[derive(Debug, Default)]
+pub struct SverRepositoryBuilder {
+    repository: Option<String>,
+    path: Option<String>,
+    profile: Option<String>,
+    options: CalcOptions,
+}
+
+impl SverRepositoryBuilder {
+    /// Root directory of the repository. The repository is opened directly instead of
+    /// searching the ancestors of `path`, and `path` is relative from this root.
+    pub fn repository(mut self, repository: impl Into<String>) -> Self {
+        self.repository = Some(repository.into());
+        self
+    }
+
+    /// Path of the target directory on disk. Defaults to the current directory.
+    pub fn path(mut self, path: impl Into<String>) -> Self {
+        self.path = Some(path.into());
+        self
+    }
+
+    /// Profile of the target. Defaults to `default`.
+    pub fn profile(mut self, profile: impl Into<String>) -> Self {
+        self.profile = Some(profile.into());
+        self
+    }
+
+    pub fn include_untracked(mut self, include_untracked: bool) -> Self {
+        self.options.include_untracked = include_untracked;
+        self
+    }
+
+    /// Replace all options including `include_untracked`.
+    pub fn options(mut self, options: CalcOptions) -> Self {
+        self.options = options;
+        self
+    }
+
+    pub fn build(self) -> Result<SverRepository, SverError> {
+        let path = self.path.unwrap_or_else(|| ".".to_string());
+        let profile = self.profile.unwrap_or_else(|| "default".to_string());
+
+        let target_path = Path::new(&path);
+        let (repo, target_path) = if let Some(repository) = self.repository {
+            let repo = Repository::open(&repository)?;
+            if target_path
+                .components()
+                .any(|c| !matches!(c, Component::Normal(_) | Component::CurDir))
+            {
+                // only paths relative from the root stay inside the repository
+                return Err(SverError::OutsideRepository { path, repository });
+            }
+            (repo, target_path.to_path_buf())
+        } else {
+            let repo = find_repository(target_path)?;
+            let target_path = relative_path(&repo, target_path)?;
+            (repo, target_path)
+        };
+        let target_path = target_path
+            .components()
+            .filter_map(|c| match c {
+                Component::Normal(os) => os.to_str(),
+                _ => None,
+            })
+            .collect::<Vec<_>>()
+            .join(SEPARATOR_STR);
+
+        let calculation_target = CalculationTarget::new(target_path, profile);
+        Ok(SverRepository::open(repo, calculation_target)?.with_options(self.options))
+    }
+}
+
 pub struct SverRepository {
     repo: Repository,
     work_dir: String,
     calculation_target: CalculationTarget,
+    options: CalcOptions,
+}
+
+/// File name of the gitignore style excludes at the repository root, applied to the sources
+/// of all targets.
+pub const IGNORE_FILE_NAME: &str = ".sverignore";
+
+// Same limit as the number of symbolic links followed in a path resolution of Linux.
+const MAX_LINK_DEPTH: usize = 40;
+// Same as PATH_MAX of Linux. Longer blobs are never regarded as symbolic links.
+const MAX_LINK_TARGET_SIZE: usize = 4096;
+
+#[derive(Clone, Debug, Default)]
+pub struct CalcOptions {
+    /// Treat executable files as regular files.
+    pub ignore_mode: bool,
+    /// Convert CRLF to LF in text files before hashing.
+    pub normalize_eol: bool,
+    /// Strip trailing whitespace of each line in text files before hashing.
+    pub strip_trailing_whitespace: bool,
+    /// Include the resolved dependency targets (path and profile) in the hash.
+    pub hash_graph: bool,
+    /// Include untracked files that are not ignored. The version is local only and
+    /// can not be reproduced from a commit.
+    pub include_untracked: bool,
+    /// Excludes relative from the repository root, applied to all sources after the
+    /// dependencies are resolved.
+    pub global_excludes: Vec<String>,
+    /// Treat a config file without `default` profile as an empty `default` profile
+    /// (the directory itself, no excludes and no dependencies) instead of an error.
+    pub lenient: bool,
+    /// Hash the content of files instead of their git object ids. Untracked files and loose
+    /// objects are streamed, so they are not loaded into memory at once. Packed objects are
+    /// inflated at once, since libgit2 can not stream them.
+    /// Text files rewritten by normalization are hashed by their normalized object id.
+    pub hash_content: bool,
+    /// How submodules are included in the version.
+    pub submodule_mode: SubmoduleMode,
+    /// Omit the target path from the version, and hash the paths of files in the target
+    /// directory relative to it. A directory moved with the same files keeps the version.
+    pub path_independent: bool,
+    /// Hash config files by their profiles without metadata such as `description`, instead
+    /// of their object ids. Editing only metadata, comments or formatting keeps the version.
+    pub ignore_config_metadata: bool,
+    /// In repositories with `core.symlinks=false`, regard a regular file whose content is a
+    /// single line relative path to a file or directory in the index as a symbolic link.
+    /// The index mode is trusted without it, since an ordinary file (e.g. `VERSION` containing
+    /// `lib1`) may look like a link.
+    pub symlinks_as_blobs: bool,
+}
+
+/// How submodules are included in the version.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum SubmoduleMode {
+    /// The pinned commit of the submodule.
+    #[default]
+    Commit,
+    /// The files checked out in the submodule, recursing into nested submodules up to
+    /// `max_depth` levels. Deeper submodules are included by their pinned commit.
+    Recurse { max_depth: usize },
+}
+
+/// Display order of the sources listed by [`SverRepository::list_sources_sorted`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum SortMode {
+    /// Sorted by the raw bytes of the paths, the order in which sources are hashed.
+    #[default]
+    Bytes,
+    /// Sorted ignoring case. Paths equal except for case are kept in byte order.
+    CaseInsensitive,
+}
+
+/// Options of the generated config file.
+#[derive(Clone, Debug, Default)]
+pub struct InitOptions {
+    /// Profiles written as sections in this order. Only `default` if empty.
+    pub profiles: Vec<String>,
+    /// Write sibling directories of the target as commented out dependencies.
+    pub scan: bool,
+}
+
+impl CalcOptions {
+    /// All normalizations for a version that is stable regardless of cosmetic differences.
+    pub fn normalized() -> Self {
+        Self {
+            ignore_mode: true,
+            normalize_eol: true,
+            strip_trailing_whitespace: true,
+            ..Default::default()
+        }
+    }
+
+    fn normalize_content(&self) -> bool {
+        self.normalize_eol || self.strip_trailing_whitespace
+    }
 }
 
 impl SverRepository {
-    pub fn new(path: &str) -> anyhow::Result<Self> {
-        let calculation_target = CalculationTarget::parse(path);
+    /// Create from `path:profile` string. The profile is optional.
+    pub fn new(path: &str) -> Result<Self, SverError> {
+        let CalculationTarget { path, profile } = CalculationTarget::parse(path);
+        Self::builder().path(path).profile(profile).build()
+    }
 
-        let target_path = Path::new(&calculation_target.path);
-        let repo = find_repository(target_path)?;
-        let target_path = relative_path(&repo, target_path)?;
-        let target_path = target_path
-            .iter()
-            .flat_map(|os| os.to_str())
-            .collect::<Vec<_>>()
-            .join(SEPARATOR_STR);
+    pub fn builder() -> SverRepositoryBuilder {
+        SverRepositoryBuilder::default()
+    }
+
+    /// Create from a parsed target without parsing `path:profile` string.
+    ///
+    /// `path_on_disk` is a path in the repository used to find the repository,
+    /// and `target.path` is a relative path from the repository root.
+    pub fn new_with_target(
+        path_on_disk: &str,
+        target: CalculationTarget,
+    ) -> Result<Self, SverError> {
+        let repo = find_repository(Path::new(path_on_disk))?;
+        let target_path = target.path.trim_matches('/');
+        let target_path = if target_path == "." { "" } else { target_path };
+        Self::open(
+            repo,
+            CalculationTarget::new(target_path.to_string(), target.profile),
+        )
+    }
+
+    /// Create from an already opened repository without searching the repository again.
+    ///
+    /// `target.path` is used as is, so the caller is responsible for it being a relative path
+    /// from the repository root (`""` for the root) without leading or trailing separators.
+    pub fn from_repository(repo: Repository, target: CalculationTarget) -> Result<Self, SverError> {
+        Self::open(repo, target)
+    }
+
+    fn open(repo: Repository, calculation_target: CalculationTarget) -> Result<Self, SverError> {
         let work_dir = repo
             .workdir()
-            .and_then(|p| p.to_str())
-            .with_context(|| "bare repository")?
+            .ok_or(SverError::BareRepositoryUnsupported)?
+            .to_str()
+            .ok_or_else(|| SverError::non_utf8_path(repo.path().as_os_str().as_encoded_bytes()))?
             .to_string();
         debug!("repository_root:{}", work_dir);
-        debug!("target_path:{}", target_path);
+        debug!("target_path:{}", calculation_target.path);
 
-        let calculation_target = CalculationTarget::new(target_path, calculation_target.profile);
         Ok(Self {
             repo,
             work_dir,
             calculation_target,
+            options: CalcOptions::default(),
         })
     }
 
+    pub fn with_options(mut self, options: CalcOptions) -> Self {
+        self.options = options;
+        self
+    }
+
     pub fn work_dir(&self) -> &str {
         &self.work_dir
     }
 
-    pub fn contain_directories(&self, dirs: Vec<String>) -> anyhow::Result<Vec<String>> {
-        let prefix = self.repo.workdir().with_context(|| "get workdir")?;
+    // The `.git` directory, which has the index.
+    #[cfg(feature = "watch")]
+    pub(crate) fn git_dir(&self) -> &Path {
+        self.repo.path()
+    }
+
+    pub fn contain_directories(&self, dirs: Vec<String>) -> Result<Vec<String>, SverError> {
+        let prefix = self
+            .repo
+            .workdir()
+            .ok_or(SverError::BareRepositoryUnsupported)?;
         let mut temp_dirs = BTreeSet::<String>::new();
         temp_dirs.extend(dirs);
         let mut result = BTreeSet::<String>::new();
-        self.repo.index()?.iter().for_each(|entry| {
+        self.index()?.iter().for_each(|entry| {
             if entry.path.starts_with(".git".as_bytes()) {
                 debug!(
                     "git path:{:?}",
@@ -87,21 +311,141 @@ impl SverRepository {
         Ok(result.into_iter().collect())
     }
 
-    pub fn init_sver_config(&self) -> anyhow::Result<String> {
+    pub fn init_sver_config(&self) -> Result<String, SverError> {
+        self.init_sver_config_with(&InitOptions::default())
+    }
+
+    /// Generate the config file with the profiles and suggestions of `options`.
+    pub fn init_sver_config_with(&self, options: &InitOptions) -> Result<String, SverError> {
+        let default_profiles = ["default".to_string()];
+        let profiles = if options.profiles.is_empty() {
+            &default_profiles[..]
+        } else {
+            &options.profiles[..]
+        };
+        for (i, profile) in profiles.iter().enumerate() {
+            if !is_valid_profile_name(profile) {
+                return Err(SverError::InvalidConfig(format!(
+                    "invalid profile name. profile:{profile}"
+                )));
+            }
+            if profiles[..i].contains(profile) {
+                return Err(SverError::InvalidConfig(format!(
+                    "duplicated profile. profile:{profile}"
+                )));
+            }
+        }
+        let suggestions = if options.scan {
+            self.sibling_directories(&self.index()?)?
+        } else {
+            Vec::new()
+        };
+        self.write_sver_config(&SverConfig::initial_config(profiles, &suggestions))
+    }
+
+    /// Generate the config of the target depending on `files`, such as the files accessed by a
+    /// command.
+    ///
+    /// `files` are relative from the repository root. Files in the target directory are omitted
+    /// because they are sources already, and a directory is used instead of its files when all
+    /// files of the directory in the index are in `files`.
+    pub fn config_from_files(&self, files: &[String]) -> Result<String, SverError> {
+        let dependencies = self.collapse_files(&self.index()?, files)?;
+        Ok(SverConfig::dependencies_config(&dependencies))
+    }
+
+    /// Write the config generated by [`Self::config_from_files`] to the target directory.
+    pub fn init_sver_config_from_files(&self, files: &[String]) -> Result<String, SverError> {
+        self.write_sver_config(&self.config_from_files(files)?)
+    }
+
+    fn collapse_files(&self, index: &Index, files: &[String]) -> Result<Vec<String>, SverError> {
+        let target = Path::new(&self.calculation_target.path);
+        let files = files
+            .iter()
+            .map(PathBuf::from)
+            .filter(|file| !file.starts_with(target))
+            .collect::<BTreeSet<_>>();
+        // (files in the index, files in `files`) of each directory except the root
+        let mut counts = HashMap::<PathBuf, (usize, usize)>::new();
+        for entry in index.iter() {
+            let path = PathBuf::from(path_string(&entry.path)?);
+            let accessed = files.contains(&path);
+            for dir in path.ancestors().skip(1) {
+                if dir.as_os_str().is_empty() {
+                    break;
+                }
+                let count = counts.entry(dir.to_path_buf()).or_default();
+                count.0 += 1;
+                count.1 += usize::from(accessed);
+            }
+        }
+        let mut dependencies = BTreeSet::new();
+        for file in &files {
+            // the outermost directory whose files are all in `files`
+            let collapsed = file
+                .ancestors()
+                .skip(1)
+                .filter(|dir| !dir.as_os_str().is_empty())
+                .filter(|dir| counts.get(*dir).is_some_and(|(all, found)| all == found))
+                .last()
+                .unwrap_or(file);
+            dependencies.insert(
+                collapsed
+                    .iter()
+                    .flat_map(|os| os.to_str())
+                    .collect::<Vec<_>>()
+                    .join(SEPARATOR_STR),
+            );
+        }
+        Ok(dependencies.into_iter().collect())
+    }
+
+    fn sibling_directories(&self, index: &Index) -> Result<Vec<String>, SverError> {
+        let target = Path::new(&self.calculation_target.path);
+        let Some(parent) = target.parent() else {
+            // the repository root has no siblings
+            return Ok(Vec::new());
+        };
+        let mut siblings = BTreeSet::new();
+        for entry in index.iter() {
+            let path = PathBuf::from(path_string(&entry.path)?);
+            let Ok(relative) = path.strip_prefix(parent) else {
+                continue;
+            };
+            let mut components = relative.components();
+            let (Some(dir), Some(_)) = (components.next(), components.next()) else {
+                // files directly under the parent
+                continue;
+            };
+            let dir = parent.join(dir);
+            if dir != target {
+                siblings.insert(
+                    dir.iter()
+                        .flat_map(|os| os.to_str())
+                        .collect::<Vec<_>>()
+                        .join(SEPARATOR_STR),
+                );
+            }
+        }
+        Ok(siblings.into_iter().collect())
+    }
+
+    fn write_sver_config(&self, content: &str) -> Result<String, SverError> {
         debug!("path:{}", self.calculation_target.path);
+        if let Some(entry) = find_config_entry(&self.index()?, &self.calculation_target.path)? {
+            let file_name = config_file_name(&entry.path).unwrap_or("sver.toml");
+            return Ok(format!("{file_name} already exists"));
+        }
         let mut path_buf = PathBuf::new();
         path_buf.push(&self.calculation_target.path);
         path_buf.push("sver.toml");
         let config_path = path_buf.as_path();
 
-        if self.repo.index()?.get_path(config_path, 0).is_some() {
-            return Ok("sver.toml already exists".into());
-        }
-
         let mut fs_path = PathBuf::new();
         fs_path.push(&self.work_dir);
         fs_path.push(config_path);
-        if !SverConfig::write_initial_config(fs_path.as_path())? {
+        if !SverConfig::write_initial_config(fs_path.as_path(), content)? {
             return Ok(format!(
                 "sver.toml already exists, but is not committed. path:{}",
                 self.calculation_target.path
@@ -113,190 +457,1252 @@ impl SverRepository {
         ))
     }
 
-    pub fn validate_sver_config(&self) -> anyhow::Result<ValidationResults> {
-        let configs = SverConfig::load_all_configs(&self.repo)?;
+    pub fn validate_sver_config(&self) -> Result<ValidationResults, SverError> {
+        let mut results = Vec::new();
+        self.validate_streaming(|result| results.push(result))?;
+        Ok(ValidationResults::new(results))
+    }
+
+    /// Validate the config files of the commit `revision` points to instead of the current index.
+    /// Dependencies and excludes are also checked against the entries of the commit.
+    pub fn validate_sver_config_at(&self, revision: &str) -> Result<ValidationResults, SverError> {
+        let mut results = Vec::new();
+        self.validate_streaming_at(revision, |result| results.push(result))?;
+        Ok(ValidationResults::new(results))
+    }
+
+    /// Validate all config files, calling `callback` with each result as soon as it is produced.
+    pub fn validate_streaming(
+        &self,
+        callback: impl FnMut(ValidationResult),
+    ) -> Result<(), SverError> {
+        self.validate_index_streaming(&self.index()?, callback)
+    }
+
+    /// [`SverRepository::validate_streaming`] for the commit `revision` points to.
+    pub fn validate_streaming_at(
+        &self,
+        revision: &str,
+        callback: impl FnMut(ValidationResult),
+    ) -> Result<(), SverError> {
+        self.validate_index_streaming(&self.index_at(revision)?, callback)
+    }
+
+    fn validate_index_streaming(
+        &self,
+        index: &Index,
+        mut callback: impl FnMut(ValidationResult),
+    ) -> Result<(), SverError> {
+        let mut configs = Vec::new();
+        for config in SverConfig::load_all_configs_with_errors(&self.repo, index)? {
+            match config {
+                Ok(config) => configs.push(config),
+                Err(e) => callback(ValidationResult::SyntaxError(e)),
+            }
+        }
         if log_enabled!(Level::Debug) {
             configs
                 .iter()
                 .for_each(|config| debug!("{}", config.config_file_path()));
         }
-        let index = self.repo.index()?;
-        let results: Vec<ValidationResult> = configs
+        for sver_config in &configs {
+            let target_path = &sver_config.target_path;
+            for (profile, config) in sver_config.iter() {
+                callback(config.validate(target_path, profile, index, &self.repo, &configs));
+            }
+        }
+        let unmatched_patterns = self.unmatched_ignore_patterns(index)?;
+        if !unmatched_patterns.is_empty() {
+            callback(ValidationResult::UnmatchedIgnore {
+                ignore_file: IGNORE_FILE_NAME.to_string(),
+                unmatched_patterns,
+            });
+        }
+        Ok(())
+    }
+
+    /// The profile of the target as used for the calculation, with the paths in the canonical
+    /// form. An empty profile without config file.
+    pub fn resolved_config(&self) -> Result<ResolvedConfig, SverError> {
+        let target = &self.calculation_target;
+        let config = self
+            .load_target_config(&self.index()?, target)?
+            .unwrap_or_default();
+        ResolvedConfig::new(target.clone(), config)
+    }
+
+    pub fn dependency_graph(&self) -> Result<DependencyGraph, SverError> {
+        let configs = SverConfig::load_all_configs(&self.repo, &self.index()?)?;
+        let mut graph = DependencyGraph::default();
+        for sver_config in &configs {
+            for (profile, config) in sver_config.iter() {
+                let target =
+                    CalculationTarget::new(sver_config.target_path.clone(), profile.clone());
+                graph.nodes.insert(target.clone());
+                for dependency in &config.dependencies {
+                    let dependency_target =
+                        CalculationTarget::parse_dependency(&sver_config.target_path, dependency)?;
+                    graph.nodes.insert(dependency_target.clone());
+                    graph.edges.insert((target.clone(), dependency_target));
+                }
+            }
+        }
+        Ok(graph)
+    }
+
+    /// List the profiles of the target directory: every profile of its config file, or only
+    /// `default` without config file.
+    pub fn list_profiles(&self) -> Result<Vec<String>, SverError> {
+        let profiles = self
+            .list_targets()?
+            .into_iter()
+            .filter(|target| target.path == self.calculation_target.path)
+            .map(|target| target.profile)
+            .collect::<Vec<_>>();
+        if profiles.is_empty() {
+            return Ok(vec!["default".to_string()]);
+        }
+        Ok(profiles)
+    }
+
+    /// Calculate the version of every profile of the target directory, sorted by the profile.
+    /// The profile of this target is ignored.
+    pub fn calc_versions_of_all_profiles(&self) -> Result<Vec<Version>, SverError> {
+        self.list_profiles()?
+            .into_iter()
+            .map(|profile| {
+                let target = CalculationTarget::new(self.calculation_target.path.clone(), profile);
+                Self::open(Repository::open(self.repo.path())?, target)?
+                    .with_options(self.options.clone())
+                    .calc_version()
+            })
+            .collect()
+    }
+
+    /// List all calculable targets in the repository.
+    ///
+    /// Targets are every profile of every config file, and the default profile of
+    /// directories without config file that are referenced as dependencies.
+    pub fn list_targets(&self) -> Result<Vec<CalculationTarget>, SverError> {
+        self.list_targets_of_index(&self.index()?)
+    }
+
+    /// List the targets whose version differs between two revisions.
+    ///
+    /// Targets are listed at `to`. Targets which did not exist at `from` are also changed.
+    pub fn changed_targets(
+        &self,
+        from: &str,
+        to: &str,
+    ) -> Result<Vec<CalculationTarget>, SverError> {
+        let from_index = self.index_at(from)?;
+        let from_targets = self.list_targets_of_index(&from_index)?;
+        let to_index = self.index_at(to)?;
+        let mut changed = Vec::new();
+        for target in self.list_targets_of_index(&to_index)? {
+            let target_repo = Self::open(Repository::open(self.repo.path())?, target.clone())?
+                .with_options(self.options.clone());
+            let to_version = target_repo.calc_version_of_index(&to_index)?;
+            let is_changed = !from_targets.contains(&target)
+                || match target_repo.calc_version_of_index(&from_index) {
+                    Ok(from_version) => from_version.version != to_version.version,
+                    // the profile was added after `from`
+                    Err(SverError::ProfileNotFound(_)) => true,
+                    Err(e) => return Err(e),
+                };
+            if is_changed {
+                changed.push(target);
+            }
+        }
+        Ok(changed)
+    }
+
+    /// Calculate the full versions of all targets listed by [`Self::list_targets`].
+    pub fn lock_versions(&self) -> Result<Lockfile, SverError> {
+        let mut lockfile = Lockfile::default();
+        for target in self.list_targets()? {
+            let target_repo = Self::open(Repository::open(self.repo.path())?, target.clone())?
+                .with_options(self.options.clone());
+            lockfile
+                .versions
+                .insert(target.to_string(), target_repo.calc_version()?.version);
+        }
+        Ok(lockfile)
+    }
+
+    /// Calculate a single version of the repository from the versions of all targets.
+    /// See [`Lockfile::aggregate_version`].
+    pub fn calc_aggregate_version(&self) -> Result<String, SverError> {
+        Ok(self.lock_versions()?.aggregate_version())
+    }
+
+    fn list_targets_of_index(&self, index: &Index) -> Result<Vec<CalculationTarget>, SverError> {
+        let configs = SverConfig::load_all_configs(&self.repo, index)?;
+        let mut targets = BTreeSet::new();
+        for sver_config in &configs {
+            for (profile, config) in sver_config.iter() {
+                targets.insert(CalculationTarget::new(
+                    sver_config.target_path.clone(),
+                    profile.clone(),
+                ));
+                for dependency in &config.dependencies {
+                    let dependency_target =
+                        CalculationTarget::parse_dependency(&sver_config.target_path, dependency)?;
+                    let has_config = configs
+                        .iter()
+                        .any(|c| c.target_path == dependency_target.path);
+                    let dir_prefix = [dependency_target.path.as_bytes(), SEPARATOR_BYTE].concat();
+                    let is_dir = index
+                        .iter()
+                        .any(|entry| entry.path.starts_with(dir_prefix.as_slice()));
+                    if !has_config && is_dir && dependency_target.profile == "default" {
+                        targets.insert(dependency_target);
+                    }
+                }
+            }
+        }
+        Ok(targets.into_iter().collect())
+    }
+
+    /// Descriptions of the profiles of all config files in the repository.
+    /// Profiles without description are not included.
+    pub fn target_descriptions(&self) -> Result<BTreeMap<CalculationTarget, String>, SverError> {
+        let configs = SverConfig::load_all_configs(&self.repo, &self.index()?)?;
+        Ok(configs
             .iter()
             .flat_map(|sver_config| {
-                let target_path = sver_config.target_path.clone();
-                sver_config
-                    .iter()
-                    .map(|(profile, config)| {
-                        config.validate(&target_path, profile, &index, &self.repo, &configs)
-                    })
-                    .collect::<Vec<ValidationResult>>()
+                sver_config.iter().filter_map(|(profile, config)| {
+                    let description = config.description.clone()?;
+                    let target =
+                        CalculationTarget::new(sver_config.target_path.clone(), profile.clone());
+                    Some((target, description))
+                })
             })
-            .collect();
-        let has_invalid = results
+            .collect())
+    }
+
+    /// Sources of the target, sorted by the raw bytes of the paths.
+    pub fn list_sources(&self) -> Result<Vec<String>, SverError> {
+        self.sources_iter()?.collect()
+    }
+
+    /// Same as [`Self::list_sources`] but converts the paths to strings one by one, so the
+    /// caller does not hold all of them at once.
+    pub fn sources_iter(
+        &self,
+    ) -> Result<impl Iterator<Item = Result<String, SverError>>, SverError> {
+        let index = self.index()?;
+        let path_set = self.collect_path_set(&index)?;
+        let entries = self.list_working_entries(&index, &path_set)?;
+        Ok(entries.into_keys().map(|path| path_string(&path)))
+    }
+
+    /// Sources of the target with their object ids and file modes, sorted by the raw bytes
+    /// of the paths.
+    pub fn list_source_entries(&self) -> Result<Vec<SourceEntry>, SverError> {
+        let index = self.index()?;
+        let path_set = self.collect_path_set(&index)?;
+        let entries = self.list_working_entries(&index, &path_set)?;
+        entries
+            .into_iter()
+            .map(|(path, OidAndMode { oid, mode })| {
+                Ok(SourceEntry {
+                    path: path_string(&path)?,
+                    oid,
+                    mode,
+                })
+            })
+            .collect()
+    }
+
+    /// List the sources with the target which included each of them.
+    ///
+    /// When a source belongs to multiple targets, the most direct one is reported: the calculated
+    /// target, then dependencies, then symbolic links, and the smallest `path:profile` among them.
+    pub fn source_provenance(&self) -> Result<Vec<SourceProvenance>, SverError> {
+        let index = self.index()?;
+        let path_set = self.collect_path_set(&index)?;
+        let mut targets = path_set.iter().collect::<Vec<_>>();
+        targets.sort_by(|(a, a_setting), (b, b_setting)| {
+            (a_setting.reason, a).cmp(&(b_setting.reason, b))
+        });
+        let entries = self.list_working_entries(&index, &path_set)?;
+        let mut provenance = Vec::with_capacity(entries.len());
+        for path in entries.keys() {
+            // every source belongs to some target
+            let Some((target, setting)) = targets
+                .iter()
+                .find(|(target, setting)| is_source_of(path, target, setting))
+            else {
+                continue;
+            };
+            provenance.push(SourceProvenance {
+                path: path_string(path)?,
+                target: (*target).clone(),
+                reason: setting.reason,
+            });
+        }
+        Ok(provenance)
+    }
+
+    /// Same as [`Self::list_sources`] but sorted by `sort_mode`.
+    ///
+    /// Only the display order changes. Sources are always hashed in byte order, so the version
+    /// does not depend on `sort_mode`.
+    pub fn list_sources_sorted(&self, sort_mode: SortMode) -> Result<Vec<String>, SverError> {
+        let mut sources = self.list_sources()?;
+        match sort_mode {
+            SortMode::Bytes => {}
+            // the sort is stable, so the byte order is kept among paths equal ignoring case
+            SortMode::CaseInsensitive => sources.sort_by_cached_key(|path| path.to_lowercase()),
+        }
+        Ok(sources)
+    }
+
+    /// Recompute the version and compare it with `expected`.
+    ///
+    /// Without `expected`, the version of the target in the committed attestation file
+    /// (`sver-versions.toml` at the repository root) is used.
+    /// The expected version may be shortened, like versions printed by `calc`.
+    pub fn verify_version(&self, expected: Option<&str>) -> Result<Verification, SverError> {
+        let index = self.index()?;
+        let expected = match expected {
+            Some(expected) => expected.to_string(),
+            None => {
+                let attested = Attestations::load(&self.repo, &index)?
+                    .and_then(|a| a.get(&self.calculation_target).map(|v| v.to_string()));
+                match attested {
+                    Some(expected) => expected,
+                    None => return Ok(Verification::MissingAttestation),
+                }
+            }
+        };
+        let actual = self.calc_version()?.version;
+        Ok(Verification::compare(&expected, actual))
+    }
+
+    /// Whether the version is still `expected`, which may be shortened.
+    /// Same as [`SverRepository::verify_version`] with the expected version.
+    pub fn verify(&self, expected: &str) -> Result<bool, SverError> {
+        Ok(self.verify_version(Some(expected))?.is_verified())
+    }
+
+    /// List the sources with a digest of each file.
+    ///
+    /// A digest is the SHA-256 of the bytes folded into the version for the file
+    /// (path, file mode and oid). The version is the SHA-256 of the target path followed by
+    /// these bytes of all sources in this order, and environment variables and the
+    /// dependency graph if configured.
+    ///
+    /// The version hashes the bytes themselves, not the digests, so the digests can not be
+    /// combined into the version. Use them to compare or cache each file.
+    pub fn source_digests(&self) -> Result<Vec<SourceDigest>, SverError> {
+        let index = self.index()?;
+        let path_set = self.collect_path_set(&index)?;
+        let entries = self.list_working_entries(&index, &path_set)?;
+        entries
             .iter()
-            .any(|s| matches!(s, ValidationResult::Invalid { .. }));
-        Ok(ValidationResults {
-            has_invalid,
-            results,
+            .map(|(path, oid_and_mode)| {
+                let mut hasher = Sha256::default();
+                self.fold_entry(&mut hasher, path, oid_and_mode)?;
+                Ok(SourceDigest {
+                    path: path_string(path)?,
+                    mode: oid_and_mode.mode,
+                    digest: format!("{:#x}", hasher.finalize()),
+                })
+            })
+            .collect()
+    }
+
+    /// Calculate the version, the sources and their statistics in a single pass over the index.
+    pub fn calc_report(&self) -> Result<CalcReport, SverError> {
+        let index = self.index()?;
+        let path_set = self.collect_path_set(&index)?;
+        let entries = self.list_working_entries(&index, &path_set)?;
+        let version = self.version_of_entries(&entries, &path_set)?;
+
+        let mut sources = Vec::with_capacity(entries.len());
+        let mut total_bytes = 0;
+        for (path, oid_and_mode) in &entries {
+            sources.push(path_string(path)?);
+            total_bytes += self.entry_size(path, oid_and_mode)?;
+        }
+        let mut config_files = Vec::new();
+        for target in path_set.keys() {
+            if let Some(entry) = find_config_entry(&index, &target.path)? {
+                config_files.push(path_string(&entry.path)?);
+            }
+        }
+        config_files.sort();
+        config_files.dedup();
+
+        Ok(CalcReport {
+            version,
+            source_count: sources.len(),
+            sources,
+            total_bytes,
+            config_files,
         })
     }
 
-    pub fn list_sources(&self) -> anyhow::Result<Vec<String>> {
-        let entries = self.list_sorted_entries()?;
-        let result = entries
-            .keys()
-            .map(|path| String::from_utf8(path.clone()).unwrap())
-            .collect();
-        Ok(result)
+    // Size of the content. Submodules have no content in the repository.
+    fn entry_size(&self, path: &[u8], oid_and_mode: &OidAndMode) -> Result<u64, SverError> {
+        if oid_and_mode.mode == FileMode::Commit {
+            return Ok(0);
+        }
+        match self.repo.odb()?.read_header(oid_and_mode.oid) {
+            Ok((size, _)) => Ok(size as u64),
+            // untracked files are not in the object database
+            Err(e) if e.code() == ErrorCode::NotFound => {
+                let full_path = Path::new(&self.work_dir).join(path_string(path)?);
+                Ok(fs::symlink_metadata(full_path)?.len())
+            }
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    pub fn calc_version(&self) -> Result<Version, SverError> {
+        let index = self.index()?;
+        let path_set = self.collect_path_set(&index)?;
+        let entries = self.list_working_entries(&index, &path_set)?;
+        self.version_of_entries(&entries, &path_set)
+    }
+
+    /// Calculate the version of the commit `revision` points to instead of the current index.
+    /// Config files are also read from the commit.
+    /// Only the path, the mode and the object id of the entries are hashed, so the version is
+    /// the same as [`SverRepository::calc_version`] when the index matches the commit.
+    pub fn calc_version_at(&self, revision: &str) -> Result<Version, SverError> {
+        self.calc_version_of_index(&self.index_at(revision)?)
+    }
+
+    /// Calculate the versions at the commits of `range` from the oldest, like `git log --reverse`.
+    ///
+    /// `range` is `<from>..<to>`, or a revision for all of its history. A commit is listed only
+    /// when the version differs from the previously listed commit, and commits without the
+    /// profile are skipped.
+    pub fn calc_versions_in_range(&self, range: &str) -> Result<Vec<CommitVersion>, SverError> {
+        let mut revwalk = self.repo.revwalk()?;
+        revwalk.set_sorting(Sort::TOPOLOGICAL | Sort::REVERSE)?;
+        if range.contains("..") {
+            revwalk.push_range(range)?;
+        } else {
+            revwalk.push(self.repo.revparse_single(range)?.peel_to_commit()?.id())?;
+        }
+        let mut versions: Vec<CommitVersion> = Vec::new();
+        for oid in revwalk {
+            let commit = oid?.to_string();
+            let version = match self.calc_version_of_index(&self.index_at(&commit)?) {
+                Ok(version) => version,
+                Err(SverError::ProfileNotFound(profile)) => {
+                    debug!("profile is not found. commit:{commit}, profile:{profile}");
+                    continue;
+                }
+                Err(e) => return Err(e),
+            };
+            if versions
+                .last()
+                .is_some_and(|last| last.version.version == version.version)
+            {
+                debug!("version is unchanged. commit:{commit}");
+                continue;
+            }
+            versions.push(CommitVersion { commit, version });
+        }
+        Ok(versions)
+    }
+
+    /// Calculate the versions of HEAD and the current index to preview whether committing changes the version.
+    /// `head` is `None` before the first commit.
+    pub fn preview_version(&self) -> Result<VersionPreview, SverError> {
+        let head = match self.repo.head() {
+            Ok(_) => Some(self.calc_version_at("HEAD")?),
+            Err(e) if e.code() == ErrorCode::UnbornBranch => None,
+            Err(e) => return Err(e.into()),
+        };
+        let index = self.calc_version()?;
+        Ok(VersionPreview { head, index })
     }
 
-    pub fn calc_version(&self) -> anyhow::Result<Version> {
-        let entries = self.list_sorted_entries()?;
-        let version = self.calc_hash_string(&entries)?;
+    /// Compare the sources of two revisions.
+    pub fn diff_sources(&self, from: &str, to: &str) -> Result<SourceDiff, SverError> {
+        let from_index = self.index_at(from)?;
+        let from_entries =
+            self.list_sorted_entries(&from_index, &self.collect_path_set(&from_index)?)?;
+        let to_index = self.index_at(to)?;
+        let to_entries = self.list_sorted_entries(&to_index, &self.collect_path_set(&to_index)?)?;
+
+        let mut diff = SourceDiff::default();
+        for (path, from_entry) in &from_entries {
+            match to_entries.get(path) {
+                None => diff.removed.push(path_string(path)?),
+                Some(to_entry) if to_entry != from_entry => diff.modified.push(path_string(path)?),
+                Some(_) => {}
+            }
+        }
+        for path in to_entries.keys() {
+            if !from_entries.contains_key(path) {
+                diff.added.push(path_string(path)?);
+            }
+        }
+        Ok(diff)
+    }
+
+    /// Calculate the version with the number of its sources and their total size.
+    pub fn calc_version_with_stats(&self) -> Result<(Version, SourceStats), SverError> {
+        let details = self.calc_version_with_details()?;
+        Ok((details.version, details.stats))
+    }
+
+    /// Calculate the version with the targets folded into it and the statistics of its sources,
+    /// collecting the sources only once.
+    pub fn calc_version_with_details(&self) -> Result<VersionDetails, SverError> {
+        let index = self.index()?;
+        let path_set = self.collect_path_set(&index)?;
+        let entries = self.list_working_entries(&index, &path_set)?;
+        let version = self.version_of_entries(&entries, &path_set)?;
+        let mut stats = SourceStats {
+            files: entries.len(),
+            bytes: 0,
+        };
+        for (path, oid_and_mode) in &entries {
+            stats.bytes += self.entry_size(path, oid_and_mode)?;
+        }
+        let mut contributing_targets = path_set.into_keys().collect::<Vec<_>>();
+        contributing_targets.sort();
+        Ok(VersionDetails {
+            version,
+            contributing_targets,
+            stats,
+        })
+    }
+
+    fn calc_version_of_index(&self, index: &Index) -> Result<Version, SverError> {
+        let path_set = self.collect_path_set(index)?;
+        let entries = self.list_sorted_entries(index, &path_set)?;
+        self.version_of_entries(&entries, &path_set)
+    }
+
+    fn version_of_entries(
+        &self,
+        entries: &BTreeMap<Vec<u8>, OidAndMode>,
+        path_set: &HashMap<CalculationTarget, TargetSetting>,
+    ) -> Result<Version, SverError> {
+        let version = self.calc_hash_string(entries, path_set)?;
 
         let version = Version {
             repository_root: self.work_dir.clone(),
             path: self.calculation_target.path.clone(),
+            profile: self.calculation_target.profile.clone(),
             version,
         };
         Ok(version)
     }
 
-    fn calc_hash_string(&self, source: &BTreeMap<Vec<u8>, OidAndMode>) -> anyhow::Result<String> {
+    // The index of the repository, reloaded if it was changed on disk after opened.
+    fn index(&self) -> Result<Index, SverError> {
+        let mut index = self.repo.index()?;
+        index.read(false)?;
+        Ok(index)
+    }
+
+    // An in-memory index built from the tree of the commit.
+    // Sources are collected from it in the same way as from the index on disk.
+    fn index_at(&self, revision: &str) -> Result<Index, SverError> {
+        let tree = self
+            .repo
+            .revparse_single(revision)?
+            .peel_to_commit()?
+            .tree()?;
+        let mut index = Index::new()?;
+        index.read_tree(&tree)?;
+        Ok(index)
+    }
+
+    /// Count the files each exclude removed from the sources of its target.
+    /// Excludes which removed no files are reported with zero and can be cleaned up.
+    ///
+    /// Only files missing from the sources are counted. A file re-included by a `!` exclude or
+    /// included by another target is not removed by the exclude.
+    pub fn report_excludes(&self) -> Result<Vec<ExcludeReport>, SverError> {
+        let index = self.index()?;
+        let path_set = self.collect_path_set(&index)?;
+        let sources = self.list_working_entries(&index, &path_set)?;
+        let mut counts = path_set
+            .iter()
+            .map(|(target, setting)| (target, vec![0; setting.excludes.rules().len()]))
+            .collect::<HashMap<_, _>>();
+        let mut last_path: Option<Vec<u8>> = None;
+        for entry in index.iter() {
+            // conflicted entries share the same path
+            if last_path.as_ref() == Some(&entry.path) || sources.contains_key(&entry.path) {
+                continue;
+            }
+            for (target, setting) in &path_set {
+                if !match_samefile_or_include_dir(&entry.path, target.path.as_bytes()) {
+                    continue;
+                }
+                if let Some(index) = setting.excludes.excluded_by(&entry.path) {
+                    counts.get_mut(target).unwrap()[index] += 1;
+                }
+            }
+            last_path = Some(entry.path);
+        }
+
+        let mut targets = path_set.keys().collect::<Vec<_>>();
+        targets.sort();
+        let mut reports = Vec::new();
+        for target in targets {
+            let rules = path_set[target].excludes.rules();
+            for (rule, count) in rules.iter().zip(&counts[target]) {
+                if rule.is_negated() {
+                    continue;
+                }
+                reports.push(ExcludeReport {
+                    calculation_target: target.clone(),
+                    exclude: rule.source().to_string(),
+                    excluded_files: *count,
+                });
+            }
+        }
+        Ok(reports)
+    }
+
+    fn calc_hash_string(
+        &self,
+        source: &BTreeMap<Vec<u8>, OidAndMode>,
+        path_set: &HashMap<CalculationTarget, TargetSetting>,
+    ) -> Result<String, SverError> {
         let mut hasher = Sha256::default();
-        hasher.update(self.calculation_target.path.as_bytes());
+        if !self.options.path_independent {
+            hasher.update(self.calculation_target.path.as_bytes());
+        }
         for (path, oid_and_mode) in source {
-            hasher.update(path);
-            match oid_and_mode.mode {
-                FileMode::Blob | FileMode::BlobExecutable | FileMode::Link => {
-                    // Q. Why little endian?
-                    // A. no reason.
-                    hasher.update(u32::from(oid_and_mode.mode).to_le_bytes());
+            self.fold_entry(&mut hasher, path, oid_and_mode)?;
+        }
+        // Environment variables are folded after the files, sorted by name.
+        let env_names = path_set
+            .values()
+            .flat_map(|setting| setting.env.iter())
+            .collect::<BTreeSet<_>>();
+        for name in env_names {
+            hasher.update(name.as_bytes());
+            match std::env::var_os(name) {
+                Some(value) => {
+                    debug!("env:{}={:?}", name, value);
+                    hasher.update(b"=");
+                    hasher.update(value.as_encoded_bytes());
+                }
+                None => {
+                    debug!("env:{} is unset", name);
+                    hasher.update(b"\0unset");
+                }
+            }
+            hasher.update(b"\n");
+        }
+        if self.options.hash_graph {
+            let mut targets = path_set.keys().collect::<Vec<_>>();
+            targets.sort();
+            for target in targets {
+                debug!("graph target:{}", target);
+                hasher.update(format!("{target}\n").as_bytes());
+            }
+        }
+        let hash = format!("{:#x}", hasher.finalize());
+        Ok(hash)
+    }
+
+    // Fold a source file into the hasher.
+    fn fold_entry(
+        &self,
+        hasher: &mut Sha256,
+        path: &[u8],
+        oid_and_mode: &OidAndMode,
+    ) -> Result<(), SverError> {
+        let hashed_path = self.hashed_path(path);
+        hasher.update(hashed_path);
+        match oid_and_mode.mode {
+            FileMode::Blob | FileMode::BlobExecutable | FileMode::Link => {
+                let original_oid = oid_and_mode.oid;
+                let oid_and_mode = self.normalize_entry(path, oid_and_mode)?;
+                // Q. Why little endian?
+                // A. no reason.
+                hasher.update(u32::from(oid_and_mode.mode).to_le_bytes());
+                if self.options.hash_content && oid_and_mode.oid == original_oid {
+                    self.fold_content(hasher, path, oid_and_mode.mode, original_oid)?;
+                } else {
                     hasher.update(oid_and_mode.oid);
-                    debug!(
-                        "path:{}, mode:{:?}, oid:{}",
-                        String::from_utf8(path.clone())?,
-                        oid_and_mode.mode,
-                        oid_and_mode.oid
-                    )
                 }
-                // Commit (For submodules, include the commit hash in the calculation source.)
-                FileMode::Commit => {
+                debug!(
+                    "path:{}, mode:{:?}, oid:{}",
+                    String::from_utf8_lossy(path),
+                    oid_and_mode.mode,
+                    oid_and_mode.oid
+                )
+            }
+            // Commit (For submodules, include the commit hash in the calculation source.)
+            FileMode::Commit => match self.options.submodule_mode {
+                SubmoduleMode::Recurse { max_depth } if max_depth > 0 => {
+                    debug!("recurse submodule. path:{}", String::from_utf8_lossy(path));
+                    fold_submodule(hasher, &self.repo, path, hashed_path, 1, max_depth)?;
+                }
+                _ => {
                     debug!("commit_hash?:{}", oid_and_mode.oid);
                     hasher.update(oid_and_mode.oid);
                 }
-                _ => {
-                    debug!(
-                        "unsupported mode. skipped. path:{}, mode:{:?}",
-                        String::from_utf8(path.clone())?,
-                        oid_and_mode.mode
-                    )
+            },
+            _ => {
+                debug!(
+                    "unsupported mode. skipped. path:{}, mode:{:?}",
+                    String::from_utf8_lossy(path),
+                    oid_and_mode.mode
+                )
+            }
+        }
+        Ok(())
+    }
+
+    // Path of the source folded into the hasher. With `path_independent`, files in the target
+    // directory are relative to it, and the other files are relative from the repository root.
+    fn hashed_path<'a>(&self, path: &'a [u8]) -> &'a [u8] {
+        let target_path = self.calculation_target.path.as_bytes();
+        if !self.options.path_independent || target_path.is_empty() {
+            return path;
+        }
+        path.strip_prefix(target_path)
+            .and_then(|relative| relative.strip_prefix(SEPARATOR_BYTE))
+            .unwrap_or(path)
+    }
+
+    // Stream the content into the hasher without loading the whole blob.
+    fn fold_content(
+        &self,
+        hasher: &mut Sha256,
+        path: &[u8],
+        mode: FileMode,
+        oid: Oid,
+    ) -> Result<(), SverError> {
+        let odb = self.repo.odb()?;
+        if !odb.exists(oid) {
+            // untracked files are not in the object database
+            let full_path = Path::new(&self.work_dir).join(path_string(path)?);
+            if mode == FileMode::Link {
+                // the content of a link is its target path, as git stores it
+                let link = fs::read_link(&full_path)?;
+                hasher.update(link.as_os_str().as_encoded_bytes());
+            } else {
+                io::copy(&mut fs::File::open(&full_path)?, hasher)?;
+            }
+            return Ok(());
+        }
+        match odb.reader(oid) {
+            Ok((mut reader, _, _)) => {
+                io::copy(&mut reader, hasher)?;
+            }
+            // libgit2 can stream only loose objects, packed objects are inflated at once
+            Err(e) => {
+                debug!("fallback to read whole object. oid:{oid}, error:{e}");
+                hasher.update(odb.read(oid)?.data());
+            }
+        }
+        Ok(())
+    }
+
+    fn normalize_entry(
+        &self,
+        path: &[u8],
+        oid_and_mode: &OidAndMode,
+    ) -> Result<OidAndMode, SverError> {
+        let OidAndMode { mut oid, mut mode } = *oid_and_mode;
+        if self.options.ignore_mode && mode == FileMode::BlobExecutable {
+            mode = FileMode::Blob;
+        }
+        if self.options.ignore_config_metadata && mode != FileMode::Link {
+            if let Some(content) = self.config_content_without_metadata(path, oid)? {
+                oid = Oid::hash_object(ObjectType::Blob, &content)?;
+                return Ok(OidAndMode { oid, mode });
+            }
+        }
+        if self.options.normalize_content() && mode != FileMode::Link {
+            let text = match self.repo.find_blob(oid) {
+                Ok(blob) => (!blob.is_binary()).then(|| blob.content().to_vec()),
+                // untracked files are not in the object database
+                Err(e) if e.code() == ErrorCode::NotFound => {
+                    let content = fs::read(Path::new(&self.work_dir).join(path_string(path)?))?;
+                    (!is_binary(&content)).then_some(content)
                 }
+                Err(e) => return Err(e.into()),
+            };
+            if let Some(text) = text {
+                let content = normalize_text(
+                    &text,
+                    self.options.normalize_eol,
+                    self.options.strip_trailing_whitespace,
+                );
+                oid = Oid::hash_object(ObjectType::Blob, &content)?;
             }
         }
-        let hash = format!("{:#x}", hasher.finalize());
-        Ok(hash)
+        Ok(OidAndMode { oid, mode })
+    }
+
+    // Content of the config file `path` without metadata, or `None` for other files and config
+    // files which can not be parsed.
+    fn config_content_without_metadata(
+        &self,
+        path: &[u8],
+        oid: Oid,
+    ) -> Result<Option<Vec<u8>>, SverError> {
+        if config_file_name(path).is_none() {
+            return Ok(None);
+        }
+        let config_file = path_string(path)?;
+        let content = match blob_content(&self.repo, oid) {
+            Ok(content) => content.to_vec(),
+            // untracked files are not in the object database
+            Err(e) if e.code() == ErrorCode::NotFound => {
+                fs::read(Path::new(&self.work_dir).join(&config_file))?
+            }
+            Err(e) => return Err(e.into()),
+        };
+        Ok(SverConfig::content_without_metadata(&config_file, &content))
     }
 
-    fn list_sorted_entries(&self) -> anyhow::Result<BTreeMap<Vec<u8>, OidAndMode>> {
-        let mut path_set: HashMap<CalculationTarget, Vec<String>> = HashMap::new();
-        self.collect_path_and_excludes(&self.calculation_target, &mut path_set)?;
+    // Entries of the index, and untracked files if `include_untracked` is set.
+    // Ignored files are included only when they are tracked, like git.
+    fn list_working_entries(
+        &self,
+        index: &Index,
+        path_set: &HashMap<CalculationTarget, TargetSetting>,
+    ) -> Result<BTreeMap<Vec<u8>, OidAndMode>, SverError> {
+        let mut map = self.list_sorted_entries(index, path_set)?;
+        if !self.options.include_untracked {
+            return Ok(map);
+        }
+        let mut status_options = StatusOptions::new();
+        status_options
+            .include_untracked(true)
+            .recurse_untracked_dirs(true)
+            .include_ignored(false);
+        for status in self.repo.statuses(Some(&mut status_options))?.iter() {
+            let path = normalize_working_tree_path(status.path_bytes().to_vec());
+            if !status.status().contains(Status::WT_NEW) || !containable(&path, path_set) {
+                continue;
+            }
+            // Ignored files are never reported as WT_NEW. Check again to match git
+            // semantics (.gitignore of each directory, .git/info/exclude and core.excludesFile)
+            // regardless of the status options.
+            let raw_path = path_string(status.path_bytes())?;
+            if self.repo.is_path_ignored(&raw_path)? {
+                debug!("ignored path:{}", String::from_utf8_lossy(&path));
+                continue;
+            }
+            let full_path = Path::new(&self.work_dir).join(raw_path);
+            let metadata = fs::symlink_metadata(&full_path)?;
+            // The content is hashed but not written to the object database.
+            let oid_and_mode = if metadata.is_symlink() {
+                let link = fs::read_link(&full_path)?;
+                OidAndMode {
+                    oid: Oid::hash_object(ObjectType::Blob, link.as_os_str().as_encoded_bytes())?,
+                    mode: FileMode::Link,
+                }
+            } else {
+                OidAndMode {
+                    oid: Oid::hash_file(ObjectType::Blob, &full_path)?,
+                    mode: if is_executable(&metadata) {
+                        FileMode::BlobExecutable
+                    } else {
+                        FileMode::Blob
+                    },
+                }
+            };
+            if !containable_with_mode(&path, oid_and_mode.mode, path_set) {
+                debug!("excluded by mode. path:{}", String::from_utf8_lossy(&path));
+                continue;
+            }
+            debug!("add untracked path:{}", String::from_utf8_lossy(&path));
+            map.insert(path, oid_and_mode);
+        }
+        self.apply_global_excludes(index, &mut map)?;
+        Ok(map)
+    }
+
+    fn collect_path_set(
+        &self,
+        index: &Index,
+    ) -> Result<HashMap<CalculationTarget, TargetSetting>, SverError> {
+        let mut path_set: HashMap<CalculationTarget, TargetSetting> = HashMap::new();
+        self.collect_path_and_excludes(
+            index,
+            &self.calculation_target,
+            CollectContext {
+                reason: InclusionReason::Direct,
+                transitive: true,
+                link_depth: 0,
+            },
+            &mut path_set,
+        )?;
         debug!("dependency_paths:{:?}", path_set);
+        Ok(path_set)
+    }
+
+    fn list_sorted_entries(
+        &self,
+        index: &Index,
+        path_set: &HashMap<CalculationTarget, TargetSetting>,
+    ) -> Result<BTreeMap<Vec<u8>, OidAndMode>, SverError> {
+        // Entries are keyed by path, so a file reachable from multiple targets
+        // (dependencies or symbolic links) is hashed only once.
+        // A path appears more than once in the index only when it is conflicted (stage 1-3).
+        // The index is sorted by path and stage, so the entry of the last stage wins.
+        //
+        // The index is not `Sync`, so entries are copied out of it first and filtered in
+        // parallel. The parallel collect keeps the order of the index.
+        let entries = index.iter().collect::<Vec<_>>();
+        let contained = entries
+            .into_par_iter()
+            .filter(|entry| {
+                let containable = containable(entry.path.as_slice(), path_set);
+                debug!(
+                    "path:{}, containable:{}, mode:{:?}",
+                    String::from_utf8_lossy(&entry.path),
+                    containable,
+                    FileMode::from(entry.mode),
+                );
+                containable
+            })
+            .map(|entry| {
+                let oid_and_mode = OidAndMode {
+                    oid: entry.id,
+                    mode: entry.mode.into(),
+                };
+                (entry.path, oid_and_mode)
+            })
+            .collect::<Vec<_>>();
+        let symlinks_as_blobs = self.symlinks_as_blobs();
+        let excludes_modes = path_set
+            .values()
+            .any(|setting| !setting.exclude_modes.is_empty());
         let mut map = BTreeMap::new();
-        for entry in self.repo.index()?.iter() {
-            let containable = containable(entry.path.as_slice(), &path_set);
-            debug!(
-                "path:{}, containable:{}, mode:{:?}",
-                String::from_utf8(entry.path.clone())?,
-                containable,
-                FileMode::from(entry.mode),
-            );
-            if containable {
-                debug!("add path:{:?}", String::from_utf8(entry.path.clone()));
-                map.insert(
-                    entry.path,
-                    OidAndMode {
-                        oid: entry.id,
-                        mode: entry.mode.into(),
-                    },
+        for (path, mut oid_and_mode) in contained {
+            oid_and_mode.mode = self.entry_mode(
+                index,
+                &path,
+                oid_and_mode.oid,
+                oid_and_mode.mode,
+                symlinks_as_blobs,
+            )?;
+            if excludes_modes && !containable_with_mode(&path, oid_and_mode.mode, path_set) {
+                debug!(
+                    "excluded by mode. path:{}, mode:{:?}",
+                    String::from_utf8_lossy(&path),
+                    oid_and_mode.mode
                 );
+                continue;
             }
+            debug!("add path:{}", String::from_utf8_lossy(&path));
+            map.insert(path, oid_and_mode);
         }
+        self.apply_global_excludes(index, &mut map)?;
         Ok(map)
     }
 
+    // With `core.symlinks=false`, git checks out symbolic links as plain files of the link
+    // target, and they are added back as regular files when the index lost their mode.
+    // Such files are regarded as links only with the opt-in option.
+    fn symlinks_as_blobs(&self) -> bool {
+        self.options.symlinks_as_blobs
+            && self
+                .repo
+                .config()
+                .and_then(|config| config.get_bool("core.symlinks"))
+                .is_ok_and(|symlinks| !symlinks)
+    }
+
+    // Mode of the index entry. With `symlinks_as_blobs`, a regular file is regarded as a
+    // symbolic link when its content is a relative path to a file or directory in the index,
+    // in a single line without newline as git writes symbolic links. So the version is the same
+    // as in checkouts with symbolic links.
+    fn entry_mode(
+        &self,
+        index: &Index,
+        path: &[u8],
+        oid: Oid,
+        mode: FileMode,
+        symlinks_as_blobs: bool,
+    ) -> Result<FileMode, SverError> {
+        if !symlinks_as_blobs || mode != FileMode::Blob {
+            return Ok(mode);
+        }
+        let (size, _) = self.repo.odb()?.read_header(oid)?;
+        if size == 0 || size > MAX_LINK_TARGET_SIZE {
+            return Ok(mode);
+        }
+        let content = blob_content(&self.repo, oid)?;
+        let (Ok(path), Ok(link)) = (std::str::from_utf8(path), std::str::from_utf8(&content))
+        else {
+            return Ok(mode);
+        };
+        if link.contains(['\n', '\r', '\0']) {
+            return Ok(mode);
+        }
+        let Some(link_path) = resolve_link_target(path, link) else {
+            return Ok(mode);
+        };
+        let in_index = index.get_path(Path::new(&link_path), 0).is_some()
+            || index
+                .find_prefix(format!("{link_path}{SEPARATOR_STR}"))
+                .is_ok();
+        if in_index {
+            debug!("symbolic link stored as a regular file. path:{path}, target:{link}");
+            Ok(FileMode::Link)
+        } else {
+            Ok(mode)
+        }
+    }
+
+    // Lines of the ignore file in the index except for blank lines and comments.
+    fn load_ignore_patterns(&self, index: &Index) -> Result<Vec<String>, SverError> {
+        let Some(entry) = index.get_path(Path::new(IGNORE_FILE_NAME), 0) else {
+            return Ok(Vec::new());
+        };
+        let content = blob_content(&self.repo, entry.id)?;
+        let content = std::str::from_utf8(&content).map_err(|_| {
+            SverError::InvalidConfig(format!("{IGNORE_FILE_NAME} is not valid UTF-8"))
+        })?;
+        Ok(content
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+            .map(str::to_string)
+            .collect())
+    }
+
+    // Patterns of the ignore file which match no file in the index.
+    fn unmatched_ignore_patterns(&self, index: &Index) -> Result<Vec<String>, SverError> {
+        let mut unmatched = Vec::new();
+        for pattern in self.load_ignore_patterns(index)? {
+            let excludes = Excludes::new("", &[ignore_pattern_to_exclude(&pattern)])?;
+            let rule = &excludes.rules()[0];
+            if !index.iter().any(|entry| rule.matches(&entry.path)) {
+                unmatched.push(pattern);
+            }
+        }
+        Ok(unmatched)
+    }
+
+    // Whether the root `sver.lock` of `oid` is a lockfile. A file of the same name written by
+    // another tool is an ordinary source, so versions of repositories having it do not change.
+    fn is_lockfile(&self, oid: Oid) -> Result<bool, SverError> {
+        let content = match blob_content(&self.repo, oid) {
+            Ok(content) => content.to_vec(),
+            // untracked files are not in the object database
+            Err(e) if e.code() == ErrorCode::NotFound => {
+                fs::read(Path::new(&self.work_dir).join(LOCK_FILE_NAME))?
+            }
+            Err(e) => return Err(e.into()),
+        };
+        Ok(String::from_utf8(content).is_ok_and(|content| Lockfile::parse(&content).is_ok()))
+    }
+
+    // The ignore file is applied before `global_excludes`, so a negation of `global_excludes`
+    // can take back files it excluded.
+    fn apply_global_excludes(
+        &self,
+        index: &Index,
+        entries: &mut BTreeMap<Vec<u8>, OidAndMode>,
+    ) -> Result<(), SverError> {
+        if let Some(oid_and_mode) = entries.get(LOCK_FILE_NAME.as_bytes()) {
+            if self.is_lockfile(oid_and_mode.oid)? {
+                entries.remove(LOCK_FILE_NAME.as_bytes());
+            }
+        }
+        let mut patterns = self
+            .load_ignore_patterns(index)?
+            .iter()
+            .map(|pattern| ignore_pattern_to_exclude(pattern))
+            .collect::<Vec<_>>();
+        patterns.extend(self.options.global_excludes.iter().cloned());
+        if patterns.is_empty() {
+            return Ok(());
+        }
+        let global_excludes = Excludes::new("", &patterns)?;
+        entries.retain(|path, _| {
+            let excluded = global_excludes.is_excluded(path);
+            if excluded {
+                debug!("global excluded. path:{}", String::from_utf8_lossy(path));
+            }
+            !excluded
+        });
+        Ok(())
+    }
+
+    // Profile config of the target, or `None` without config file.
+    fn load_target_config(
+        &self,
+        index: &Index,
+        calculation_target: &CalculationTarget,
+    ) -> Result<Option<ProfileConfig>, SverError> {
+        let Some(entry) = find_config_entry(index, &calculation_target.path)? else {
+            return Ok(None);
+        };
+        debug!(
+            "config file exists. path:{}",
+            String::from_utf8_lossy(&entry.path)
+        );
+        match ProfileConfig::load_profile(
+            &path_string(&entry.path)?,
+            &blob_content(&self.repo, entry.id)?,
+            &calculation_target.profile,
+        ) {
+            Err(SverError::ProfileNotFound(profile))
+                if self.options.lenient && profile == "default" =>
+            {
+                debug!("default profile is not found. fallback to empty profile");
+                Ok(Some(ProfileConfig::default()))
+            }
+            result => result.map(Some),
+        }
+    }
+
+    // A target is collected once, so cycles of links or dependencies end at a target already
+    // added, and the collected targets do not depend on the order of the links.
     fn collect_path_and_excludes(
         &self,
+        index: &Index,
         calculation_target: &CalculationTarget,
-        path_and_excludes: &mut HashMap<CalculationTarget, Vec<String>>,
-    ) -> anyhow::Result<()> {
-        if path_and_excludes.contains_key(calculation_target) {
-            debug!(
-                "already added. path:{}, profile:{}",
-                calculation_target.path, calculation_target.profile
-            );
-            return Ok(());
+        context: CollectContext,
+        path_and_excludes: &mut HashMap<CalculationTarget, TargetSetting>,
+    ) -> Result<(), SverError> {
+        let CollectContext {
+            mut reason,
+            transitive,
+            link_depth,
+        } = context;
+        if let Some(setting) = path_and_excludes.get(calculation_target) {
+            if !transitive || !setting.dependencies_skipped {
+                debug!(
+                    "already added. path:{}, profile:{}",
+                    calculation_target.path, calculation_target.profile
+                );
+                return Ok(());
+            }
+            // added by a non-transitive dependency before, so its dependencies are collected now
+            reason = setting.reason;
         }
         debug!("add dep path : {}", calculation_target.path);
 
-        let mut p = PathBuf::new();
-        p.push(&calculation_target.path);
-        p.push("sver.toml");
+        let mut current_path_and_excludes: HashMap<CalculationTarget, TargetSetting> =
+            HashMap::new();
+        // `None` follows all symbolic links.
+        let mut allow_symlink_targets = None;
+        let mut follow_symlinks = true;
 
-        let mut current_path_and_excludes: HashMap<CalculationTarget, Vec<String>> = HashMap::new();
-
-        if let Some(entry) = self.repo.index()?.get_path(p.as_path(), 0) {
-            debug!("sver.toml exists. path:{:?}", String::from_utf8(entry.path));
-            let config = ProfileConfig::load_profile(
-                self.repo.find_blob(entry.id)?.content(),
-                &calculation_target.profile,
-            )?;
-            current_path_and_excludes.insert(calculation_target.clone(), config.excludes.clone());
-            path_and_excludes.insert(calculation_target.clone(), config.excludes);
-            for dependency in config.dependencies {
-                let dependency_target = CalculationTarget::parse_from_setting(&dependency);
-                self.collect_path_and_excludes(&dependency_target, path_and_excludes)?;
+        if let Some(config) = self.load_target_config(index, calculation_target)? {
+            let setting = TargetSetting {
+                excludes: Excludes::new(&calculation_target.path, &config.excludes)?,
+                exclude_modes: config
+                    .exclude_modes
+                    .into_iter()
+                    .map(FileMode::from)
+                    .collect(),
+                env: config.env,
+                reason,
+                dependencies_skipped: !transitive,
+            };
+            current_path_and_excludes.insert(calculation_target.clone(), setting.clone());
+            path_and_excludes.insert(calculation_target.clone(), setting);
+            if !config.allow_symlink_targets.is_empty() {
+                // same syntax as excludes, but relative from the repository root
+                allow_symlink_targets = Some(Excludes::new("", &config.allow_symlink_targets)?);
+            }
+            follow_symlinks = config.follow_symlinks.unwrap_or(true);
+            let dependencies = if transitive {
+                config.dependencies
+            } else {
+                debug!(
+                    "dependencies are not followed. path:{}, profile:{}",
+                    calculation_target.path, calculation_target.profile
+                );
+                Vec::new()
+            };
+            for dependency in dependencies {
+                let dependency_target =
+                    CalculationTarget::parse_dependency(&calculation_target.path, &dependency)?;
+                self.collect_path_and_excludes(
+                    index,
+                    &dependency_target,
+                    CollectContext {
+                        reason: InclusionReason::Dependency,
+                        transitive: is_transitive_dependency(&dependency),
+                        link_depth,
+                    },
+                    path_and_excludes,
+                )?;
             }
         } else {
-            current_path_and_excludes.insert(calculation_target.clone(), vec![]);
-            path_and_excludes.insert(calculation_target.clone(), vec![]);
+            let setting = TargetSetting {
+                reason,
+                ..Default::default()
+            };
+            current_path_and_excludes.insert(calculation_target.clone(), setting.clone());
+            path_and_excludes.insert(calculation_target.clone(), setting);
         }
 
         // include symbolic link
-        for entry in self.repo.index()?.iter() {
-            if FileMode::from(entry.mode) == FileMode::Link
-                && containable(entry.path.as_slice(), &current_path_and_excludes)
+        if !follow_symlinks {
+            debug!(
+                "symbolic links are not followed. path:{}, profile:{}",
+                calculation_target.path, calculation_target.profile
+            );
+            return Ok(());
+        }
+        let symlinks_as_blobs = self.symlinks_as_blobs();
+        for entry in index.iter() {
+            // excluded links are not followed
+            if containable_with_mode(
+                entry.path.as_slice(),
+                FileMode::Link,
+                &current_path_and_excludes,
+            ) && self.entry_mode(
+                index,
+                &entry.path,
+                entry.id,
+                entry.mode.into(),
+                symlinks_as_blobs,
+            )? == FileMode::Link
             {
-                let path = String::from_utf8(entry.path)?;
-                let mut buf = PathBuf::new();
-                buf.push(path);
-                buf.pop();
-
-                let blob = self.repo.find_blob(entry.id)?;
-                let link_path = String::from_utf8(blob.content().to_vec())?;
-                let link_path = Path::new(&link_path);
-                for link_components in link_path.components() {
-                    debug!("link_component:{:?}", link_components);
-                    match link_components {
-                        Component::ParentDir => {
-                            buf.pop();
-                        }
-                        Component::Normal(path) => buf.push(path),
-                        Component::RootDir => {}
-                        Component::CurDir => {}
-                        Component::Prefix(_prefix) => {}
+                let path = String::from_utf8(entry.path.clone())
+                    .map_err(|_| SverError::non_utf8_path(&entry.path))?;
+                let content = blob_content(&self.repo, entry.id)?;
+                let link = String::from_utf8(content.to_vec())
+                    .map_err(|_| SverError::non_utf8_path(&content))?;
+                let Some(link_path) = resolve_link_target(&path, &link) else {
+                    // absolute paths or paths escaping the repository can not be sources
+                    warn!(
+                        "symbolic link points outside of the repository. skipped. path:{}, target:{}",
+                        path, link
+                    );
+                    continue;
+                };
+                if let Some(allowed) = &allow_symlink_targets {
+                    if !allowed.is_excluded(link_path.as_bytes()) {
+                        debug!("link target is not allowed. path:{}", &link_path);
+                        continue;
                     }
                 }
-
-                let link_path = buf
-                    .iter()
-                    .flat_map(|os| os.to_str())
-                    .collect::<Vec<_>>()
-                    .join(SEPARATOR_STR);
-                debug!("collect link path. path:{}", &link_path);
+                let link_target = CalculationTarget::new(link_path, "default".to_string());
+                if link_depth >= MAX_LINK_DEPTH {
+                    return Err(SverError::TooManyLinks(path));
+                }
+                debug!("collect link path. path:{}", &link_target.path);
                 self.collect_path_and_excludes(
-                    &CalculationTarget::new(link_path, "default".to_string()),
+                    index,
+                    &link_target,
+                    CollectContext {
+                        reason: InclusionReason::Symlink,
+                        transitive: true,
+                        link_depth: link_depth + 1,
+                    },
                     path_and_excludes,
                 )?;
             }
@@ -305,7 +1711,280 @@ impl SverRepository {
     }
 }
 
+// How a target is reached while its sources are collected.
+#[derive(Clone, Copy)]
+struct CollectContext {
+    reason: InclusionReason,
+    // whether the dependencies of the target are followed
+    transitive: bool,
+    // the number of symbolic links followed to reach the target
+    link_depth: usize,
+}
+
+#[derive(Debug)]
+pub struct ExcludeReport {
+    pub calculation_target: CalculationTarget,
+    pub exclude: String,
+    /// Number of files removed from the sources of the target by the exclude.
+    pub excluded_files: usize,
+}
+
+impl Display for ExcludeReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let unused = if self.excluded_files == 0 {
+            "\t(unused)"
+        } else {
+            ""
+        };
+        writeln!(
+            f,
+            "{}\texclude:{}\texcluded_files:{}{}",
+            self.calculation_target, self.exclude, self.excluded_files, unused
+        )
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct CalcReport {
+    pub version: Version,
+    pub sources: Vec<String>,
+    pub source_count: usize,
+    /// Total size of the source contents in bytes.
+    pub total_bytes: u64,
+    /// Config files of the target and its dependencies.
+    pub config_files: Vec<String>,
+}
+
+/// Version of a target at a commit.
+#[derive(Debug, PartialEq, Serialize)]
+pub struct CommitVersion {
+    /// Commit id.
+    pub commit: String,
+    pub version: Version,
+}
+
+pub struct VersionPreview {
+    pub head: Option<Version>,
+    pub index: Version,
+}
+
+impl VersionPreview {
+    pub fn is_changed(&self) -> bool {
+        self.head
+            .as_ref()
+            .is_none_or(|head| head.version != self.index.version)
+    }
+}
+
+/// How a target was reached from the calculated target.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum InclusionReason {
+    /// The calculated target itself.
+    #[default]
+    Direct,
+    /// A target in `dependencies` of a config file.
+    Dependency,
+    /// The target of a symbolic link.
+    Symlink,
+}
+
+impl Display for InclusionReason {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            InclusionReason::Direct => write!(f, "direct"),
+            InclusionReason::Dependency => write!(f, "dependency"),
+            InclusionReason::Symlink => write!(f, "symlink"),
+        }
+    }
+}
+
+/// A source and its object in the index.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SourceEntry {
+    pub path: String,
+    /// Id of the blob, or of the pinned commit for submodules.
+    pub oid: Oid,
+    pub mode: FileMode,
+}
+
+/// A source and the digest of the bytes folded into the version for it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SourceDigest {
+    pub path: String,
+    /// Mode in the index.
+    pub mode: FileMode,
+    pub digest: String,
+}
+
+/// A version with the details of its calculation.
+#[derive(Debug)]
+pub struct VersionDetails {
+    pub version: Version,
+    /// Targets whose sources are folded into the version: the target itself and its resolved
+    /// dependencies, sorted.
+    pub contributing_targets: Vec<CalculationTarget>,
+    pub stats: SourceStats,
+}
+
+/// Number of the sources and their total size.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct SourceStats {
+    pub files: usize,
+    /// Total size of the content in bytes. Submodules are counted as files without size.
+    pub bytes: u64,
+}
+
+/// A source and the target which included it.
+#[derive(Debug, PartialEq, Serialize)]
+pub struct SourceProvenance {
+    pub path: String,
+    pub target: CalculationTarget,
+    pub reason: InclusionReason,
+}
+
+/// Source paths which differ between two revisions.
+#[derive(Debug, Default, Serialize)]
+pub struct SourceDiff {
+    pub added: Vec<String>,
+    pub removed: Vec<String>,
+    /// Paths whose oid or file mode changed.
+    pub modified: Vec<String>,
+}
+
+impl Display for SourceDiff {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for path in &self.added {
+            writeln!(f, "A\t{path}")?;
+        }
+        for path in &self.removed {
+            writeln!(f, "D\t{path}")?;
+        }
+        for path in &self.modified {
+            writeln!(f, "M\t{path}")?;
+        }
+        Ok(())
+    }
+}
+
+// Same heuristic as git: a NUL byte in the first 8000 bytes.
+fn is_binary(content: &[u8]) -> bool {
+    content.iter().take(8000).any(|b| *b == 0)
+}
+
+#[cfg(unix)]
+fn is_executable(metadata: &fs::Metadata) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+    metadata.permissions().mode() & 0o111 != 0
+}
+
+#[cfg(not(unix))]
+fn is_executable(_metadata: &fs::Metadata) -> bool {
+    false
+}
+
+// Fold the index entries of the submodule at `path` of `repo`.
+// `full_path` is the path from the root of the outermost repository, used in the hash.
+fn fold_submodule(
+    hasher: &mut Sha256,
+    repo: &Repository,
+    path: &[u8],
+    full_path: &[u8],
+    depth: usize,
+    max_depth: usize,
+) -> Result<(), SverError> {
+    let sub_repo = repo.find_submodule(&path_string(path)?)?.open()?;
+    for entry in sub_repo.index()?.iter() {
+        let entry_path = [full_path, SEPARATOR_BYTE, &entry.path].concat();
+        hasher.update(&entry_path);
+        match FileMode::from(entry.mode) {
+            FileMode::Commit if depth < max_depth => {
+                fold_submodule(
+                    hasher,
+                    &sub_repo,
+                    &entry.path,
+                    &entry_path,
+                    depth + 1,
+                    max_depth,
+                )?;
+            }
+            FileMode::Commit => hasher.update(entry.id),
+            mode => {
+                hasher.update(u32::from(mode).to_le_bytes());
+                hasher.update(entry.id);
+            }
+        }
+        debug!(
+            "submodule entry. path:{}, oid:{}",
+            String::from_utf8_lossy(&entry_path),
+            entry.id
+        );
+    }
+    Ok(())
+}
+
+fn path_string(path: &[u8]) -> Result<String, SverError> {
+    String::from_utf8(path.to_vec()).map_err(|_| SverError::non_utf8_path(path))
+}
+
+#[derive(Debug, Serialize)]
 pub struct ValidationResults {
     pub has_invalid: bool,
+    pub has_warning: bool,
     pub results: Vec<ValidationResult>,
 }
+
+impl ValidationResults {
+    pub fn new(results: Vec<ValidationResult>) -> Self {
+        ValidationResults {
+            has_invalid: results.iter().any(|s| s.is_invalid()),
+            has_warning: results.iter().any(|s| s.is_warning()),
+            results,
+        }
+    }
+
+    /// Whether the validation fails. Warnings fail it only when `fail_on_warning` is set.
+    pub fn is_failure(&self, fail_on_warning: bool) -> bool {
+        self.has_invalid || (fail_on_warning && self.has_warning)
+    }
+
+    /// Apply [`ValidationResult::allow_dependencies`] to every result.
+    pub fn allow_dependencies(self, allowed: &[String]) -> Self {
+        let results: Vec<ValidationResult> = self
+            .results
+            .into_iter()
+            .map(|result| result.allow_dependencies(allowed))
+            .collect();
+        ValidationResults::new(results)
+    }
+}
+
+#[derive(Default, Debug)]
+pub struct DependencyGraph {
+    pub nodes: BTreeSet<CalculationTarget>,
+    /// (target, dependency target)
+    pub edges: BTreeSet<(CalculationTarget, CalculationTarget)>,
+}
+
+// Graphviz DOT format
+impl Display for DependencyGraph {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "digraph sver {{")?;
+        for node in &self.nodes {
+            writeln!(f, "    {};", dot_id(node))?;
+        }
+        for (target, dependency) in &self.edges {
+            writeln!(f, "    {} -> {};", dot_id(target), dot_id(dependency))?;
+        }
+        writeln!(f, "}}")
+    }
+}
+
+// Quoted DOT id of the target. Paths may contain `"` and `\`.
+fn dot_id(target: &CalculationTarget) -> String {
+    let escaped = target
+        .to_string()
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"");
+    format!("\"{escaped}\"")
+}
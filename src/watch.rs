@@ -0,0 +1,176 @@
+use std::{
+    collections::BTreeSet,
+    io,
+    ops::ControlFlow,
+    path::{Path, PathBuf},
+    sync::mpsc::{channel, RecvTimeoutError},
+    time::Duration,
+};
+
+use log::debug;
+use notify::{Event, EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+
+use crate::{sver_config::CONFIG_FILE_NAMES, sver_repository::SverRepository, SverError, Version};
+
+/// Watch the sources of the target and pass the version to `on_version` each time it changes.
+///
+/// The initial version is passed once the sources are watched.
+/// Versions are calculated from the index, so a change of a tracked file is reflected when it is
+/// staged, while untracked files included by `include_untracked` are reflected on save.
+/// Changes are debounced by `debounce`, and the sources are resolved again when a config file or
+/// the index changes.
+/// Errors after the initial version, such as a config file broken while editing, are passed to
+/// `on_version` and watching continues. Returns when `on_version` returns [`ControlFlow::Break`].
+pub fn watch_version(
+    repo: &SverRepository,
+    debounce: Duration,
+    mut on_version: impl FnMut(Result<&Version, &SverError>) -> ControlFlow<()>,
+) -> Result<(), SverError> {
+    let (sender, receiver) = channel();
+    let mut watcher = notify::recommended_watcher(sender).map_err(watch_error)?;
+    // the index is replaced by git, so its directory is watched
+    let index_file = repo.git_dir().join("index");
+    watcher
+        .watch(repo.git_dir(), RecursiveMode::NonRecursive)
+        .map_err(watch_error)?;
+    let mut sources = WatchedSources::default();
+    sources.update(&mut watcher, WatchedSources::resolve(repo)?)?;
+
+    let version = repo.calc_version()?;
+    if on_version(Ok(&version)).is_break() {
+        return Ok(());
+    }
+    let mut last = Some(version);
+    while let Ok(event) = receiver.recv() {
+        let mut events = vec![event];
+        loop {
+            match receiver.recv_timeout(debounce) {
+                Ok(event) => events.push(event),
+                Err(RecvTimeoutError::Timeout) => break,
+                Err(RecvTimeoutError::Disconnected) => return Ok(()),
+            }
+        }
+        let events = match events.into_iter().collect::<Result<Vec<_>, _>>() {
+            Ok(events) => events,
+            Err(e) => {
+                if on_version(Err(&watch_error(e))).is_break() {
+                    return Ok(());
+                }
+                continue;
+            }
+        };
+        if !events
+            .iter()
+            .any(|event| sources.changes_version(event, &index_file))
+        {
+            continue;
+        }
+        let resolve = events
+            .iter()
+            .any(|event| changes_sources(event, &index_file));
+        let result = if resolve {
+            WatchedSources::resolve(repo)
+                .and_then(|next| sources.update(&mut watcher, next))
+                .and_then(|_| repo.calc_version())
+        } else {
+            repo.calc_version()
+        };
+        let flow = match result {
+            Ok(version) if last.as_ref() == Some(&version) => continue,
+            Ok(version) => on_version(Ok(last.insert(version))),
+            Err(e) => {
+                // the next version is passed even if it is the same as before the error
+                last = None;
+                on_version(Err(&e))
+            }
+        };
+        if flow.is_break() {
+            return Ok(());
+        }
+    }
+    Ok(())
+}
+
+#[derive(Default)]
+struct WatchedSources {
+    files: BTreeSet<PathBuf>,
+    // parent directories of the files are watched, since editors replace files on save
+    dirs: BTreeSet<PathBuf>,
+}
+
+impl WatchedSources {
+    fn resolve(repo: &SverRepository) -> Result<Self, SverError> {
+        let work_dir = Path::new(repo.work_dir());
+        let files = repo
+            .list_sources()?
+            .iter()
+            .map(|source| work_dir.join(source))
+            .collect::<BTreeSet<_>>();
+        let dirs = files
+            .iter()
+            .filter_map(|file| file.parent())
+            // sources only in the index have no directory
+            .filter(|dir| dir.is_dir())
+            .map(Path::to_path_buf)
+            .collect();
+        Ok(Self { files, dirs })
+    }
+
+    fn update(&mut self, watcher: &mut RecommendedWatcher, next: Self) -> Result<(), SverError> {
+        for dir in self.dirs.difference(&next.dirs) {
+            // the directory may be already removed
+            if let Err(e) = watcher.unwatch(dir) {
+                debug!("failed to unwatch. path:{}, error:{e}", dir.display());
+            }
+        }
+        for dir in next.dirs.difference(&self.dirs) {
+            debug!("watch:{}", dir.display());
+            watcher
+                .watch(dir, RecursiveMode::NonRecursive)
+                .map_err(watch_error)?;
+        }
+        *self = next;
+        Ok(())
+    }
+
+    // Access events are caused by the calculation itself, and never change the version.
+    // Created files may be untracked sources.
+    fn changes_version(&self, event: &Event, index_file: &Path) -> bool {
+        match event.kind {
+            EventKind::Access(_) => false,
+            EventKind::Create(_) => event.paths.iter().any(|path| {
+                path.parent().is_some_and(|dir| self.dirs.contains(dir))
+                    || changes_sources_path(path, index_file)
+            }),
+            _ => event
+                .paths
+                .iter()
+                .any(|path| self.files.contains(path) || changes_sources_path(path, index_file)),
+        }
+    }
+}
+
+// Errors of the watcher are reported as I/O errors, such as the limit of inotify watches.
+fn watch_error(e: notify::Error) -> SverError {
+    match e.kind {
+        notify::ErrorKind::Io(e) => SverError::Io(e),
+        _ => SverError::Io(io::Error::other(e)),
+    }
+}
+
+fn changes_sources(event: &Event, index_file: &Path) -> bool {
+    !matches!(event.kind, EventKind::Access(_))
+        && event
+            .paths
+            .iter()
+            .any(|path| changes_sources_path(path, index_file))
+}
+
+// Config files and the index decide the set of sources.
+fn changes_sources_path(path: &Path, index_file: &Path) -> bool {
+    path == index_file
+        || path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .is_some_and(|name| CONFIG_FILE_NAMES.contains(&name))
+}
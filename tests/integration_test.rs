@@ -1,21 +1,31 @@
 mod test_tool;
 
-use std::{env::temp_dir, fs::create_dir};
+use std::{env::temp_dir, fs::create_dir, path::Path};
 
 use chrono::{TimeZone, Utc};
 use git2::Repository;
 use log::debug;
-use sver::sver_repository::ValidationResults;
+use sha2::{Digest, Sha256};
+use sver::attestation::Verification;
+use sver::doctor::{diagnose, Severity};
+use sver::filemode::FileMode;
+use sver::lock::Lockfile;
+use sver::sver_repository::{
+    CalcOptions, DependencyGraph, InclusionReason, InitOptions, SortMode, SourceDigest,
+    SourceProvenance, SubmoduleMode, ValidationResults,
+};
 use sver::{
     sver_config::{CalculationTarget, ValidationResult},
     sver_repository::SverRepository,
 };
+use sver::{SverError, Version};
 use test_tool::commit_at;
 use uuid::Uuid;
 
 use crate::test_tool::{
-    add_blob, add_blob_executable, add_submodule, add_symlink, calc_target_path,
-    calc_target_path_with_profile, commit, initialize, setup_test_repository,
+    add_blob, add_blob_executable, add_blob_with_raw_path, add_blobs, add_conflicted_file,
+    add_submodule, add_symlink, calc_target_path, calc_target_path_with_profile, commit,
+    initialize, setup_test_repository,
 };
 
 // repo layout
@@ -297,6 +307,58 @@ fn has_symlink_single() {
     );
 }
 
+// repo layout
+// .
+// + linkdir
+//   + symlink → original
+//   + sver.toml → [default] follow_symlinks = false, [follow] no setting
+// + original
+//   + README.txt
+//   + Sample.txt
+#[test]
+fn follow_symlinks_disabled_repository() {
+    initialize();
+
+    // setup
+    let repo = setup_test_repository();
+    add_blob(&repo, "original/README.txt", "hello.world".as_bytes());
+    add_blob(&repo, "original/Sample.txt", "sample".as_bytes());
+    add_symlink(&repo, "linkdir/symlink", "../original");
+    add_blob(
+        &repo,
+        "linkdir/sver.toml",
+        "[default]\nfollow_symlinks = false\n\n[follow]\n".as_bytes(),
+    );
+    commit(&repo, "setup");
+    let not_followed = SverRepository::new(&calc_target_path(&repo, "linkdir")).unwrap();
+    let followed = SverRepository::new(&calc_target_path(&repo, "linkdir:follow")).unwrap();
+
+    // exercise
+    let sources = not_followed.list_sources().unwrap();
+    let version = not_followed.calc_version().unwrap();
+
+    // verify
+    assert_eq!(sources, vec!["linkdir/sver.toml", "linkdir/symlink"]);
+    assert_eq!(
+        followed.list_sources().unwrap(),
+        vec![
+            "linkdir/sver.toml",
+            "linkdir/symlink",
+            "original/README.txt",
+            "original/Sample.txt"
+        ]
+    );
+    assert_ne!(version.version, followed.calc_version().unwrap().version);
+
+    // exercise
+    add_blob(&repo, "original/README.txt", "good morning".as_bytes());
+    commit(&repo, "edit link target");
+
+    // verify
+    // the link target is not a source
+    assert_eq!(not_followed.calc_version().unwrap(), version);
+}
+
 // repo layout
 // .
 // + linkdir
@@ -335,6 +397,11 @@ fn has_symlink_dir() {
         version.version,
         "bfd875f92865460d1fcff4769bcd39e7c894c196265ec89937ca05505b41c935"
     );
+    let provenance = sver_repo.source_provenance().unwrap();
+    assert_eq!(provenance[0].reason, InclusionReason::Direct);
+    assert_eq!(provenance[1].path, "original/README.txt");
+    assert_eq!(provenance[1].target.path, "original");
+    assert_eq!(provenance[1].reason, InclusionReason::Symlink);
 }
 
 // repo layout
@@ -483,6 +550,8 @@ fn multiprofile_multidir() {
         // exercise
         let sources = sver_repo.list_sources().unwrap();
         let version = sver_repo.calc_version().unwrap();
+        let provenance = sver_repo.source_provenance().unwrap();
+        let details = sver_repo.calc_version_with_details().unwrap();
 
         // verify
         assert_eq!(
@@ -493,6 +562,33 @@ fn multiprofile_multidir() {
             version.version,
             "7403ad568d8781658870c471a52dd9c51aae3297965b6dded2f3afb25e3b282b"
         );
+        let lib1_prof1 = CalculationTarget::new("lib1".to_string(), "prof1".to_string());
+        let lib2_prof2 = CalculationTarget::new("lib2".to_string(), "prof2".to_string());
+        assert_eq!(details.version, version);
+        assert_eq!(
+            details.contributing_targets,
+            vec![lib1_prof1.clone(), lib2_prof2.clone()]
+        );
+        assert_eq!(
+            provenance,
+            vec![
+                SourceProvenance {
+                    path: "lib1/sver.toml".to_string(),
+                    target: lib1_prof1.clone(),
+                    reason: InclusionReason::Dependency,
+                },
+                SourceProvenance {
+                    path: "lib1/test1.txt".to_string(),
+                    target: lib1_prof1,
+                    reason: InclusionReason::Dependency,
+                },
+                SourceProvenance {
+                    path: "lib2/sver.toml".to_string(),
+                    target: lib2_prof2,
+                    reason: InclusionReason::Direct,
+                },
+            ]
+        );
     }
     // prof2
     {
@@ -540,6 +636,7 @@ fn valid_dependencies_repository() {
     let ValidationResults {
         has_invalid,
         mut results,
+        ..
     } = sver_repo.validate_sver_config().unwrap();
 
     // verify
@@ -547,12 +644,13 @@ fn valid_dependencies_repository() {
     assert_eq!(results.len(), 1);
     if let Some(ValidationResult::Valid {
         calcuration_target: CalculationTarget { path, profile },
+        ..
     }) = results.pop()
     {
         assert_eq!(path, "service2");
         assert_eq!(profile, "default");
     } else {
-        assert!(false, "this line will not be execute");
+        unreachable!("this line will not be execute");
     }
 }
 
@@ -585,6 +683,7 @@ fn invalid_dependencies_repository() {
     let ValidationResults {
         has_invalid,
         mut results,
+        ..
     } = sver_repo.validate_sver_config().unwrap();
 
     // verify
@@ -594,6 +693,7 @@ fn invalid_dependencies_repository() {
         calcuration_target: CalculationTarget { path, profile },
         invalid_dependencies,
         invalid_excludes,
+        ..
     }) = results.pop()
     {
         assert_eq!(path, "service2");
@@ -601,7 +701,7 @@ fn invalid_dependencies_repository() {
         assert_eq!(invalid_dependencies, vec!["service1/hello-hello.txt"]);
         assert!(invalid_excludes.is_empty());
     } else {
-        assert!(false, "this line will not be execute");
+        unreachable!("this line will not be execute");
     }
 }
 
@@ -634,6 +734,7 @@ fn valid_excludes_repository() {
     let ValidationResults {
         has_invalid,
         mut results,
+        ..
     } = sver_repo.validate_sver_config().unwrap();
 
     // verify
@@ -641,12 +742,13 @@ fn valid_excludes_repository() {
     assert_eq!(results.len(), 1);
     if let Some(ValidationResult::Valid {
         calcuration_target: CalculationTarget { path, profile },
+        ..
     }) = results.pop()
     {
         assert_eq!(path, "service1");
         assert_eq!(profile, "default");
     } else {
-        assert!(false, "this line will not be execute");
+        unreachable!("this line will not be execute");
     }
 }
 
@@ -679,6 +781,7 @@ fn invalid_excludes_repository() {
     let ValidationResults {
         has_invalid,
         mut results,
+        ..
     } = sver_repo.validate_sver_config().unwrap();
 
     // verify
@@ -688,6 +791,7 @@ fn invalid_excludes_repository() {
         calcuration_target: CalculationTarget { path, profile },
         invalid_dependencies,
         invalid_excludes,
+        ..
     }) = results.pop()
     {
         assert_eq!(path, "service1");
@@ -695,7 +799,7 @@ fn invalid_excludes_repository() {
         assert!(invalid_dependencies.is_empty());
         assert_eq!(invalid_excludes, vec!["hello-hello.txt"]);
     } else {
-        assert!(false, "this line will not be execute");
+        unreachable!("this line will not be execute");
     }
 }
 
@@ -729,6 +833,7 @@ fn valid_has_profile_repository() {
     let ValidationResults {
         has_invalid,
         mut results,
+        ..
     } = sver_repo.validate_sver_config().unwrap();
 
     // verify
@@ -736,21 +841,23 @@ fn valid_has_profile_repository() {
     assert_eq!(results.len(), 2);
     if let Some(ValidationResult::Valid {
         calcuration_target: CalculationTarget { path, profile },
+        ..
     }) = results.pop()
     {
         assert_eq!(path, "service2");
         assert_eq!(profile, "prof1");
     } else {
-        assert!(false, "this line will not be execute");
+        unreachable!("this line will not be execute");
     }
     if let Some(ValidationResult::Valid {
         calcuration_target: CalculationTarget { path, profile },
+        ..
     }) = results.pop()
     {
         assert_eq!(path, "service2");
         assert_eq!(profile, "default");
     } else {
-        assert!(false, "this line will not be execute");
+        unreachable!("this line will not be execute");
     }
 }
 
@@ -784,6 +891,7 @@ fn invalid_has_profile_repository() {
     let ValidationResults {
         has_invalid,
         mut results,
+        ..
     } = sver_repo.validate_sver_config().unwrap();
 
     // verify
@@ -799,16 +907,17 @@ fn invalid_has_profile_repository() {
         assert_eq!(profile, "prof1");
         assert_eq!(invalid_dependencies, vec!["service1/helloo.txt"]);
     } else {
-        assert!(false, "this line will not be execute");
+        unreachable!("this line will not be execute");
     }
     if let Some(ValidationResult::Valid {
         calcuration_target: CalculationTarget { path, profile },
+        ..
     }) = results.pop()
     {
         assert_eq!(path, "service2");
         assert_eq!(profile, "default");
     } else {
-        assert!(false, "this line will not be execute");
+        unreachable!("this line will not be execute");
     }
 }
 
@@ -850,6 +959,7 @@ fn valid_no_target_profile_repository() {
     let ValidationResults {
         has_invalid,
         mut results,
+        ..
     } = sver_repo.validate_sver_config().unwrap();
 
     // verify
@@ -858,21 +968,23 @@ fn valid_no_target_profile_repository() {
     assert_eq!(results.len(), 4);
     if let Some(ValidationResult::Valid {
         calcuration_target: CalculationTarget { path, profile },
+        ..
     }) = results.pop()
     {
         assert_eq!(path, "service2");
         assert_eq!(profile, "prof2");
     } else {
-        assert!(false, "this line will not be execute");
+        unreachable!("this line will not be execute");
     }
     if let Some(ValidationResult::Valid {
         calcuration_target: CalculationTarget { path, profile },
+        ..
     }) = results.pop()
     {
         assert_eq!(path, "service2");
         assert_eq!(profile, "default");
     } else {
-        assert!(false, "this line will not be execute");
+        unreachable!("this line will not be execute");
     }
 }
 
@@ -918,6 +1030,7 @@ fn invalid_no_target_profile_repository() {
     let ValidationResults {
         has_invalid,
         mut results,
+        ..
     } = sver_repo.validate_sver_config().unwrap();
 
     // verify
@@ -934,7 +1047,7 @@ fn invalid_no_target_profile_repository() {
         assert_eq!(profile, "prof3");
         assert_eq!(invalid_dependencies, vec!["service1/:prof999"]);
     } else {
-        assert!(false, "this line will not be execute");
+        unreachable!("this line will not be execute");
     }
     if let Some(ValidationResult::Invalid {
         calcuration_target: CalculationTarget { path, profile },
@@ -946,16 +1059,17 @@ fn invalid_no_target_profile_repository() {
         assert_eq!(profile, "prof2");
         assert_eq!(invalid_dependencies, vec!["service1:prof999"]);
     } else {
-        assert!(false, "this line will not be execute");
+        unreachable!("this line will not be execute");
     }
     if let Some(ValidationResult::Valid {
         calcuration_target: CalculationTarget { path, profile },
+        ..
     }) = results.pop()
     {
         assert_eq!(path, "service2");
         assert_eq!(profile, "default");
     } else {
-        assert!(false, "this line will not be execute");
+        unreachable!("this line will not be execute");
     }
 }
 
@@ -995,6 +1109,7 @@ fn invalid_no_default_repository() {
     let ValidationResults {
         has_invalid,
         mut results,
+        ..
     } = sver_repo.validate_sver_config().unwrap();
 
     // verify
@@ -1009,7 +1124,7 @@ fn invalid_no_default_repository() {
         assert_eq!(path, "service2");
         assert_eq!(profile, "default");
     } else {
-        assert!(false, "this line will not be execute");
+        unreachable!("this line will not be execute");
     }
 
     if let Some(ValidationResult::Valid {
@@ -1020,7 +1135,7 @@ fn invalid_no_default_repository() {
         assert_eq!(path, "service1");
         assert_eq!(profile, "no-default");
     } else {
-        assert!(false, "this line will not be execute");
+        unreachable!("this line will not be execute");
     }
 }
 
@@ -1053,6 +1168,7 @@ fn valid_ref_to_no_config_repository() {
     let ValidationResults {
         has_invalid,
         mut results,
+        ..
     } = sver_repo.validate_sver_config().unwrap();
 
     // verify
@@ -1067,7 +1183,7 @@ fn valid_ref_to_no_config_repository() {
         assert_eq!(path, "service2");
         assert_eq!(profile, "default");
     } else {
-        assert!(false, "this line will not be execute");
+        unreachable!("this line will not be execute");
     }
 }
 
@@ -1260,7 +1376,7 @@ fn multiprofile_ref_singledir() {
 // + test1.txt
 // + src/test2.txt
 // + lib/test3.txt
-#[cfg(target_os = "linux")]
+#[cfg(all(target_os = "linux", feature = "inspect"))]
 #[test]
 fn inspect_test() {
     initialize();
@@ -1280,7 +1396,8 @@ fn inspect_test() {
             vec![],
             std::process::Stdio::null(),
         )
-        .unwrap();
+        .unwrap()
+        .paths();
 
         // verify
         assert_eq!(result, Vec::<String>::new());
@@ -1293,7 +1410,8 @@ fn inspect_test() {
             vec!["test1.txt".to_string()],
             std::process::Stdio::null(),
         )
-        .unwrap();
+        .unwrap()
+        .paths();
         // verify
         assert_eq!(result, vec!["test1.txt"]);
     }
@@ -1305,7 +1423,8 @@ fn inspect_test() {
             vec!["src/test2.txt".to_string(), "lib/test3.txt".to_string()],
             std::process::Stdio::null(),
         )
-        .unwrap();
+        .unwrap()
+        .paths();
 
         //verify
         assert_eq!(result, vec!["lib/test3.txt", "src/test2.txt"]);
@@ -1321,7 +1440,8 @@ fn inspect_test() {
             ],
             std::process::Stdio::null(),
         )
-        .unwrap();
+        .unwrap()
+        .paths();
 
         // verify
         assert_eq!(result, Vec::<String>::new());
@@ -1333,6 +1453,7 @@ fn inspect_test() {
 // + service1/hello.txt
 // + service1/unknown.txt
 // + service2/sver.toml → dependency = [ "service1/hello.txt" ]
+#[cfg(feature = "export")]
 #[test]
 fn export_repository() {
     initialize();
@@ -1378,6 +1499,7 @@ fn export_repository() {
 //   + symlink → original/README.txt
 // + original
 //   + README.txt
+#[cfg(feature = "export")]
 #[test]
 fn export_has_symlink_single() {
     initialize();
@@ -1413,6 +1535,7 @@ fn export_has_symlink_single() {
 // + original
 //   + README.txt
 //   + Sample.txt
+#[cfg(feature = "export")]
 #[test]
 fn export_has_symlink_dir() {
     initialize();
@@ -1448,6 +1571,7 @@ fn export_has_symlink_dir() {
 // repo layout
 // .
 // + sub → submodule ../sub e40a885afd013606e105c027a5c31910137e5566
+#[cfg(feature = "export")]
 #[test]
 fn export_has_submodule() {
     initialize();
@@ -1504,3 +1628,5373 @@ fn export_has_submodule() {
     assert!(export_dir.as_path().join("sub").is_dir());
     assert!(export_dir.as_path().join("sub").join(".git").exists());
 }
+
+// repo layout
+// .
+// + lib1/hello.txt
+// + service1/sver.toml → dependency = [ "service2", "lib1" ]
+// + service2/sver.toml → [default] dependency = [ "service1" ], [prof1] dependency = [ "lib1/hello.txt" ]
+#[test]
+fn dependency_graph() {
+    initialize();
+
+    // setup
+    let repo = setup_test_repository();
+    add_blob(&repo, "lib1/hello.txt", "hello".as_bytes());
+    add_blob(
+        &repo,
+        "service1/sver.toml",
+        "
+        [default]
+        dependencies = [
+            \"service2\",
+            \"lib1/\",
+        ]"
+        .as_bytes(),
+    );
+    add_blob(
+        &repo,
+        "service2/sver.toml",
+        "
+        [default]
+        dependencies = [
+            \"service1\",
+        ]
+
+        [prof1]
+        dependencies = [
+            \"lib1/hello.txt\",
+        ]"
+        .as_bytes(),
+    );
+    commit(&repo, "setup");
+
+    let sver_repo = SverRepository::new(&calc_target_path(&repo, "")).unwrap();
+
+    // exercise
+    let graph = sver_repo.dependency_graph().unwrap();
+    let dot = graph.to_string();
+
+    // verify
+    assert_eq!(graph.edges.len(), 4);
+    assert!(dot.starts_with("digraph sver {\n"));
+    assert!(dot.contains("    \"service1:default\" -> \"service2:default\";\n"));
+    assert!(dot.contains("    \"service1:default\" -> \"lib1:default\";\n"));
+    assert!(dot.contains("    \"service2:default\" -> \"service1:default\";\n"));
+    assert!(dot.contains("    \"service2:prof1\" -> \"lib1/hello.txt:default\";\n"));
+    assert!(dot.ends_with("}\n"));
+}
+
+#[test]
+fn dependency_graph_escape() {
+    initialize();
+
+    // setup
+    let target = CalculationTarget::new("say \"hi\"\\".to_string(), "default".to_string());
+    let dependency = CalculationTarget::new("lib1".to_string(), "default".to_string());
+    let graph = DependencyGraph {
+        nodes: [target.clone(), dependency.clone()].into(),
+        edges: [(target, dependency)].into(),
+    };
+
+    // exercise
+    let dot = graph.to_string();
+
+    // verify
+    assert!(dot.contains("    \"say \\\"hi\\\"\\\\:default\";\n"));
+    assert!(dot.contains("    \"say \\\"hi\\\"\\\\:default\" -> \"lib1:default\";\n"));
+}
+
+// repo layout
+// .
+// + hello.txt (LF / CRLF + trailing whitespace, regular / executable)
+// + image.bin (binary)
+#[test]
+fn normalize_repository() {
+    initialize();
+
+    // setup
+    let repo1 = setup_test_repository();
+    add_blob(&repo1, "hello.txt", "hello\nworld\n".as_bytes());
+    add_blob(&repo1, "image.bin", "\0\r\n".as_bytes());
+    commit(&repo1, "setup");
+
+    let repo2 = setup_test_repository();
+    add_blob_executable(&repo2, "hello.txt", "hello  \r\nworld\t\r\n".as_bytes());
+    add_blob(&repo2, "image.bin", "\0\r\n".as_bytes());
+    commit(&repo2, "setup");
+
+    let repo3 = setup_test_repository();
+    add_blob(&repo3, "hello.txt", "hello\nworld\n".as_bytes());
+    add_blob(&repo3, "image.bin", "\0\n".as_bytes());
+    commit(&repo3, "setup");
+
+    let calc = |repo: &Repository, options: CalcOptions| {
+        SverRepository::new(&calc_target_path(repo, ""))
+            .unwrap()
+            .with_options(options)
+            .calc_version()
+            .unwrap()
+            .version
+    };
+
+    // exercise & verify
+    assert_ne!(
+        calc(&repo1, CalcOptions::default()),
+        calc(&repo2, CalcOptions::default())
+    );
+    assert_eq!(
+        calc(&repo1, CalcOptions::normalized()),
+        calc(&repo2, CalcOptions::normalized())
+    );
+    // normalization does not change the version of already normalized content
+    assert_eq!(
+        calc(&repo1, CalcOptions::default()),
+        calc(&repo1, CalcOptions::normalized())
+    );
+    // binary files are not normalized
+    assert_ne!(
+        calc(&repo1, CalcOptions::normalized()),
+        calc(&repo3, CalcOptions::normalized())
+    );
+    // each normalization only works on its own difference
+    assert_ne!(
+        calc(
+            &repo1,
+            CalcOptions {
+                ignore_mode: true,
+                ..Default::default()
+            }
+        ),
+        calc(
+            &repo2,
+            CalcOptions {
+                ignore_mode: true,
+                ..Default::default()
+            }
+        )
+    );
+}
+
+// repo layout
+// .
+// + lib1/test1.txt
+// + lib1/test2.txt
+// + lib1/sver.toml → [default] no setting, [prof1] excludes = ["test2.txt"]
+// + lib2/sver.toml → [default] no setting, [prof2] dependency = ["lib1:prof1"], [prof3] dependency = ["lib1/test2.txt"]
+#[test]
+fn list_targets_multiprofile_multidir() {
+    initialize();
+
+    // setup
+    let repo = setup_test_repository();
+    add_blob(&repo, "lib1/test1.txt", "hello".as_bytes());
+    add_blob(&repo, "lib1/test2.txt", "world".as_bytes());
+    add_blob(
+        &repo,
+        "lib1/sver.toml",
+        "
+        [default]
+
+        [prof1]
+        excludes = [
+            \"test2.txt\",
+        ]"
+        .as_bytes(),
+    );
+    add_blob(
+        &repo,
+        "lib2/sver.toml",
+        "
+        [default]
+
+        [prof2]
+        dependencies = [
+            \"lib1:prof1\",
+        ]
+
+        [prof3]
+        dependencies = [
+            \"lib1/test2.txt\",
+        ]"
+        .as_bytes(),
+    );
+    commit(&repo, "setup");
+
+    let sver_repo = SverRepository::new(&calc_target_path(&repo, "")).unwrap();
+
+    // exercise
+    let targets = sver_repo.list_targets().unwrap();
+
+    // verify
+    assert_eq!(
+        targets
+            .iter()
+            .map(|t| t.to_string())
+            .collect::<Vec<String>>(),
+        vec![
+            "lib1:default",
+            "lib1:prof1",
+            "lib2:default",
+            "lib2:prof2",
+            "lib2:prof3",
+        ]
+    );
+}
+
+// repo layout
+// .
+// + lib1/hello.txt → no config file
+// + sver.toml → dependency = [ "lib1" ]
+#[test]
+fn list_targets_implicit_default() {
+    initialize();
+
+    // setup
+    let repo = setup_test_repository();
+    add_blob(&repo, "lib1/hello.txt", "hello".as_bytes());
+    add_blob(
+        &repo,
+        "sver.toml",
+        "
+        [default]
+        dependencies = [
+            \"lib1\",
+        ]"
+        .as_bytes(),
+    );
+    commit(&repo, "setup");
+
+    let sver_repo = SverRepository::new(&calc_target_path(&repo, "")).unwrap();
+
+    // exercise
+    let targets = sver_repo.list_targets().unwrap();
+
+    // verify
+    assert_eq!(
+        targets,
+        vec![
+            CalculationTarget::new("".to_string(), "default".to_string()),
+            CalculationTarget::new("lib1".to_string(), "default".to_string()),
+        ]
+    );
+    assert_eq!(targets[0].to_string(), ".:default");
+}
+
+// repo layout
+// .
+// + lib1/hello.txt → no config file in the repository
+#[test]
+fn list_targets_without_config() {
+    initialize();
+
+    // setup
+    let repo = setup_test_repository();
+    add_blob(&repo, "lib1/hello.txt", "hello".as_bytes());
+    commit(&repo, "setup");
+
+    let sver_repo = SverRepository::new(&calc_target_path(&repo, "lib1")).unwrap();
+
+    // exercise
+    let targets = sver_repo.list_targets().unwrap();
+
+    // verify
+    assert!(targets.is_empty());
+}
+
+// repo layout
+// .
+// + lib1/test1.txt
+// + lib1/sver.toml → [default] no setting, [prof1] excludes = ["test1.txt"]
+// + lib2/sver.toml → [default] dependency = ["lib1:prof1"], [prof2] dependency = ["unknown"], [prof3] no setting
+#[test]
+fn validate_streaming() {
+    initialize();
+
+    // setup
+    let repo = setup_test_repository();
+    add_blob(&repo, "lib1/test1.txt", "hello".as_bytes());
+    add_blob(
+        &repo,
+        "lib1/sver.toml",
+        "
+        [default]
+
+        [prof1]
+        excludes = [
+            \"test1.txt\",
+        ]"
+        .as_bytes(),
+    );
+    add_blob(
+        &repo,
+        "lib2/sver.toml",
+        "
+        [default]
+        dependencies = [
+            \"lib1:prof1\",
+        ]
+
+        [prof2]
+        dependencies = [
+            \"unknown\",
+        ]
+
+        [prof3]"
+            .as_bytes(),
+    );
+    commit(&repo, "setup");
+
+    let sver_repo = SverRepository::new(&calc_target_path(&repo, "")).unwrap();
+
+    // exercise
+    let mut count = 0;
+    let mut invalid_count = 0;
+    sver_repo
+        .validate_streaming(|result| {
+            count += 1;
+            if result.is_invalid() {
+                invalid_count += 1;
+            }
+        })
+        .unwrap();
+
+    // verify
+    assert_eq!(count, 5);
+    assert_eq!(invalid_count, 1);
+    let ValidationResults {
+        has_invalid,
+        results,
+        ..
+    } = sver_repo.validate_sver_config().unwrap();
+    assert!(has_invalid);
+    assert_eq!(results.len(), count);
+}
+
+// repo layout
+// .
+// + service1/hello.txt
+// + service2/sver.toml → dependency = [ "service1" ] or [ "service1/hello.txt" ], excludes = [ "sver.toml" ]
+#[test]
+fn hash_graph_repository() {
+    initialize();
+
+    // setup
+    let setup = |dependency: &str| {
+        let repo = setup_test_repository();
+        add_blob(&repo, "service1/hello.txt", "hello world!".as_bytes());
+        add_blob(
+            &repo,
+            "service2/sver.toml",
+            format!(
+                "
+                [default]
+                dependencies = [
+                    \"{dependency}\",
+                ]
+                excludes = [
+                    \"sver.toml\",
+                ]"
+            )
+            .as_bytes(),
+        );
+        commit(&repo, "setup");
+        repo
+    };
+    let repo1 = setup("service1");
+    let repo2 = setup("service1/hello.txt");
+
+    let calc = |repo: &Repository, hash_graph: bool| {
+        let sver_repo = SverRepository::new(&calc_target_path(repo, "service2"))
+            .unwrap()
+            .with_options(CalcOptions {
+                hash_graph,
+                ..Default::default()
+            });
+        (
+            sver_repo.list_sources().unwrap(),
+            sver_repo.calc_version().unwrap().version,
+        )
+    };
+
+    // exercise
+    let (sources1, version1) = calc(&repo1, false);
+    let (sources2, version2) = calc(&repo2, false);
+    let (_, graph_version1) = calc(&repo1, true);
+    let (_, graph_version2) = calc(&repo2, true);
+
+    // verify
+    assert_eq!(sources1, vec!["service1/hello.txt"]);
+    assert_eq!(sources1, sources2);
+    assert_eq!(version1, version2);
+    assert_ne!(graph_version1, graph_version2);
+    assert_ne!(version1, graph_version1);
+}
+
+// repo layout
+// .
+// + lib1/hello.txt
+// + service1/sver.toml → [prof1] dependency = [ "lib1", "unknown" ] (no default profile)
+// + service2/sver.toml (not staged)
+// + linkdir/symlink → ../../outside.txt
+#[test]
+fn doctor_repository() {
+    initialize();
+
+    // setup
+    let repo = setup_test_repository();
+    add_blob(&repo, "lib1/hello.txt", "hello".as_bytes());
+    add_blob(
+        &repo,
+        "service1/sver.toml",
+        "
+        [prof1]
+        dependencies = [
+            \"lib1\",
+            \"unknown\",
+        ]"
+        .as_bytes(),
+    );
+    add_symlink(&repo, "linkdir/symlink", "../../outside.txt");
+    commit(&repo, "setup");
+    create_dir(repo.workdir().unwrap().join("service2")).unwrap();
+    std::fs::write(
+        repo.workdir().unwrap().join("service2/sver.toml"),
+        "[default]",
+    )
+    .unwrap();
+
+    // exercise
+    let diagnostics = diagnose(&calc_target_path(&repo, "")).unwrap();
+
+    // verify
+    let messages = diagnostics
+        .iter()
+        .map(|d| (d.severity, d.message.as_str()))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        messages,
+        vec![
+            (
+                Severity::Error,
+                "dependency resolves to no files. path:service1/sver.toml:[prof1], dependency:unknown"
+            ),
+            (
+                Severity::Warning,
+                "config file is not staged. path:service2/sver.toml"
+            ),
+            (
+                Severity::Warning,
+                "symbolic link points outside of the repository. path:linkdir/symlink, target:../../outside.txt"
+            ),
+            (
+                Severity::Warning,
+                "profile[default] is not found. path:service1/sver.toml"
+            ),
+        ]
+    );
+}
+
+#[test]
+fn doctor_empty_and_bare_repository() {
+    initialize();
+
+    // setup
+    let repo = setup_test_repository();
+    let mut bare_repo_dir = temp_dir();
+    bare_repo_dir.push(format!("sver-{}", Uuid::now_v7()));
+    Repository::init_bare(&bare_repo_dir).unwrap();
+
+    // exercise
+    let empty_diagnostics = diagnose(&calc_target_path(&repo, "")).unwrap();
+    let bare_diagnostics = diagnose(bare_repo_dir.to_str().unwrap()).unwrap();
+
+    // verify
+    assert_eq!(empty_diagnostics.len(), 1);
+    assert_eq!(empty_diagnostics[0].severity, Severity::Warning);
+    assert!(empty_diagnostics[0].message.starts_with("index is empty."));
+    assert_eq!(bare_diagnostics.len(), 1);
+    assert_eq!(bare_diagnostics[0].severity, Severity::Error);
+    assert_eq!(
+        bare_diagnostics[0].message,
+        "bare repository is not supported"
+    );
+}
+
+// repo layout
+// .
+// + hello.txt
+// + docs/api.md
+// + docs/guide.md
+// + sver.toml → excludes = [ "docs/**", "!docs/api.md" ]
+#[test]
+fn negated_exclude_repository() {
+    initialize();
+
+    // setup
+    let repo = setup_test_repository();
+    add_blob(&repo, "hello.txt", "hello".as_bytes());
+    add_blob(&repo, "docs/api.md", "api".as_bytes());
+    add_blob(&repo, "docs/guide.md", "guide".as_bytes());
+    add_blob(
+        &repo,
+        "sver.toml",
+        "
+        [default]
+        excludes = [
+            \"docs/**\",
+            \"!docs/api.md\",
+        ]"
+        .as_bytes(),
+    );
+    commit(&repo, "setup");
+
+    let sver_repo = SverRepository::new(&calc_target_path(&repo, "")).unwrap();
+
+    // exercise
+    let sources = sver_repo.list_sources().unwrap();
+    let version = sver_repo.calc_version().unwrap();
+
+    // verify
+    assert_eq!(sources, vec!["docs/api.md", "hello.txt", "sver.toml"]);
+    assert!(!sver_repo.validate_sver_config().unwrap().has_invalid);
+
+    // re-included file affects the version
+    add_blob(&repo, "docs/api.md", "api v2".as_bytes());
+    add_blob(&repo, "docs/guide.md", "guide v2".as_bytes());
+    commit(&repo, "update docs");
+    let sver_repo = SverRepository::new(&calc_target_path(&repo, "")).unwrap();
+    assert_ne!(sver_repo.calc_version().unwrap().version, version.version);
+}
+
+// repo layout
+// .
+// + hello.txt
+// + docs/guide.md
+// + sver.toml → excludes = [ "docs/**", "!docs/api.md" ]
+#[test]
+fn invalid_negated_exclude_repository() {
+    initialize();
+
+    // setup
+    let repo = setup_test_repository();
+    add_blob(&repo, "hello.txt", "hello".as_bytes());
+    add_blob(&repo, "docs/guide.md", "guide".as_bytes());
+    add_blob(
+        &repo,
+        "sver.toml",
+        "
+        [default]
+        excludes = [
+            \"docs/**\",
+            \"!docs/api.md\",
+        ]"
+        .as_bytes(),
+    );
+    commit(&repo, "setup");
+
+    let sver_repo = SverRepository::new(&calc_target_path(&repo, "")).unwrap();
+
+    // exercise
+    let ValidationResults {
+        has_invalid,
+        mut results,
+        ..
+    } = sver_repo.validate_sver_config().unwrap();
+
+    // verify
+    assert!(has_invalid);
+    if let Some(ValidationResult::Invalid {
+        invalid_excludes, ..
+    }) = results.pop()
+    {
+        assert_eq!(invalid_excludes, vec!["!docs/api.md"]);
+    } else {
+        unreachable!("this line will not be execute");
+    }
+}
+
+// repo layout
+// .
+// + hello.txt
+// + lib1/sver.toml → env = [ "SVER_TEST_TARGET_ARCH" ]
+// + sver.toml → dependency = [ "lib1" ]
+#[test]
+fn env_repository() {
+    initialize();
+
+    // setup
+    let env_name = format!("SVER_TEST_TARGET_ARCH_{}", Uuid::now_v7().simple());
+    let repo = setup_test_repository();
+    add_blob(&repo, "hello.txt", "hello".as_bytes());
+    add_blob(
+        &repo,
+        "lib1/sver.toml",
+        format!(
+            "
+            [default]
+            env = [
+                \"{env_name}\",
+            ]"
+        )
+        .as_bytes(),
+    );
+    add_blob(
+        &repo,
+        "sver.toml",
+        "
+        [default]
+        dependencies = [
+            \"lib1\",
+        ]"
+        .as_bytes(),
+    );
+    commit(&repo, "setup");
+
+    let calc = || {
+        SverRepository::new(&calc_target_path(&repo, ""))
+            .unwrap()
+            .calc_version()
+            .unwrap()
+            .version
+    };
+
+    // exercise
+    let unset_version = calc();
+    std::env::set_var(&env_name, "x86_64");
+    let x86_version = calc();
+    std::env::set_var(&env_name, "aarch64");
+    let aarch_version = calc();
+    std::env::set_var(&env_name, "");
+    let empty_version = calc();
+    std::env::remove_var(&env_name);
+
+    // verify
+    assert_ne!(unset_version, x86_version);
+    assert_ne!(x86_version, aarch_version);
+    assert_ne!(unset_version, empty_version);
+    assert_eq!(unset_version, calc());
+}
+
+// repo layout
+// .
+// + lib/common.txt
+// + linkdir/symlink → ../lib
+// + service1/sver.toml → dependency = [ "lib" ]
+// + service2/sver.toml → dependency = [ "lib", "lib/common.txt", "service1" ], excludes = [ "sver.toml" ]
+// + service3/sver.toml → dependency = [ "service1" ], excludes = [ "sver.toml" ]
+#[test]
+fn duplicated_entries_repository() {
+    initialize();
+
+    // setup
+    let repo = setup_test_repository();
+    add_blob(&repo, "lib/common.txt", "common".as_bytes());
+    add_symlink(&repo, "linkdir/symlink", "../lib");
+    add_blob(
+        &repo,
+        "service1/sver.toml",
+        "
+        [default]
+        dependencies = [
+            \"lib\",
+        ]"
+        .as_bytes(),
+    );
+    add_blob(
+        &repo,
+        "service2/sver.toml",
+        "
+        [default]
+        dependencies = [
+            \"lib\",
+            \"lib/common.txt\",
+            \"service1\",
+        ]
+        excludes = [
+            \"sver.toml\",
+        ]"
+        .as_bytes(),
+    );
+    add_blob(
+        &repo,
+        "service3/sver.toml",
+        "
+        [default]
+        dependencies = [
+            \"service1\",
+        ]
+        excludes = [
+            \"sver.toml\",
+        ]"
+        .as_bytes(),
+    );
+    commit(&repo, "setup");
+
+    let service2 = SverRepository::new(&calc_target_path(&repo, "service2")).unwrap();
+    let service3 = SverRepository::new(&calc_target_path(&repo, "service3")).unwrap();
+
+    // exercise
+    let sources2 = service2.list_sources().unwrap();
+    let sources3 = service3.list_sources().unwrap();
+    let version2 = service2.calc_version().unwrap();
+    let version3 = service3.calc_version().unwrap();
+
+    // verify
+    assert_eq!(sources2, vec!["lib/common.txt", "service1/sver.toml"]);
+    assert_eq!(sources2, sources3);
+    // the version contains the target path, so compare with the hash of the same path.
+    assert_ne!(version2.version, version3.version);
+    let expected = {
+        let repo = setup_test_repository();
+        add_blob(&repo, "lib/common.txt", "common".as_bytes());
+        add_blob(
+            &repo,
+            "service1/sver.toml",
+            "
+        [default]
+        dependencies = [
+            \"lib\",
+        ]"
+            .as_bytes(),
+        );
+        add_blob(
+            &repo,
+            "service2/sver.toml",
+            "
+            [default]
+            dependencies = [
+                \"service1\",
+            ]
+            excludes = [
+                \"sver.toml\",
+            ]"
+            .as_bytes(),
+        );
+        commit(&repo, "setup");
+        SverRepository::new(&calc_target_path(&repo, "service2"))
+            .unwrap()
+            .calc_version()
+            .unwrap()
+    };
+    assert_eq!(version2.version, expected.version);
+
+    // symbolic link resolution reaches the same file again
+    let linkdir = SverRepository::new(&calc_target_path(&repo, "linkdir")).unwrap();
+    assert_eq!(
+        linkdir.list_sources().unwrap(),
+        vec!["lib/common.txt", "linkdir/symlink"]
+    );
+}
+
+// repo layout
+// .
+// + hello.txt (conflicted. stage 2: regular, stage 3: executable)
+#[test]
+fn conflicted_entries_repository() {
+    initialize();
+
+    // setup
+    let conflicted_repo = setup_test_repository();
+    add_conflicted_file(
+        &conflicted_repo,
+        "hello.txt",
+        "ours".as_bytes(),
+        FileMode::Blob,
+        2,
+    );
+    add_conflicted_file(
+        &conflicted_repo,
+        "hello.txt",
+        "theirs".as_bytes(),
+        FileMode::BlobExecutable,
+        3,
+    );
+    let resolved_repo = setup_test_repository();
+    add_blob_executable(&resolved_repo, "hello.txt", "theirs".as_bytes());
+
+    let conflicted = SverRepository::new(&calc_target_path(&conflicted_repo, "")).unwrap();
+    let resolved = SverRepository::new(&calc_target_path(&resolved_repo, "")).unwrap();
+
+    // exercise & verify
+    assert_eq!(conflicted.list_sources().unwrap(), vec!["hello.txt"]);
+    assert_eq!(
+        conflicted.calc_version().unwrap().version,
+        resolved.calc_version().unwrap().version
+    );
+}
+
+// repo layout
+// .
+// + lib1/test1.txt
+// + lib1/test2.txt
+// + lib1/sver.toml → [default] no setting, [prof1] excludes = ["test2.txt"]
+#[test]
+fn new_with_target() {
+    initialize();
+
+    // setup
+    let repo = setup_test_repository();
+    add_blob(&repo, "lib1/test1.txt", "hello".as_bytes());
+    add_blob(&repo, "lib1/test2.txt", "world".as_bytes());
+    add_blob(
+        &repo,
+        "lib1/sver.toml",
+        "
+        [default]
+
+        [prof1]
+        excludes = [
+            \"test2.txt\",
+        ]"
+        .as_bytes(),
+    );
+    commit(&repo, "setup");
+
+    let parsed = SverRepository::new(&calc_target_path(&repo, "lib1:prof1")).unwrap();
+
+    // exercise
+    let sver_repo = SverRepository::new_with_target(
+        &calc_target_path(&repo, ""),
+        CalculationTarget::new("lib1".to_string(), "prof1".to_string()),
+    )
+    .unwrap();
+    let root_repo = SverRepository::new_with_target(
+        &calc_target_path(&repo, "lib1"),
+        CalculationTarget::new(".".to_string(), "default".to_string()),
+    )
+    .unwrap();
+
+    // verify
+    assert_eq!(
+        sver_repo.list_sources().unwrap(),
+        vec!["lib1/sver.toml", "lib1/test1.txt"]
+    );
+    assert_eq!(
+        sver_repo.calc_version().unwrap().version,
+        parsed.calc_version().unwrap().version
+    );
+    assert_eq!(
+        root_repo.calc_version().unwrap().version,
+        SverRepository::new(&calc_target_path(&repo, ""))
+            .unwrap()
+            .calc_version()
+            .unwrap()
+            .version
+    );
+}
+
+// repo layout
+// .
+// + dir:prof/hello.txt
+#[cfg(not(target_os = "windows"))]
+#[test]
+fn new_with_target_colon_path() {
+    initialize();
+
+    // setup
+    let repo = setup_test_repository();
+    add_blob(&repo, "dir:prof/hello.txt", "hello".as_bytes());
+    commit(&repo, "setup");
+
+    // exercise
+    let sver_repo = SverRepository::new_with_target(
+        &calc_target_path(&repo, ""),
+        CalculationTarget::new("dir:prof".to_string(), "default".to_string()),
+    )
+    .unwrap();
+
+    // verify
+    assert_eq!(
+        sver_repo.list_sources().unwrap(),
+        vec!["dir:prof/hello.txt"]
+    );
+    // `path:profile` string is parsed as profile "prof" of "dir"
+    assert!(SverRepository::new(&calc_target_path(&repo, "dir:prof")).is_err());
+}
+
+// repo layout
+// (no repository)
+#[test]
+fn error_repository_not_found() {
+    initialize();
+
+    // setup
+    let mut tmp_dir = temp_dir();
+    tmp_dir.push(format!("sver-{}", Uuid::now_v7()));
+    create_dir(tmp_dir.clone()).unwrap();
+
+    // exercise
+    let result = SverRepository::new(tmp_dir.to_str().unwrap());
+
+    // verify
+    // the search may stop at the mount point when the temporary directory is another filesystem
+    assert!(matches!(
+        result,
+        Err(SverError::RepositoryNotFound) | Err(SverError::RepositoryNotFoundBelow { .. })
+    ));
+}
+
+// repo layout
+// .
+// + hello.txt
+// + sver.toml
+#[test]
+fn error_profile_not_found() {
+    initialize();
+
+    // setup
+    let repo = setup_test_repository();
+    add_blob(&repo, "hello.txt", "hello".as_bytes());
+    add_blob(
+        &repo,
+        "sver.toml",
+        "[default]
+        dependencies = []"
+            .as_bytes(),
+    );
+    commit(&repo, "setup");
+
+    // exercise
+    let result = SverRepository::new(&calc_target_path_with_profile(&repo, "", "missing"))
+        .and_then(|sver_repo| sver_repo.calc_version());
+
+    // verify
+    match result {
+        Err(SverError::ProfileNotFound(profile)) => assert_eq!(profile, "missing"),
+        other => unreachable!("unexpected result. {:?}", other.map(|v| v.version)),
+    }
+}
+
+// repo layout
+// .
+// + service1/hello.txt
+// + service2/sver.toml → dependency = [ "service1/hello-hello.txt", "unknown/path", "service1" ]
+#[test]
+fn allow_invalid_dependencies_repository() {
+    initialize();
+
+    // setup
+    let repo = setup_test_repository();
+    add_blob(&repo, "service1/hello.txt", "hello world!".as_bytes());
+    add_blob(
+        &repo,
+        "service2/sver.toml",
+        "
+        [default]
+        dependencies = [
+            \"service1/hello-hello.txt\",
+            \"unknown/path\",
+            \"service1\",
+        ]"
+        .as_bytes(),
+    );
+    commit(&repo, "setup");
+
+    let sver_repo = SverRepository::new(&calc_target_path(&repo, "service2")).unwrap();
+
+    // exercise
+    let partially_allowed = sver_repo
+        .validate_sver_config()
+        .unwrap()
+        .allow_dependencies(&["unknown/path".to_string()]);
+    let ValidationResults {
+        has_invalid,
+        mut results,
+        ..
+    } = sver_repo
+        .validate_sver_config()
+        .unwrap()
+        .allow_dependencies(&[
+            "service1/hello-hello.txt".to_string(),
+            "unknown/path".to_string(),
+        ]);
+
+    // verify
+    assert!(partially_allowed.has_invalid);
+    if let Some(ValidationResult::Invalid {
+        invalid_dependencies,
+        ..
+    }) = partially_allowed.results.first()
+    {
+        assert_eq!(invalid_dependencies, &vec!["service1/hello-hello.txt"]);
+    } else {
+        unreachable!("this line will not be execute");
+    }
+    assert!(!has_invalid);
+    assert_eq!(results.len(), 1);
+    if let Some(ValidationResult::Valid {
+        calcuration_target: CalculationTarget { path, profile },
+        ..
+    }) = results.pop()
+    {
+        assert_eq!(path, "service2");
+        assert_eq!(profile, "default");
+    } else {
+        unreachable!("this line will not be execute");
+    }
+}
+
+// repo layout
+// .
+// + service1/doc/README.md
+// + service1/doc/api.md
+// + service1/hello.txt
+// + service1/error.log
+// + service1/keep.log
+// + service1/sver.toml → excludes = [ "doc", "*.log", "!doc/api.md", "target", "!keep.log" ], dependencies = [ "lib1", "lib2", "lib2/gen" ]
+// + lib1/build/out.o
+// + lib1/lib.rs
+// + lib1/sver.toml → excludes = [ "build" ]
+// + lib2/gen/out.rs
+// + lib2/sver.toml → excludes = [ "gen" ]
+#[test]
+fn report_excludes() {
+    initialize();
+
+    // setup
+    let repo = setup_test_repository();
+    add_blob(&repo, "service1/doc/README.md", "readme".as_bytes());
+    add_blob(&repo, "service1/doc/api.md", "api".as_bytes());
+    add_blob(&repo, "service1/hello.txt", "hello".as_bytes());
+    add_blob(&repo, "service1/error.log", "error".as_bytes());
+    add_blob(&repo, "service1/keep.log", "keep".as_bytes());
+    add_blob(
+        &repo,
+        "service1/sver.toml",
+        "
+        [default]
+        excludes = [\"doc\", \"*.log\", \"!doc/api.md\", \"target\", \"!keep.log\"]
+        dependencies = [\"lib1\", \"lib2\", \"lib2/gen\"]"
+            .as_bytes(),
+    );
+    add_blob(&repo, "lib2/gen/out.rs", "generated".as_bytes());
+    add_blob(
+        &repo,
+        "lib2/sver.toml",
+        "
+        [default]
+        excludes = [\"gen\"]"
+            .as_bytes(),
+    );
+    add_blob(&repo, "lib1/build/out.o", "binary".as_bytes());
+    add_blob(&repo, "lib1/lib.rs", "lib".as_bytes());
+    add_blob(
+        &repo,
+        "lib1/sver.toml",
+        "
+        [default]
+        excludes = [\"build\"]"
+            .as_bytes(),
+    );
+    commit(&repo, "setup");
+
+    // exercise
+    let sver_repo = SverRepository::new(&calc_target_path(&repo, "service1")).unwrap();
+    let reports = sver_repo
+        .report_excludes()
+        .unwrap()
+        .into_iter()
+        .map(|report| {
+            (
+                report.calculation_target.to_string(),
+                report.exclude,
+                report.excluded_files,
+            )
+        })
+        .collect::<Vec<_>>();
+
+    // verify
+    assert_eq!(
+        reports,
+        vec![
+            ("lib1:default".to_string(), "build".to_string(), 1),
+            // re-included by the dependency on lib2/gen
+            ("lib2:default".to_string(), "gen".to_string(), 0),
+            ("service1:default".to_string(), "doc".to_string(), 1),
+            ("service1:default".to_string(), "*.log".to_string(), 1),
+            ("service1:default".to_string(), "target".to_string(), 0),
+        ]
+    );
+}
+
+// repo layout
+// .
+// + hello.txt
+// + caf\xe9.txt (Latin-1, not valid UTF-8)
+#[test]
+fn non_utf8_path_repository() {
+    initialize();
+
+    // setup
+    let repo = setup_test_repository();
+    add_blob(&repo, "hello.txt", "hello".as_bytes());
+    add_blob_with_raw_path(&repo, b"caf\xe9.txt", "coffee".as_bytes());
+    commit(&repo, "setup");
+
+    let sver_repo = SverRepository::new(&calc_target_path(&repo, "")).unwrap();
+
+    // exercise
+    let version = sver_repo.calc_version();
+    let sources = sver_repo.list_sources();
+
+    // verify
+    assert!(version.is_ok());
+    match sources {
+        Err(SverError::NonUtf8Path(path)) => assert_eq!(path, "caf\u{fffd}.txt"),
+        other => unreachable!("unexpected result. {:?}", other),
+    }
+}
+
+// repo layout
+// .
+// + lib1/hello.txt
+// + lib1/removed.txt (removed in the second commit)
+// + lib1/added.txt (added in the second commit)
+// + service1/main.rs
+// + service1/sver.toml → dependency = [ "lib1" ]
+#[test]
+fn diff_sources_between_revisions() {
+    initialize();
+
+    // setup
+    let repo = setup_test_repository();
+    add_blob(&repo, "lib1/hello.txt", "hello".as_bytes());
+    add_blob(&repo, "lib1/removed.txt", "removed".as_bytes());
+    add_blob(&repo, "service1/main.rs", "fn main() {}".as_bytes());
+    add_blob(
+        &repo,
+        "service1/sver.toml",
+        "
+        [default]
+        dependencies = [\"lib1\"]"
+            .as_bytes(),
+    );
+    commit(&repo, "first");
+    add_blob(&repo, "lib1/hello.txt", "hello world".as_bytes());
+    add_blob(&repo, "lib1/added.txt", "added".as_bytes());
+    let mut index = repo.index().unwrap();
+    index.remove_path(Path::new("lib1/removed.txt")).unwrap();
+    index.write().unwrap();
+    commit(&repo, "second");
+
+    let sver_repo = SverRepository::new(&calc_target_path(&repo, "service1")).unwrap();
+
+    // exercise
+    let diff = sver_repo.diff_sources("HEAD~1", "HEAD").unwrap();
+    let previous = sver_repo.calc_version_at("HEAD~1").unwrap();
+    let current = sver_repo.calc_version_at("HEAD").unwrap();
+
+    // verify
+    assert_eq!(diff.added, vec!["lib1/added.txt"]);
+    assert_eq!(diff.removed, vec!["lib1/removed.txt"]);
+    assert_eq!(diff.modified, vec!["lib1/hello.txt"]);
+    assert_ne!(previous.version, current.version);
+    assert_eq!(
+        current.version,
+        sver_repo.calc_version().unwrap().version,
+        "HEAD and index are same"
+    );
+}
+
+// repo layout
+// .
+// + service1/hello.txt
+// + service2/hello.txt
+#[test]
+fn preview_version_with_staged_changes() {
+    initialize();
+
+    // setup
+    let repo = setup_test_repository();
+    let sver_repo = SverRepository::new(&calc_target_path(&repo, "")).unwrap();
+    let unborn = sver_repo.preview_version().unwrap();
+    add_blob(&repo, "service1/hello.txt", "hello".as_bytes());
+    add_blob(&repo, "service2/hello.txt", "hello".as_bytes());
+    commit(&repo, "setup");
+
+    let service1 = SverRepository::new(&calc_target_path(&repo, "service1")).unwrap();
+    let service2 = SverRepository::new(&calc_target_path(&repo, "service2")).unwrap();
+
+    // exercise
+    let committed = service1.preview_version().unwrap();
+    add_blob(&repo, "service2/hello.txt", "hello world".as_bytes());
+    let unaffected = service1.preview_version().unwrap();
+    let affected = service2.preview_version().unwrap();
+
+    // verify
+    assert!(unborn.head.is_none());
+    assert!(unborn.is_changed());
+    assert!(!committed.is_changed());
+    assert!(!unaffected.is_changed());
+    assert_eq!(unaffected.head.unwrap().version, unaffected.index.version);
+    assert!(affected.is_changed());
+    assert_ne!(affected.head.unwrap().version, affected.index.version);
+}
+
+// repo layout
+// .
+// + lib1/test1.txt (modified in the second commit)
+// + lib1/sver.toml
+// + lib2/test2.txt
+// + lib2/sver.toml → [default], [prof2] dependency = [ "lib1" ]
+// + service1/hello.txt
+// + service1/sver.toml
+// + service2/sver.toml (added in the second commit)
+#[test]
+fn changed_targets_between_revisions() {
+    initialize();
+
+    // setup
+    let repo = setup_test_repository();
+    add_blob(&repo, "lib1/test1.txt", "test1".as_bytes());
+    add_blob(&repo, "lib1/sver.toml", "[default]".as_bytes());
+    add_blob(&repo, "lib2/test2.txt", "test2".as_bytes());
+    add_blob(
+        &repo,
+        "lib2/sver.toml",
+        "
+        [default]
+
+        [prof2]
+        dependencies = [\"lib1\"]"
+            .as_bytes(),
+    );
+    add_blob(&repo, "service1/hello.txt", "hello".as_bytes());
+    add_blob(&repo, "service1/sver.toml", "[default]".as_bytes());
+    commit(&repo, "first");
+    add_blob(&repo, "lib1/test1.txt", "test1 modified".as_bytes());
+    add_blob(&repo, "service2/sver.toml", "[default]".as_bytes());
+    commit(&repo, "second");
+
+    let sver_repo = SverRepository::new(&calc_target_path(&repo, "")).unwrap();
+
+    // exercise
+    let changed = sver_repo.changed_targets("HEAD~1", "HEAD").unwrap();
+    let unchanged = sver_repo.changed_targets("HEAD", "HEAD").unwrap();
+
+    // verify
+    assert_eq!(
+        changed
+            .iter()
+            .map(|target| target.to_string())
+            .collect::<Vec<_>>(),
+        vec!["lib1:default", "lib2:prof2", "service2:default"]
+    );
+    assert!(unchanged.is_empty());
+}
+
+// repo layout
+// .
+// + .gitignore → "*.log"
+// + service1/hello.txt
+// + service1/new.txt (untracked)
+// + service1/debug.log (untracked, ignored)
+#[test]
+fn include_untracked_files() {
+    initialize();
+
+    // setup
+    let repo = setup_test_repository();
+    add_blob(&repo, ".gitignore", "*.log".as_bytes());
+    add_blob(&repo, "service1/hello.txt", "hello".as_bytes());
+    commit(&repo, "setup");
+    let workdir = repo.workdir().unwrap();
+    std::fs::write(workdir.join(".gitignore"), "*.log").unwrap();
+    std::fs::create_dir_all(workdir.join("service1")).unwrap();
+    std::fs::write(workdir.join("service1/hello.txt"), "hello").unwrap();
+
+    let target_path = calc_target_path(&repo, "service1");
+    let untracked_options = CalcOptions {
+        include_untracked: true,
+        ..Default::default()
+    };
+    let before = SverRepository::new(&target_path)
+        .unwrap()
+        .calc_version()
+        .unwrap();
+    let before_untracked = SverRepository::new(&target_path)
+        .unwrap()
+        .with_options(untracked_options.clone())
+        .calc_version()
+        .unwrap();
+
+    // exercise
+    std::fs::write(workdir.join("service1/debug.log"), "debug").unwrap();
+    let ignored_only = SverRepository::new(&target_path)
+        .unwrap()
+        .with_options(untracked_options.clone())
+        .calc_version()
+        .unwrap();
+    std::fs::write(workdir.join("service1/new.txt"), "new").unwrap();
+    let default_repo = SverRepository::new(&target_path).unwrap();
+    let untracked_repo = SverRepository::new(&target_path)
+        .unwrap()
+        .with_options(untracked_options);
+
+    // verify
+    assert_eq!(before.version, before_untracked.version);
+    assert_eq!(before.version, ignored_only.version);
+    assert_eq!(default_repo.calc_version().unwrap().version, before.version);
+    assert_ne!(
+        untracked_repo.calc_version().unwrap().version,
+        before.version
+    );
+    assert_eq!(
+        untracked_repo.list_sources().unwrap(),
+        vec!["service1/hello.txt", "service1/new.txt"]
+    );
+    // untracked contents are read from the working tree for normalization
+    let normalized = SverRepository::new(&target_path)
+        .unwrap()
+        .with_options(CalcOptions {
+            include_untracked: true,
+            ..CalcOptions::normalized()
+        })
+        .calc_version();
+    assert!(normalized.is_ok());
+}
+
+// repo layout
+// .
+// + lib1/lib.rs
+// + lib1/Cargo.lock
+// + lib2/lib.rs
+// + lib2/Cargo.lock
+// + service1/main.rs
+// + service1/Cargo.lock
+// + service1/sver.toml → dependency = [ "lib1", "lib2" ]
+#[test]
+fn global_excludes_across_dependencies() {
+    initialize();
+
+    // setup
+    let repo = setup_test_repository();
+    add_blob(&repo, "lib1/lib.rs", "lib1".as_bytes());
+    add_blob(&repo, "lib1/Cargo.lock", "lock1".as_bytes());
+    add_blob(&repo, "lib2/lib.rs", "lib2".as_bytes());
+    add_blob(&repo, "lib2/Cargo.lock", "lock2".as_bytes());
+    add_blob(&repo, "service1/main.rs", "main".as_bytes());
+    add_blob(&repo, "service1/Cargo.lock", "lock3".as_bytes());
+    add_blob(
+        &repo,
+        "service1/sver.toml",
+        "
+        [default]
+        dependencies = [\"lib1\", \"lib2\"]"
+            .as_bytes(),
+    );
+    commit(&repo, "setup");
+
+    let options = CalcOptions {
+        global_excludes: vec!["**/Cargo.lock".to_string()],
+        ..Default::default()
+    };
+    let sver_repo = SverRepository::new(&calc_target_path(&repo, "service1"))
+        .unwrap()
+        .with_options(options.clone());
+
+    // exercise
+    let sources = sver_repo.list_sources().unwrap();
+    let version = sver_repo.calc_version().unwrap();
+    add_blob(&repo, "lib1/Cargo.lock", "lock1 updated".as_bytes());
+    let updated_lock_version = SverRepository::new(&calc_target_path(&repo, "service1"))
+        .unwrap()
+        .with_options(options)
+        .calc_version()
+        .unwrap();
+
+    // verify
+    assert_eq!(
+        sources,
+        vec![
+            "lib1/lib.rs",
+            "lib2/lib.rs",
+            "service1/main.rs",
+            "service1/sver.toml"
+        ]
+    );
+    assert_eq!(version.version, updated_lock_version.version);
+}
+
+// repo layout
+// .
+// + .gitignore → "/build/"
+// + service1/.gitignore → "*.tmp"
+// + service1/hello.txt
+// + service1/tracked.tmp (tracked even though ignored)
+// + service1/cache.tmp (untracked, ignored by service1/.gitignore)
+// + service1/build/out.o (untracked, not ignored. "/build/" matches only the root)
+// + service1/target/out.o (untracked, ignored by .git/info/exclude)
+// + build/out.o (untracked, ignored by .gitignore)
+#[test]
+fn include_untracked_files_respects_ignore_rules() {
+    initialize();
+
+    // setup
+    let repo = setup_test_repository();
+    add_blob(&repo, ".gitignore", "/build/".as_bytes());
+    add_blob(&repo, "service1/.gitignore", "*.tmp".as_bytes());
+    add_blob(&repo, "service1/hello.txt", "hello".as_bytes());
+    add_blob(&repo, "service1/tracked.tmp", "tracked".as_bytes());
+    commit(&repo, "setup");
+    let workdir = repo.workdir().unwrap();
+    for (path, content) in [
+        (".gitignore", "/build/"),
+        ("service1/.gitignore", "*.tmp"),
+        ("service1/hello.txt", "hello"),
+        ("service1/tracked.tmp", "tracked"),
+        ("service1/cache.tmp", "cache"),
+        ("service1/build/out.o", "out"),
+        ("service1/target/out.o", "out"),
+        ("build/out.o", "out"),
+    ] {
+        let path = workdir.join(path);
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(path, content).unwrap();
+    }
+    std::fs::write(repo.path().join("info/exclude"), "target/\n").unwrap();
+
+    // exercise
+    let sources = SverRepository::new(&calc_target_path(&repo, ""))
+        .unwrap()
+        .with_options(CalcOptions {
+            include_untracked: true,
+            ..Default::default()
+        })
+        .list_sources()
+        .unwrap();
+
+    // verify
+    assert_eq!(
+        sources,
+        vec![
+            ".gitignore",
+            "service1/.gitignore",
+            "service1/build/out.o",
+            "service1/hello.txt",
+            "service1/tracked.tmp",
+        ]
+    );
+}
+
+// repo layout
+// .
+// + hello.txt
+// + service1/world.txt
+#[test]
+fn source_digests_reproduce_version() {
+    initialize();
+
+    // setup
+    let repo = setup_test_repository();
+    add_blob(&repo, "hello.txt", "hello world!".as_bytes());
+    add_blob(&repo, "service1/world.txt", "good morning!".as_bytes());
+    commit(&repo, "setup");
+
+    let sver_repo = SverRepository::new(&calc_target_path(&repo, "")).unwrap();
+
+    // exercise
+    let digests = sver_repo.source_digests().unwrap();
+
+    // verify
+    // the bytes folded for each file: path, file mode (little endian) and oid
+    let index = repo.index().unwrap();
+    let folded = digests
+        .iter()
+        .map(|SourceDigest { path, .. }| {
+            let entry = index.get_path(Path::new(path), 0).unwrap();
+            [
+                path.as_bytes(),
+                &entry.mode.to_le_bytes(),
+                entry.id.as_bytes(),
+            ]
+            .concat()
+        })
+        .collect::<Vec<_>>();
+    for (SourceDigest { path, digest, .. }, folded) in digests.iter().zip(&folded) {
+        assert_eq!(
+            digest,
+            &format!("{:#x}", Sha256::digest(folded)),
+            "path:{path}"
+        );
+    }
+    let mut hasher = Sha256::default();
+    hasher.update("".as_bytes());
+    folded.iter().for_each(|f| hasher.update(f));
+    assert_eq!(
+        format!("{:#x}", hasher.finalize()),
+        "d601cac0967b58cd86a3a0384709f81ada1db3a42060e4458b843a7c7613b6ea"
+    );
+    assert_eq!(
+        digests
+            .iter()
+            .map(|digest| &digest.path)
+            .collect::<Vec<_>>(),
+        vec!["hello.txt", "service1/world.txt"]
+    );
+}
+
+// repo layout
+// .
+// + hello.txt (executable)
+// + service1/world.txt
+#[test]
+fn list_json_simple_repository() {
+    initialize();
+
+    // setup
+    let repo = setup_test_repository();
+    add_blob_executable(&repo, "hello.txt", "hello world!".as_bytes());
+    add_blob(&repo, "service1/world.txt", "good morning!".as_bytes());
+    commit(&repo, "setup");
+    let path = calc_target_path(&repo, "");
+    let list = |args: &[&str]| {
+        let output = std::process::Command::new(env!("CARGO_BIN_EXE_sver"))
+            .arg("list")
+            .arg(&path)
+            .args(args)
+            .output()
+            .unwrap();
+        assert!(output.status.success());
+        serde_json::from_slice::<serde_json::Value>(&output.stdout).unwrap()
+    };
+
+    // exercise
+    let sources = list(&["--output", "json"]);
+    let digests = list(&["--output", "json", "--digests"]);
+
+    // verify
+    assert_eq!(
+        sources,
+        serde_json::json!(["hello.txt", "service1/world.txt"])
+    );
+    let expected = SverRepository::new(&path)
+        .unwrap()
+        .source_digests()
+        .unwrap();
+    assert_eq!(
+        digests,
+        serde_json::json!([
+            { "path": "hello.txt", "mode": "100755", "digest": expected[0].digest },
+            { "path": "service1/world.txt", "mode": "100644", "digest": expected[1].digest },
+        ])
+    );
+}
+
+// repo layout
+// .
+// + hello.txt
+#[test]
+fn calc_custom_length_without_targets() {
+    initialize();
+
+    // setup
+    let repo = setup_test_repository();
+    add_blob(&repo, "hello.txt", "hello world!".as_bytes());
+    commit(&repo, "setup");
+
+    // exercise
+    let output = std::process::Command::new(env!("CARGO_BIN_EXE_sver"))
+        .args(["calc", "-l", "8", "-"])
+        .current_dir(repo.workdir().unwrap())
+        .stdin(std::process::Stdio::null())
+        .output()
+        .unwrap();
+
+    // verify
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    assert!(!String::from_utf8_lossy(&output.stderr).contains("collide"));
+}
+
+// repo layout
+// .
+// + hello.txt
+#[test]
+fn doctor_with_repo_option() {
+    initialize();
+
+    // setup
+    let repo = setup_test_repository();
+    add_blob(&repo, "hello.txt", "hello world!".as_bytes());
+    commit(&repo, "setup");
+
+    // exercise
+    // run outside of the repository, so it is found only by `--repo`
+    let output = std::process::Command::new(env!("CARGO_BIN_EXE_sver"))
+        .arg("--repo")
+        .arg(repo.workdir().unwrap())
+        .arg("doctor")
+        .current_dir("/")
+        .output()
+        .unwrap();
+
+    // verify
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+}
+
+// repo layout
+// .
+// + service1/hello.txt
+// + service1/world.txt
+// + service1/sver.toml → [default], [prof1] excludes = [ "world.txt" ]
+#[test]
+fn build_repository_with_builder() {
+    initialize();
+
+    // setup
+    let repo = setup_test_repository();
+    add_blob(&repo, "service1/hello.txt", "hello".as_bytes());
+    add_blob(&repo, "service1/world.txt", "world".as_bytes());
+    add_blob(
+        &repo,
+        "service1/sver.toml",
+        "
+        [default]
+
+        [prof1]
+        excludes = [\"world.txt\"]"
+            .as_bytes(),
+    );
+    commit(&repo, "setup");
+    let workdir = repo.workdir().unwrap();
+    std::fs::create_dir_all(workdir.join("service1")).unwrap();
+    std::fs::write(workdir.join("service1/new.txt"), "new").unwrap();
+
+    // exercise
+    let built = SverRepository::builder()
+        .path(calc_target_path(&repo, "service1"))
+        .profile("prof1")
+        .build()
+        .unwrap();
+    let parsed =
+        SverRepository::new(&calc_target_path_with_profile(&repo, "service1", "prof1")).unwrap();
+    let built_default = SverRepository::builder()
+        .path(calc_target_path(&repo, "service1"))
+        .build()
+        .unwrap();
+    let parsed_default = SverRepository::new(&calc_target_path(&repo, "service1")).unwrap();
+    let built_untracked = SverRepository::builder()
+        .path(calc_target_path(&repo, "service1"))
+        .include_untracked(true)
+        .build()
+        .unwrap();
+
+    // verify
+    assert_eq!(
+        built.calc_version().unwrap().version,
+        parsed.calc_version().unwrap().version
+    );
+    assert_eq!(
+        built.list_sources().unwrap(),
+        parsed.list_sources().unwrap()
+    );
+    assert_eq!(
+        built_default.calc_version().unwrap().version,
+        parsed_default.calc_version().unwrap().version
+    );
+    assert_ne!(
+        built.calc_version().unwrap().version,
+        built_default.calc_version().unwrap().version
+    );
+    assert_eq!(
+        built_untracked.list_sources().unwrap(),
+        vec![
+            "service1/hello.txt",
+            "service1/new.txt",
+            "service1/sver.toml",
+            "service1/world.txt"
+        ]
+    );
+}
+
+// repo layout
+// .
+// + service1/hello.txt
+// + service2/hello.txt
+// + sver-versions.toml → service1 = (first 8 digits of the version), service2 = "00000000"
+#[test]
+fn verify_version_with_attestation() {
+    initialize();
+
+    // setup
+    let repo = setup_test_repository();
+    add_blob(&repo, "service1/hello.txt", "hello".as_bytes());
+    add_blob(&repo, "service2/hello.txt", "hello".as_bytes());
+    commit(&repo, "setup");
+    let service1 = SverRepository::new(&calc_target_path(&repo, "service1")).unwrap();
+    let service1_version = service1.calc_version().unwrap().version;
+    add_blob(
+        &repo,
+        "sver-versions.toml",
+        format!(
+            "
+            [versions]
+            \"service1\" = \"{}\"
+            \"service2\" = \"00000000\"",
+            &service1_version[..8]
+        )
+        .as_bytes(),
+    );
+    commit(&repo, "add attestation");
+    let service2 = SverRepository::new(&calc_target_path(&repo, "service2")).unwrap();
+    let service1_prof1 =
+        SverRepository::new(&calc_target_path_with_profile(&repo, "service1", "prof1")).unwrap();
+
+    // exercise
+    let explicit_pass = service1.verify_version(Some(&service1_version)).unwrap();
+    let explicit_fail = service1.verify_version(Some("00000000")).unwrap();
+    let too_short_expected = service1_version[..1].to_string();
+    let too_short = service1.verify_version(Some(&too_short_expected)).unwrap();
+    let attested_pass = service1.verify_version(None).unwrap();
+    let attested_fail = service2.verify_version(None).unwrap();
+    let missing = service1_prof1.verify_version(None).unwrap();
+
+    // verify
+    assert!(explicit_pass.is_verified());
+    assert_eq!(
+        explicit_fail,
+        Verification::Mismatched {
+            expected: "00000000".to_string(),
+            actual: service1_version.clone()
+        }
+    );
+    assert_eq!(
+        attested_pass,
+        Verification::Verified {
+            version: service1_version
+        }
+    );
+    assert!(matches!(attested_fail, Verification::Mismatched { .. }));
+    assert_eq!(missing, Verification::MissingAttestation);
+    // a single digit prefix matches 1 in 16 versions
+    assert_eq!(
+        too_short,
+        Verification::TooShort {
+            expected: too_short_expected
+        }
+    );
+    assert!(!too_short.is_verified());
+}
+
+// repo layout
+// .
+// + service1/hello.txt → changed by the second commit
+// + service2/world.txt → changed by the third commit
+#[test]
+fn versions_in_range_repository() {
+    initialize();
+
+    // setup
+    let repo = setup_test_repository();
+    add_blob(&repo, "service1/hello.txt", "hello".as_bytes());
+    add_blob(&repo, "service2/world.txt", "world".as_bytes());
+    commit(&repo, "first");
+    let first = repo.head().unwrap().target().unwrap().to_string();
+    add_blob(&repo, "service1/hello.txt", "good morning".as_bytes());
+    commit(&repo, "second");
+    let second = repo.head().unwrap().target().unwrap().to_string();
+    add_blob(&repo, "service2/world.txt", "good evening".as_bytes());
+    commit(&repo, "third");
+    let sver_repo = SverRepository::new(&calc_target_path(&repo, "service1")).unwrap();
+
+    // exercise
+    let in_range = sver_repo
+        .calc_versions_in_range(&format!("{first}..HEAD"))
+        .unwrap();
+    let history = sver_repo.calc_versions_in_range("HEAD").unwrap();
+
+    // verify
+    // the third commit does not change the version
+    assert_eq!(
+        in_range
+            .iter()
+            .map(|v| v.commit.as_str())
+            .collect::<Vec<_>>(),
+        vec![second.as_str()]
+    );
+    assert_eq!(
+        in_range[0].version,
+        sver_repo.calc_version_at(&second).unwrap()
+    );
+    assert_eq!(
+        history
+            .iter()
+            .map(|v| v.commit.as_str())
+            .collect::<Vec<_>>(),
+        vec![first.as_str(), second.as_str()]
+    );
+    assert_ne!(history[0].version, history[1].version);
+    assert!(sver_repo
+        .calc_versions_in_range("HEAD..HEAD")
+        .unwrap()
+        .is_empty());
+}
+
+// repo layout
+// .
+// + lib1/hello.txt → not checked out
+// + service1/world.txt → not checked out
+// + service1/sver.toml → not checked out, dependency = [ "lib1" ]
+// + service2/hello.txt
+#[test]
+fn sparse_checkout_repository() {
+    initialize();
+
+    // setup
+    let repo = setup_test_repository();
+    add_blob(&repo, "lib1/hello.txt", "hello".as_bytes());
+    add_blob(&repo, "service1/world.txt", "world".as_bytes());
+    add_blob(
+        &repo,
+        "service1/sver.toml",
+        "[default]\ndependencies = [\"lib1\"]\n".as_bytes(),
+    );
+    add_blob(&repo, "service2/hello.txt", "hello".as_bytes());
+    commit(&repo, "setup");
+    let expected = SverRepository::new(&calc_target_path(&repo, "service1"))
+        .unwrap()
+        .calc_version()
+        .unwrap();
+    repo.config()
+        .unwrap()
+        .set_bool("core.sparseCheckout", true)
+        .unwrap();
+    let work_dir = repo.workdir().unwrap();
+    std::fs::remove_dir_all(work_dir.join("lib1")).unwrap();
+    std::fs::remove_dir_all(work_dir.join("service1")).unwrap();
+
+    // exercise
+    let sver_repo = SverRepository::new(&calc_target_path(&repo, "service1")).unwrap();
+    let version = sver_repo.calc_version().unwrap();
+    let sources = sver_repo.list_sources().unwrap();
+    let missing = SverRepository::new(&calc_target_path(&repo, "service3"));
+
+    // verify
+    assert_eq!(version, expected);
+    assert_eq!(
+        sources,
+        vec!["lib1/hello.txt", "service1/sver.toml", "service1/world.txt"]
+    );
+    // neither in the working tree nor in the index
+    assert!(matches!(missing, Err(SverError::Io(_))));
+}
+
+// repo layout
+// .
+// + service1/hello.txt
+#[test]
+fn verify_repository() {
+    initialize();
+
+    // setup
+    let repo = setup_test_repository();
+    add_blob(&repo, "service1/hello.txt", "hello".as_bytes());
+    commit(&repo, "setup");
+    let sver_repo = SverRepository::new(&calc_target_path(&repo, "service1")).unwrap();
+    let version = sver_repo.calc_version().unwrap().version;
+
+    // exercise & verify
+    assert!(sver_repo.verify(&version).unwrap());
+    assert!(sver_repo.verify(&version[..12]).unwrap());
+    assert!(!sver_repo.verify("000000000000").unwrap());
+    assert!(!sver_repo.verify("").unwrap());
+
+    // exercise
+    add_blob(&repo, "service1/hello.txt", "good morning".as_bytes());
+    commit(&repo, "edit");
+
+    // verify
+    assert!(!sver_repo.verify(&version).unwrap());
+}
+
+// repo layout
+// .
+// + hello.txt
+// + service1/world.txt
+// + service1/sver.toml → [prof1] excludes = [ "world.txt" ]
+#[test]
+fn from_repository_handle() {
+    initialize();
+
+    // setup
+    let repo = setup_test_repository();
+    add_blob(&repo, "hello.txt", "hello world!".as_bytes());
+    add_blob(&repo, "service1/world.txt", "good morning!".as_bytes());
+    add_blob(
+        &repo,
+        "service1/sver.toml",
+        "
+        [default]
+
+        [prof1]
+        excludes = [\"world.txt\"]"
+            .as_bytes(),
+    );
+    commit(&repo, "setup");
+
+    // exercise
+    let handle = Repository::open(repo.workdir().unwrap()).unwrap();
+    let from_handle = SverRepository::from_repository(
+        handle,
+        CalculationTarget::new("service1".to_string(), "prof1".to_string()),
+    )
+    .unwrap();
+    let from_path =
+        SverRepository::new(&calc_target_path_with_profile(&repo, "service1", "prof1")).unwrap();
+    let root_from_handle = SverRepository::from_repository(
+        Repository::open(repo.workdir().unwrap()).unwrap(),
+        CalculationTarget::new("".to_string(), "default".to_string()),
+    )
+    .unwrap();
+
+    // verify
+    assert_eq!(
+        from_handle.calc_version().unwrap().version,
+        from_path.calc_version().unwrap().version
+    );
+    assert_eq!(
+        from_handle.list_sources().unwrap(),
+        vec!["service1/sver.toml"]
+    );
+    assert_eq!(
+        root_from_handle.calc_version().unwrap().version,
+        SverRepository::new(&calc_target_path(&repo, ""))
+            .unwrap()
+            .calc_version()
+            .unwrap()
+            .version
+    );
+}
+
+// repo layout
+// .
+// + service1/hello.txt
+// + service1/world.txt
+// + service1/sver.toml → ["v1.2"] excludes = [ "world.txt" ]
+#[test]
+fn profile_with_dot() {
+    initialize();
+
+    // setup
+    let repo = setup_test_repository();
+    add_blob(&repo, "service1/hello.txt", "hello".as_bytes());
+    add_blob(&repo, "service1/world.txt", "world".as_bytes());
+    add_blob(
+        &repo,
+        "service1/sver.toml",
+        "
+        [default]
+
+        [\"v1.2\"]
+        excludes = [\"world.txt\"]"
+            .as_bytes(),
+    );
+    commit(&repo, "setup");
+
+    // exercise
+    let sver_repo =
+        SverRepository::new(&calc_target_path_with_profile(&repo, "service1", "v1.2")).unwrap();
+
+    // verify
+    assert_eq!(
+        sver_repo.list_sources().unwrap(),
+        vec!["service1/hello.txt", "service1/sver.toml"]
+    );
+}
+
+// repo layout
+// .
+// + shared/lib/lib.rs
+// + other/secret.txt
+// + service1/main.rs
+// + service1/shared_lib → ../shared/lib
+// + service1/other → ../other
+// + service1/sver.toml → allow_symlink_targets = [ "shared/**" ]
+#[test]
+fn allow_symlink_targets() {
+    initialize();
+
+    // setup
+    let repo = setup_test_repository();
+    add_blob(&repo, "shared/lib/lib.rs", "lib".as_bytes());
+    add_blob(&repo, "other/secret.txt", "secret".as_bytes());
+    add_blob(&repo, "service1/main.rs", "main".as_bytes());
+    add_symlink(&repo, "service1/shared_lib", "../shared/lib");
+    add_symlink(&repo, "service1/other", "../other");
+    add_blob(
+        &repo,
+        "service1/sver.toml",
+        "
+        [default]
+        allow_symlink_targets = [\"shared/**\"]
+
+        [all]"
+            .as_bytes(),
+    );
+    commit(&repo, "setup");
+
+    // exercise
+    let allowed = SverRepository::new(&calc_target_path(&repo, "service1")).unwrap();
+    let all =
+        SverRepository::new(&calc_target_path_with_profile(&repo, "service1", "all")).unwrap();
+
+    // verify
+    assert_eq!(
+        allowed.list_sources().unwrap(),
+        vec![
+            "service1/main.rs",
+            "service1/other",
+            "service1/shared_lib",
+            "service1/sver.toml",
+            "shared/lib/lib.rs",
+        ]
+    );
+    assert_eq!(
+        all.list_sources().unwrap(),
+        vec![
+            "other/secret.txt",
+            "service1/main.rs",
+            "service1/other",
+            "service1/shared_lib",
+            "service1/sver.toml",
+            "shared/lib/lib.rs",
+        ]
+    );
+}
+
+// repo layout
+// .
+// + lib1/lib.rs
+// + service1/main.rs
+// + service1/sver.toml → dependency = [ "lib1" ]
+#[test]
+fn calc_report_is_consistent() {
+    initialize();
+
+    // setup
+    let repo = setup_test_repository();
+    add_blob(&repo, "lib1/lib.rs", "lib".as_bytes());
+    add_blob(&repo, "service1/main.rs", "fn main() {}".as_bytes());
+    let config = "
+        [default]
+        dependencies = [\"lib1\"]";
+    add_blob(&repo, "service1/sver.toml", config.as_bytes());
+    commit(&repo, "setup");
+
+    let sver_repo = SverRepository::new(&calc_target_path(&repo, "service1")).unwrap();
+
+    // exercise
+    let report = sver_repo.calc_report().unwrap();
+
+    // verify
+    assert_eq!(
+        report.version.version,
+        sver_repo.calc_version().unwrap().version
+    );
+    assert_eq!(report.version.path, "service1");
+    assert_eq!(report.sources, sver_repo.list_sources().unwrap());
+    assert_eq!(report.source_count, 3);
+    assert_eq!(
+        report.total_bytes,
+        ("lib".len() + "fn main() {}".len() + config.len()) as u64
+    );
+    assert_eq!(report.config_files, vec!["service1/sver.toml"]);
+}
+
+// repo layout
+// .
+// + service1/sver.toml → dependency = [ "service2" ]
+// + service2/sver.toml → dependency = [ "service1" ]
+// + service3/sver.toml → dependency = [ "service1" ]
+#[test]
+fn validate_cyclic_repository() {
+    initialize();
+
+    // setup
+    let repo = setup_test_repository();
+    add_blob(
+        &repo,
+        "service1/sver.toml",
+        "
+        [default]
+        dependencies = [
+            \"service2\",
+        ]"
+        .as_bytes(),
+    );
+    add_blob(
+        &repo,
+        "service2/sver.toml",
+        "
+        [default]
+        dependencies = [
+            \"service1\",
+        ]"
+        .as_bytes(),
+    );
+    add_blob(
+        &repo,
+        "service3/sver.toml",
+        "
+        [default]
+        dependencies = [
+            \"service1\",
+        ]"
+        .as_bytes(),
+    );
+    commit(&repo, "setup");
+
+    // exercise
+    let validation_results = SverRepository::new(&calc_target_path(&repo, ""))
+        .unwrap()
+        .validate_sver_config()
+        .unwrap();
+
+    // verify
+    // cycles are warnings, so the validation fails only with fail_on_warning
+    assert!(!validation_results.has_invalid);
+    assert!(validation_results.has_warning);
+    assert!(!validation_results.is_failure(false));
+    assert!(validation_results.is_failure(true));
+    let mut results = validation_results.results;
+    assert_eq!(results.len(), 3);
+    // service3 depends on the cycle but is not a part of it
+    assert!(matches!(
+        results.pop(),
+        Some(ValidationResult::Valid { .. })
+    ));
+    if let Some(ValidationResult::Warning {
+        calcuration_target: CalculationTarget { path, .. },
+        cyclic_dependencies,
+        ..
+    }) = results.pop()
+    {
+        assert_eq!(path, "service2");
+        assert_eq!(
+            cyclic_dependencies,
+            vec!["service2:default", "service1:default", "service2:default"]
+        );
+    } else {
+        unreachable!("this line will not be execute");
+    }
+    if let Some(ValidationResult::Warning {
+        calcuration_target: CalculationTarget { path, .. },
+        cyclic_dependencies,
+        ..
+    }) = results.pop()
+    {
+        assert_eq!(path, "service1");
+        assert_eq!(
+            cyclic_dependencies,
+            vec!["service1:default", "service2:default", "service1:default"]
+        );
+    } else {
+        unreachable!("this line will not be execute");
+    }
+}
+
+// repo layout
+// .
+// + hello.txt
+// + sver.toml → [prof1] only
+#[test]
+fn lenient_default_profile_repository() {
+    initialize();
+
+    // setup
+    let repo = setup_test_repository();
+    add_blob(&repo, "hello.txt", "hello".as_bytes());
+    add_blob(
+        &repo,
+        "sver.toml",
+        "[prof1]
+        excludes = [\"hello.txt\"]"
+            .as_bytes(),
+    );
+    commit(&repo, "setup");
+
+    // exercise
+    let strict = SverRepository::new(&calc_target_path(&repo, ""))
+        .and_then(|sver_repo| sver_repo.calc_version());
+    let lenient_repo = SverRepository::new(&calc_target_path(&repo, ""))
+        .unwrap()
+        .with_options(CalcOptions {
+            lenient: true,
+            ..Default::default()
+        });
+    let sources = lenient_repo.list_sources().unwrap();
+    let version = lenient_repo.calc_version().unwrap();
+
+    // verify
+    match strict {
+        Err(SverError::ProfileNotFound(profile)) => assert_eq!(profile, "default"),
+        other => unreachable!("unexpected result. {:?}", other.map(|v| v.version)),
+    }
+    assert_eq!(sources, vec!["hello.txt", "sver.toml"]);
+    assert_eq!(version.version.len(), 64);
+
+    // other missing profiles are still errors
+    let result = SverRepository::new(&calc_target_path_with_profile(&repo, "", "missing"))
+        .unwrap()
+        .with_options(CalcOptions {
+            lenient: true,
+            ..Default::default()
+        })
+        .calc_version();
+    assert!(matches!(result, Err(SverError::ProfileNotFound(_))));
+}
+
+// repo layout
+// .
+// + lib0/sver.toml → dependency = [ "lib1" ]
+// + lib0/hello.txt
+// + lib1/sver.toml → dependency = [ "lib2" ]
+// + lib1/hello.txt
+// ...
+// + lib49/hello.txt
+#[test]
+fn deep_nested_dependencies_repository() {
+    initialize();
+
+    // setup
+    let depth = 50;
+    let repo = setup_test_repository();
+    for i in 0..depth {
+        add_blob(
+            &repo,
+            &format!("lib{i}/hello.txt"),
+            format!("hello{i}").as_bytes(),
+        );
+        if i + 1 < depth {
+            add_blob(
+                &repo,
+                &format!("lib{i}/sver.toml"),
+                format!("[default]\ndependencies = [\"lib{}\"]", i + 1).as_bytes(),
+            );
+        }
+    }
+    commit(&repo, "setup");
+
+    let sver_repo = SverRepository::new(&calc_target_path(&repo, "lib0")).unwrap();
+
+    // exercise
+    let sources = sver_repo.list_sources().unwrap();
+    let version = sver_repo.calc_version().unwrap();
+
+    // verify
+    let mut expected = (0..depth)
+        .flat_map(|i| {
+            let mut files = vec![format!("lib{i}/hello.txt")];
+            if i + 1 < depth {
+                files.push(format!("lib{i}/sver.toml"));
+            }
+            files
+        })
+        .collect::<Vec<_>>();
+    expected.sort();
+    assert_eq!(sources, expected);
+    assert_eq!(
+        version.version,
+        "156b1e18fc5abe47ddca87e2823c8039b5035294417556b99b0ad72097d76dec"
+    );
+}
+
+// repo layout
+// .
+// + large.bin (4MiB)
+// + hello.txt
+#[test]
+fn hash_content_repository() {
+    initialize();
+
+    // setup
+    let repo = setup_test_repository();
+    let large = (0..4 * 1024 * 1024)
+        .map(|i: u32| (i % 251) as u8)
+        .collect::<Vec<_>>();
+    add_blob(&repo, "large.bin", &large);
+    add_blob(&repo, "hello.txt", "hello".as_bytes());
+    commit(&repo, "setup");
+
+    let calc = |hash_content| {
+        SverRepository::new(&calc_target_path(&repo, ""))
+            .unwrap()
+            .with_options(CalcOptions {
+                hash_content,
+                ..Default::default()
+            })
+            .calc_version()
+            .unwrap()
+            .version
+    };
+
+    // exercise
+    let oid_version = calc(false);
+    let content_version = calc(true);
+
+    // verify
+    // SHA-256 of the path, file mode and content of each file
+    assert_eq!(
+        content_version,
+        "ec38c3a942e8a6f5f0e0719ed4fac642cd2d217dd04e2680129c6b7f9b8b2ebc"
+    );
+    assert_eq!(content_version, calc(true));
+    assert_ne!(content_version, oid_version);
+}
+
+// repo layout
+// .
+// + large.bin (4MiB)
+// + symlink → missing.txt (dangling)
+#[test]
+fn hash_content_untracked_repository() {
+    initialize();
+
+    // setup
+    let large = (0..4 * 1024 * 1024)
+        .map(|i: u32| (i % 251) as u8)
+        .collect::<Vec<_>>();
+    let tracked_repo = setup_test_repository();
+    add_blob(&tracked_repo, "large.bin", &large);
+    add_symlink(&tracked_repo, "symlink", "missing.txt");
+    commit(&tracked_repo, "setup");
+    let untracked_repo = setup_test_repository();
+    let workdir = untracked_repo.workdir().unwrap();
+    std::fs::write(workdir.join("large.bin"), &large).unwrap();
+    std::os::unix::fs::symlink("missing.txt", workdir.join("symlink")).unwrap();
+
+    let calc = |repo: &Repository| {
+        SverRepository::new(&calc_target_path(repo, ""))
+            .unwrap()
+            .with_options(CalcOptions {
+                hash_content: true,
+                include_untracked: true,
+                ..Default::default()
+            })
+            .calc_version()
+            .unwrap()
+            .version
+    };
+
+    // exercise
+    let tracked_version = calc(&tracked_repo);
+    let untracked_version = calc(&untracked_repo);
+
+    // verify
+    // files in the working tree are hashed as their blobs
+    assert_eq!(untracked_version, tracked_version);
+}
+
+// repo layout
+// .
+// + service1/sver.toml → broken at line 3
+// + service1/hello.txt
+// + service2/sver.toml → dependency = [ "service1" ]
+#[test]
+fn validate_syntax_error_repository() {
+    initialize();
+
+    // setup
+    let repo = setup_test_repository();
+    add_blob(&repo, "service1/hello.txt", "hello".as_bytes());
+    add_blob(
+        &repo,
+        "service1/sver.toml",
+        "[default]\ndependencies = []\nexcludes = [\"hello.txt\"\n".as_bytes(),
+    );
+    add_blob(
+        &repo,
+        "service2/sver.toml",
+        "[default]\ndependencies = [\"service1\"]\n".as_bytes(),
+    );
+    commit(&repo, "setup");
+
+    // exercise
+    let ValidationResults {
+        has_invalid,
+        mut results,
+        ..
+    } = SverRepository::new(&calc_target_path(&repo, ""))
+        .unwrap()
+        .validate_sver_config()
+        .unwrap();
+    let calc_result = SverRepository::new(&calc_target_path(&repo, "service1"))
+        .and_then(|sver_repo| sver_repo.calc_version());
+
+    // verify
+    assert!(has_invalid);
+    assert_eq!(results.len(), 2);
+    if let Some(ValidationResult::Valid {
+        calcuration_target: CalculationTarget { path, profile },
+        ..
+    }) = results.pop()
+    {
+        assert_eq!(path, "service2");
+        assert_eq!(profile, "default");
+    } else {
+        unreachable!("this line will not be execute");
+    }
+    if let Some(ValidationResult::SyntaxError(error)) = results.pop() {
+        assert_eq!(error.config_file, "service1/sver.toml");
+        assert_eq!(error.line, 4);
+        assert_eq!(error.column, 1);
+    } else {
+        unreachable!("this line will not be execute");
+    }
+    match calc_result {
+        Err(SverError::InvalidConfig(message)) => {
+            assert!(message.contains("path:service1/sver.toml, line:4, column:1"));
+        }
+        other => unreachable!("unexpected result. {:?}", other.map(|v| v.version)),
+    }
+}
+
+// repo layout
+// .
+// + sub → submodule ../sub e40a885afd013606e105c027a5c31910137e5566
+#[test]
+fn has_submodule_recurse() {
+    initialize();
+
+    // setup
+    let mut tmp_dir = temp_dir();
+    let uuid = Uuid::now_v7();
+    tmp_dir.push(format!("sver-{}", uuid));
+    create_dir(tmp_dir.clone()).unwrap();
+
+    // setup external repo
+    let mut sub_repo_dir = tmp_dir.clone();
+    sub_repo_dir.push("sub");
+
+    let sub_repo = Repository::init(sub_repo_dir).unwrap();
+    add_blob(&sub_repo, "hello.txt", "hello".as_bytes());
+    commit_at(
+        &sub_repo,
+        "setup",
+        Utc.with_ymd_and_hms(2022, 10, 1, 10, 20, 30)
+            .earliest()
+            .unwrap(),
+    );
+
+    // setup sut repo
+    let mut sut_repo_dir = tmp_dir.clone();
+    sut_repo_dir.push("sut");
+
+    let mut repo = Repository::init(sut_repo_dir.clone()).unwrap();
+    add_submodule(
+        &mut repo,
+        "../sub",
+        "sub",
+        "e40a885afd013606e105c027a5c31910137e5566",
+    );
+    commit(&repo, "setup");
+
+    let calc = |submodule_mode| {
+        SverRepository::new(&calc_target_path(&repo, ""))
+            .unwrap()
+            .with_options(CalcOptions {
+                submodule_mode,
+                ..Default::default()
+            })
+            .calc_version()
+            .unwrap()
+            .version
+    };
+    let recurse = SubmoduleMode::Recurse { max_depth: 8 };
+
+    // exercise
+    let commit_version = calc(SubmoduleMode::Commit);
+    let recurse_version = calc(recurse);
+
+    // verify
+    assert_eq!(
+        commit_version,
+        "975af38bee93750b69eed48da18f3041058bacd90e215fb61f920c1e9cb710b7"
+    );
+    assert_ne!(recurse_version, commit_version);
+    // no recursion is the same as the pinned commit
+    assert_eq!(
+        calc(SubmoduleMode::Recurse { max_depth: 0 }),
+        commit_version
+    );
+
+    // change the submodule contents without bumping the pin
+    let checked_out_sub_repo = Repository::open(sut_repo_dir.join("sub")).unwrap();
+    add_blob(&checked_out_sub_repo, "world.txt", "world".as_bytes());
+    commit(&checked_out_sub_repo, "add world");
+
+    assert_eq!(calc(SubmoduleMode::Commit), commit_version);
+    assert_ne!(calc(recurse), recurse_version);
+}
+
+// repo layout
+// .
+// + dir1
+//   + hello.txt
+//   + symlink → ../dir2
+// + dir2
+//   + world.txt
+//   + symlink → ../dir1
+#[test]
+fn has_symlink_cycle() {
+    initialize();
+
+    // setup
+    let repo = setup_test_repository();
+    add_blob(&repo, "dir1/hello.txt", "hello".as_bytes());
+    add_blob(&repo, "dir2/world.txt", "world".as_bytes());
+    add_symlink(&repo, "dir1/symlink", "../dir2");
+    add_symlink(&repo, "dir2/symlink", "../dir1");
+    commit(&repo, "setup");
+
+    // exercise
+    let dir1_sources = SverRepository::new(&calc_target_path(&repo, "dir1"))
+        .unwrap()
+        .list_sources()
+        .unwrap();
+    let dir2_sources = SverRepository::new(&calc_target_path(&repo, "dir2"))
+        .unwrap()
+        .list_sources()
+        .unwrap();
+
+    // verify
+    let expected = vec![
+        "dir1/hello.txt",
+        "dir1/symlink",
+        "dir2/symlink",
+        "dir2/world.txt",
+    ];
+    assert_eq!(dir1_sources, expected);
+    assert_eq!(dir2_sources, expected);
+    // each link is followed once, so the cycle does not reach the depth limit
+    let provenance = SverRepository::new(&calc_target_path(&repo, "dir1"))
+        .unwrap()
+        .source_provenance()
+        .unwrap();
+    assert_eq!(provenance[3].target.path, "dir2");
+    assert_eq!(provenance[3].reason, InclusionReason::Symlink);
+}
+
+// repo layout
+// .
+// + dir{0..N}
+//   + hello.txt
+//   + symlink → ../dir{n+1} (except the last directory)
+#[test]
+fn symlink_depth_limit() {
+    initialize();
+
+    let setup = |links: usize| {
+        let repo = setup_test_repository();
+        for n in 0..=links {
+            add_blob(&repo, &format!("dir{n}/hello.txt"), "hello".as_bytes());
+            if n < links {
+                add_symlink(
+                    &repo,
+                    &format!("dir{n}/symlink"),
+                    &format!("../dir{}", n + 1),
+                );
+            }
+        }
+        commit(&repo, "setup");
+        repo
+    };
+
+    // exercise
+    let repo = setup(40);
+    let sources = SverRepository::new(&calc_target_path(&repo, "dir0"))
+        .unwrap()
+        .list_sources()
+        .unwrap();
+    let repo = setup(41);
+    let result = SverRepository::new(&calc_target_path(&repo, "dir0"))
+        .unwrap()
+        .list_sources();
+
+    // verify
+    // 40 links are followed
+    assert_eq!(sources.len(), 41 + 40);
+    assert!(sources.contains(&"dir40/hello.txt".to_string()));
+    match result {
+        Err(SverError::TooManyLinks(path)) => assert_eq!(path, "dir40/symlink"),
+        _ => unreachable!("this line will not be execute"),
+    }
+}
+
+// repo layout
+// .
+// + hello.txt
+// + linkdir
+//   + absolute → /etc
+//   + escaped → ../../hello.txt
+//   + README.txt
+#[test]
+fn has_symlink_outside_of_repository() {
+    initialize();
+
+    // setup
+    let repo = setup_test_repository();
+    add_blob(&repo, "hello.txt", "hello".as_bytes());
+    add_blob(&repo, "linkdir/README.txt", "hello.world".as_bytes());
+    add_symlink(&repo, "linkdir/absolute", "/etc");
+    add_symlink(&repo, "linkdir/escaped", "../../hello.txt");
+    commit(&repo, "setup");
+
+    let sver_repo = SverRepository::new(&calc_target_path(&repo, "linkdir")).unwrap();
+
+    // exercise
+    let sources = sver_repo.list_sources().unwrap();
+
+    // verify
+    // links are included as is, but their targets are not followed
+    // (`../../hello.txt` must not be resolved as `hello.txt` of the repository root)
+    assert_eq!(
+        sources,
+        vec!["linkdir/README.txt", "linkdir/absolute", "linkdir/escaped"]
+    );
+}
+
+// repo layout
+// .
+// + service1/hello.txt
+// + service2/sver.toml → dependency = [ "service1/hello-hello.txt" ]
+#[test]
+fn validate_results_json() {
+    initialize();
+
+    // setup
+    let repo = setup_test_repository();
+    add_blob(&repo, "service1/hello.txt", "hello world!".as_bytes());
+    add_blob(
+        &repo,
+        "service2/sver.toml",
+        "
+        [default]
+        dependencies = [
+            \"service1/hello-hello.txt\",
+        ]"
+        .as_bytes(),
+    );
+    commit(&repo, "setup");
+
+    let sver_repo = SverRepository::new(&calc_target_path(&repo, "service2")).unwrap();
+
+    // exercise
+    let json = serde_json::to_string(&sver_repo.validate_sver_config().unwrap()).unwrap();
+
+    // verify
+    let json = serde_json::from_str::<serde_json::Value>(&json).unwrap();
+    assert_eq!(json["has_invalid"], true);
+    assert_eq!(
+        json["results"],
+        serde_json::json!([{
+            "result": "invalid",
+            "calcuration_target": { "path": "service2", "profile": "default" },
+            "config_file_name": "sver.toml",
+            "invalid_excludes": [],
+            "invalid_dependencies": ["service1/hello-hello.txt"],
+        }])
+    );
+}
+
+// repo layout
+// .
+// + hello.txt
+// + lib1/lib.rs
+// + lib2/nested/lib.rs
+// + service1/main.rs
+#[test]
+fn init_with_scan() {
+    initialize();
+
+    // setup
+    let repo = setup_test_repository();
+    add_blob(&repo, "hello.txt", "hello".as_bytes());
+    add_blob(&repo, "lib1/lib.rs", "lib1".as_bytes());
+    add_blob(&repo, "lib2/nested/lib.rs", "lib2".as_bytes());
+    add_blob(&repo, "service1/main.rs", "main".as_bytes());
+    commit(&repo, "setup");
+
+    let sver_repo = SverRepository::new(&calc_target_path(&repo, "service1")).unwrap();
+
+    // exercise
+    let message = sver_repo
+        .init_sver_config_with(&InitOptions {
+            scan: true,
+            ..Default::default()
+        })
+        .unwrap();
+
+    // verify
+    assert_eq!(message, "sver.toml is generated. path:service1");
+    let content =
+        std::fs::read_to_string(Path::new(repo.workdir().unwrap()).join("service1/sver.toml"))
+            .unwrap();
+    assert!(content.contains("# Files or directories this directory depends on."));
+    assert!(content.contains("# Files or directories excluded from this directory."));
+    assert!(content.contains("  # \"lib1\",\n  # \"lib2\",\n]"));
+    // suggestions are commented out
+    let config = toml::from_str::<toml::Table>(&content).unwrap();
+    assert_eq!(
+        config["default"]["dependencies"],
+        toml::Value::Array(vec![])
+    );
+}
+
+// repo layout
+// .
+// + service1/main.rs
+#[test]
+fn init_with_profiles() {
+    initialize();
+
+    // setup
+    let repo = setup_test_repository();
+    add_blob(&repo, "service1/main.rs", "main".as_bytes());
+    commit(&repo, "setup");
+
+    let sver_repo = SverRepository::new(&calc_target_path(&repo, "service1")).unwrap();
+    let init = |profiles: &[&str]| {
+        sver_repo.init_sver_config_with(&InitOptions {
+            profiles: profiles.iter().map(|p| p.to_string()).collect(),
+            ..Default::default()
+        })
+    };
+
+    // exercise
+    let duplicated = init(&["default", "ci", "default"]);
+    let invalid = init(&["default", "with space"]);
+    let message = init(&["default", "ci", "release"]).unwrap();
+
+    // verify
+    assert!(matches!(duplicated, Err(SverError::InvalidConfig(_))));
+    assert!(matches!(invalid, Err(SverError::InvalidConfig(_))));
+    assert_eq!(message, "sver.toml is generated. path:service1");
+    let content =
+        std::fs::read_to_string(Path::new(repo.workdir().unwrap()).join("service1/sver.toml"))
+            .unwrap();
+    let sections = content
+        .lines()
+        .filter(|line| line.starts_with('['))
+        .collect::<Vec<_>>();
+    assert_eq!(sections, vec!["[default]", "[ci]", "[release]"]);
+}
+
+// repo layout
+// .
+// + inner
+//   + .git (nested repository)
+//   + hello.txt
+#[test]
+fn builder_with_repository_root() {
+    initialize();
+
+    // setup
+    let repo = setup_test_repository();
+    add_blob(&repo, "inner/hello.txt", "hello".as_bytes());
+    commit(&repo, "setup");
+    let inner_repo = Repository::init(repo.workdir().unwrap().join("inner")).unwrap();
+    add_blob(&inner_repo, "world.txt", "world".as_bytes());
+    commit(&inner_repo, "setup");
+    let root = repo.workdir().unwrap().to_str().unwrap();
+
+    // exercise
+    let searched = SverRepository::builder()
+        .path(calc_target_path(&repo, "inner"))
+        .build()
+        .unwrap();
+    let opened = SverRepository::builder()
+        .repository(root)
+        .path("./inner/")
+        .build()
+        .unwrap();
+    let escaped = SverRepository::builder()
+        .repository(root)
+        .path("../inner")
+        .build();
+
+    // verify
+    // the ancestor search finds the nested repository
+    assert_eq!(searched.list_sources().unwrap(), vec!["world.txt"]);
+    assert_eq!(opened.list_sources().unwrap(), vec!["inner/hello.txt"]);
+    assert_eq!(opened.calc_version().unwrap().path, "inner");
+    match escaped {
+        Err(SverError::OutsideRepository { path, repository }) => {
+            assert_eq!(path, "../inner");
+            assert_eq!(repository, root);
+        }
+        _ => unreachable!("this line will not be execute"),
+    }
+}
+
+// repo layout
+// .
+// + hello.txt
+// + sver.toml → [default] no setting, [prof1] no setting
+#[test]
+fn serialize_version_with_profile() {
+    initialize();
+
+    // setup
+    let repo = setup_test_repository();
+    add_blob(&repo, "hello.txt", "hello".as_bytes());
+    add_blob(&repo, "sver.toml", "[default]\n[prof1]\n".as_bytes());
+    commit(&repo, "setup");
+    let sver_repo =
+        SverRepository::new(&calc_target_path_with_profile(&repo, ".", "prof1")).unwrap();
+
+    // exercise
+    let version = sver_repo.calc_version().unwrap();
+    let json = serde_json::to_value(&version).unwrap();
+
+    // verify
+    assert_eq!(version.profile, "prof1");
+    assert_eq!(json["path"], "");
+    assert_eq!(json["profile"], "prof1");
+    assert_eq!(json["version"], version.version.as_str());
+    let deserialized: Version = serde_json::from_value(json).unwrap();
+    assert_eq!(deserialized, version);
+}
+
+// repo layout
+// .
+// + linkdir
+//   + symlink1 → original
+//   + symlink2 → original/
+//   + symlink3 → original/sub
+// + original
+//   + README.txt
+//   + sub
+//     + sver.toml → [default] excludes = [ "test.txt" ]
+//     + test.txt
+#[test]
+fn has_symlinks_to_same_dir() {
+    initialize();
+
+    // setup
+    let repo = setup_test_repository();
+    add_blob(&repo, "original/README.txt", "hello.world".as_bytes());
+    add_blob(
+        &repo,
+        "original/sub/sver.toml",
+        "[default]\nexcludes = [\"test.txt\"]".as_bytes(),
+    );
+    add_blob(&repo, "original/sub/test.txt", "test".as_bytes());
+    add_symlink(&repo, "linkdir/symlink1", "../original");
+    add_symlink(&repo, "linkdir/symlink2", "../original/");
+    add_symlink(&repo, "linkdir/symlink3", "../original/sub");
+    commit(&repo, "setup");
+
+    // exercise
+    // every run collects the targets into a new hash map, which iterates in a different order
+    let results = (0..10)
+        .map(|_| {
+            let sver_repo = SverRepository::new(&calc_target_path(&repo, "linkdir")).unwrap();
+            (
+                sver_repo.list_sources().unwrap(),
+                sver_repo.calc_version().unwrap().version,
+            )
+        })
+        .collect::<Vec<_>>();
+
+    // verify
+    let (sources, version) = &results[0];
+    assert_eq!(
+        sources,
+        &vec![
+            "linkdir/symlink1",
+            "linkdir/symlink2",
+            "linkdir/symlink3",
+            "original/README.txt",
+            "original/sub/sver.toml",
+            "original/sub/test.txt",
+        ]
+    );
+    assert_eq!(
+        version,
+        "36e34364840a7798be8cf3f282ed023d39afa3e538750fc72cd12a4f557d0bb5"
+    );
+    assert!(results.iter().all(|result| result == &results[0]));
+}
+
+// repo layout
+// .
+// + Makefile
+// + README.md
+// + build.rs
+// + readme.txt
+// + src/Main.rs
+// + src/lib.rs
+#[test]
+fn list_sources_sorted_repository() {
+    initialize();
+
+    // setup
+    let repo = setup_test_repository();
+    add_blob(&repo, "Makefile", "all:".as_bytes());
+    add_blob(&repo, "README.md", "# hello".as_bytes());
+    add_blob(&repo, "build.rs", "fn main() {}".as_bytes());
+    add_blob(&repo, "readme.txt", "hello".as_bytes());
+    add_blob(&repo, "src/Main.rs", "fn main() {}".as_bytes());
+    add_blob(&repo, "src/lib.rs", "".as_bytes());
+    commit(&repo, "setup");
+    let sver_repo = SverRepository::new(&calc_target_path(&repo, "")).unwrap();
+
+    // exercise
+    let bytes = sver_repo.list_sources_sorted(SortMode::Bytes).unwrap();
+    let case_insensitive = sver_repo
+        .list_sources_sorted(SortMode::CaseInsensitive)
+        .unwrap();
+
+    // verify
+    assert_eq!(bytes, sver_repo.list_sources().unwrap());
+    assert_eq!(
+        bytes,
+        vec![
+            "Makefile",
+            "README.md",
+            "build.rs",
+            "readme.txt",
+            "src/Main.rs",
+            "src/lib.rs"
+        ]
+    );
+    assert_eq!(
+        case_insensitive,
+        vec![
+            "build.rs",
+            "Makefile",
+            "README.md",
+            "readme.txt",
+            "src/lib.rs",
+            "src/Main.rs"
+        ]
+    );
+}
+
+// repo layout
+// .
+// + service1/hello.txt
+// + service1/sver.toml → [default] no setting
+// + service2/world.txt
+// + service2/sver.toml → [default] no setting, [prof1] dependency = [ "service1" ]
+#[test]
+fn aggregate_version_repository() {
+    initialize();
+
+    // setup
+    let repo = setup_test_repository();
+    add_blob(&repo, "service1/hello.txt", "hello".as_bytes());
+    add_blob(&repo, "service1/sver.toml", "[default]\n".as_bytes());
+    add_blob(&repo, "service2/world.txt", "world".as_bytes());
+    add_blob(
+        &repo,
+        "service2/sver.toml",
+        "[default]\n[prof1]\ndependencies = [\"service1\"]\n".as_bytes(),
+    );
+    commit(&repo, "setup");
+    let aggregate = |path: &str| {
+        SverRepository::new(&calc_target_path(&repo, path))
+            .unwrap()
+            .calc_aggregate_version()
+            .unwrap()
+    };
+
+    // exercise
+    let initial = aggregate("");
+
+    // verify
+    // the same from any target
+    assert_eq!(aggregate("service1"), initial);
+    assert_eq!(aggregate("service2:prof1"), initial);
+    // independent of the order versions are added
+    let lockfile = SverRepository::new(&calc_target_path(&repo, ""))
+        .unwrap()
+        .lock_versions()
+        .unwrap();
+    let mut reversed = Lockfile::default();
+    for (target, version) in lockfile.versions.iter().rev() {
+        reversed.versions.insert(target.clone(), version.clone());
+    }
+    assert_eq!(reversed.aggregate_version(), initial);
+
+    // exercise
+    add_blob(&repo, "service2/world.txt", "good evening".as_bytes());
+    commit(&repo, "edit service2");
+    let service2_changed = aggregate("");
+    add_blob(&repo, "service1/hello.txt", "good morning".as_bytes());
+    commit(&repo, "edit service1");
+    let service1_changed = aggregate("");
+
+    // verify
+    assert_ne!(service2_changed, initial);
+    assert_ne!(service1_changed, service2_changed);
+    assert_ne!(service1_changed, initial);
+}
+
+// repo layout
+// .
+// + service1/hello.txt
+// + service1/sver.toml → [default] no setting
+// + service2/world.txt
+// + service2/sver.toml → [default] no setting, [prof1] dependency = [ "service1" ]
+// + sver.lock → written by the test
+#[test]
+fn lock_versions_repository() {
+    initialize();
+
+    // setup
+    let repo = setup_test_repository();
+    add_blob(&repo, "service1/hello.txt", "hello".as_bytes());
+    add_blob(&repo, "service1/sver.toml", "[default]\n".as_bytes());
+    add_blob(&repo, "service2/world.txt", "world".as_bytes());
+    add_blob(
+        &repo,
+        "service2/sver.toml",
+        "[default]\n[prof1]\ndependencies = [\"service1\"]\n".as_bytes(),
+    );
+    commit(&repo, "setup");
+    let sver_repo = SverRepository::new(&calc_target_path(&repo, "")).unwrap();
+    let version = |path: &str| {
+        SverRepository::new(&calc_target_path(&repo, path))
+            .unwrap()
+            .calc_version()
+            .unwrap()
+            .version
+    };
+
+    // exercise
+    let lockfile = sver_repo.lock_versions().unwrap();
+
+    // verify
+    assert_eq!(
+        lockfile.versions.keys().collect::<Vec<_>>(),
+        vec!["service1:default", "service2:default", "service2:prof1"]
+    );
+    assert_eq!(lockfile.versions["service1:default"], version("service1"));
+    assert_eq!(
+        lockfile.versions["service2:prof1"],
+        version("service2:prof1")
+    );
+    let content = toml::to_string(&lockfile).unwrap();
+    assert_eq!(Lockfile::parse(&content).unwrap(), lockfile);
+    let content = serde_json::to_string_pretty(&lockfile).unwrap();
+    assert_eq!(Lockfile::parse(&content).unwrap(), lockfile);
+
+    // the lockfile is not a source
+    add_blob(
+        &repo,
+        "sver.lock",
+        toml::to_string(&lockfile).unwrap().as_bytes(),
+    );
+    commit(&repo, "lock");
+    let locked = sver_repo.lock_versions().unwrap();
+    assert!(lockfile.drift(&locked).is_empty());
+
+    // exercise
+    add_blob(&repo, "service1/hello.txt", "good morning".as_bytes());
+    commit(&repo, "edit");
+    let drift = lockfile.drift(&sver_repo.lock_versions().unwrap());
+
+    // verify
+    assert!(drift.added.is_empty());
+    assert!(drift.removed.is_empty());
+    assert_eq!(
+        drift
+            .changed
+            .iter()
+            .map(|c| c.target.as_str())
+            .collect::<Vec<_>>(),
+        vec!["service1:default", "service2:prof1"]
+    );
+    assert_eq!(drift.changed[0].actual, version("service1"));
+    assert_eq!(
+        drift.to_string().lines().next().unwrap(),
+        format!(
+            "M\tservice1:default\tlocked:{}, actual:{}",
+            lockfile.versions["service1:default"],
+            version("service1")
+        )
+    );
+}
+
+// repo layout
+// .
+// + hello.txt
+// + sver.lock → not a lockfile
+#[test]
+fn foreign_lock_file_repository() {
+    initialize();
+
+    // setup
+    let repo = setup_test_repository();
+    add_blob(&repo, "hello.txt", "hello".as_bytes());
+    add_blob(
+        &repo,
+        "sver.lock",
+        "# lock of another tool\nname = \"sver\"\n".as_bytes(),
+    );
+    commit(&repo, "setup");
+
+    let sver_repo = SverRepository::new(&calc_target_path(&repo, "")).unwrap();
+
+    // exercise
+    let sources = sver_repo.list_sources().unwrap();
+    let parse_error = Lockfile::parse("{ \"versions\": [] }");
+
+    // verify
+    assert_eq!(sources, vec!["hello.txt", "sver.lock"]);
+    match parse_error {
+        Err(SverError::InvalidLockfile(message)) => {
+            assert!(message.contains("invalid type"));
+        }
+        _ => unreachable!("this line will not be execute"),
+    }
+}
+
+// repo layout
+// .
+// + service1/hello.txt
+// + service2/sver.yaml → dependency = [ "service1" ]
+// + service3/sver.json → dependency = [ "service1" ]
+#[test]
+fn has_dependencies_yaml_and_json_repository() {
+    initialize();
+
+    // setup
+    let repo = setup_test_repository();
+    add_blob(&repo, "service1/hello.txt", "hello world!".as_bytes());
+    add_blob(
+        &repo,
+        "service2/sver.yaml",
+        "
+        default:
+          dependencies:
+            - service1
+        "
+        .as_bytes(),
+    );
+    add_blob(
+        &repo,
+        "service3/sver.json",
+        r#"{ "default": { "dependencies": ["service1"] } }"#.as_bytes(),
+    );
+    commit(&repo, "setup");
+
+    let yaml_repo = SverRepository::new(&calc_target_path(&repo, "service2")).unwrap();
+    let json_repo = SverRepository::new(&calc_target_path(&repo, "service3")).unwrap();
+
+    // exercise
+    let yaml_sources = yaml_repo.list_sources().unwrap();
+    let json_sources = json_repo.list_sources().unwrap();
+    let validation = yaml_repo.validate_sver_config().unwrap();
+
+    // verify
+    assert_eq!(
+        yaml_sources,
+        vec!["service1/hello.txt", "service2/sver.yaml"]
+    );
+    assert_eq!(
+        json_sources,
+        vec!["service1/hello.txt", "service3/sver.json"]
+    );
+    assert!(!validation.has_invalid);
+    assert_eq!(validation.results.len(), 2);
+    assert_eq!(
+        validation.results[0].to_string(),
+        "[OK]\tservice2/sver.yaml:[default]\n"
+    );
+    assert_eq!(
+        validation.results[1].to_string(),
+        "[OK]\tservice3/sver.json:[default]\n"
+    );
+    assert_eq!(
+        yaml_repo.list_targets().unwrap(),
+        vec![
+            CalculationTarget::new("service1".to_string(), "default".to_string()),
+            CalculationTarget::new("service2".to_string(), "default".to_string()),
+            CalculationTarget::new("service3".to_string(), "default".to_string()),
+        ]
+    );
+    assert_eq!(
+        yaml_repo.calc_version().unwrap().version,
+        "637c89a5b30f397287e76640800d3693566077debd4542ac51c72fc1f24f1dce"
+    );
+}
+
+// repo layout
+// .
+// + service1/sver.toml → [default] no setting
+// + service1/sver.yaml → default: no setting
+// + service2/sver.yml → syntax error at line 3
+#[test]
+fn multiple_config_files_repository() {
+    initialize();
+
+    // setup
+    let repo = setup_test_repository();
+    add_blob(&repo, "service1/sver.toml", "[default]\n".as_bytes());
+    add_blob(&repo, "service1/sver.yaml", "default: {}\n".as_bytes());
+    add_blob(
+        &repo,
+        "service2/sver.yml",
+        "default:\n  dependencies: []\n  excludes: [\n".as_bytes(),
+    );
+    commit(&repo, "setup");
+
+    // exercise
+    let calc_result = SverRepository::new(&calc_target_path(&repo, "service1"))
+        .and_then(|sver_repo| sver_repo.calc_version());
+    let validate_result = SverRepository::new(&calc_target_path(&repo, ""))
+        .and_then(|sver_repo| sver_repo.validate_sver_config());
+    let syntax_error = SverRepository::new(&calc_target_path(&repo, "service2"))
+        .and_then(|sver_repo| sver_repo.calc_version());
+
+    // verify
+    for result in [calc_result.map(|_| ()), validate_result.map(|_| ())] {
+        match result {
+            Err(SverError::InvalidConfig(message)) => {
+                assert_eq!(
+                    message,
+                    "multiple config files in a directory. paths:service1/sver.toml, service1/sver.yaml"
+                );
+            }
+            _ => unreachable!("this line will not be execute"),
+        }
+    }
+    match syntax_error {
+        Err(SverError::InvalidConfig(message)) => {
+            assert!(message.contains("path:service2/sver.yml, line:4, column:1"));
+        }
+        _ => unreachable!("this line will not be execute"),
+    }
+}
+
+// repo layout
+// .
+// + lib/x.txt
+// + lib-extra/y.txt
+// + service1/hello.txt
+// + service10/hello.txt
+// + app/sver.toml → dependency = [ "service1", "lib-extra" ], excludes = [ "lib/" ]
+// + app/lib/z.txt
+// + app/lib-extra/z.txt
+#[test]
+fn sibling_prefix_repository() {
+    initialize();
+
+    // setup
+    let repo = setup_test_repository();
+    add_blob(&repo, "lib/x.txt", "x".as_bytes());
+    add_blob(&repo, "lib-extra/y.txt", "y".as_bytes());
+    add_blob(&repo, "service1/hello.txt", "hello".as_bytes());
+    add_blob(&repo, "service10/hello.txt", "hello".as_bytes());
+    add_blob(
+        &repo,
+        "app/sver.toml",
+        "
+        [default]
+        dependencies = [
+            \"service1\",
+            \"lib-extra\",
+        ]
+        excludes = [
+            \"lib/\",
+        ]"
+        .as_bytes(),
+    );
+    add_blob(&repo, "app/lib/z.txt", "z".as_bytes());
+    add_blob(&repo, "app/lib-extra/z.txt", "z".as_bytes());
+    commit(&repo, "setup");
+    let sver_repo = SverRepository::new(&calc_target_path(&repo, "app")).unwrap();
+
+    // exercise
+    let sources = sver_repo.list_sources().unwrap();
+    let validation = sver_repo.validate_sver_config().unwrap();
+
+    // verify
+    assert_eq!(
+        sources,
+        vec![
+            "app/lib-extra/z.txt",
+            "app/sver.toml",
+            "lib-extra/y.txt",
+            "service1/hello.txt"
+        ]
+    );
+    assert!(!validation.has_invalid);
+}
+
+// repo layout
+// .
+// + service1/hello.txt
+// + service1/draft.txt (untracked)
+#[cfg(all(target_os = "linux", feature = "watch"))]
+#[test]
+fn watch_version_repository() {
+    use std::{ops::ControlFlow, sync::mpsc::channel, time::Duration};
+
+    initialize();
+
+    // setup
+    let repo = setup_test_repository();
+    let work_dir = repo.workdir().unwrap().to_path_buf();
+    create_dir(work_dir.join("service1")).unwrap();
+    std::fs::write(work_dir.join("service1/hello.txt"), "hello").unwrap();
+    std::fs::write(work_dir.join("service1/draft.txt"), "draft").unwrap();
+    add_blob(&repo, "service1/hello.txt", "hello".as_bytes());
+    commit(&repo, "setup");
+    let target = calc_target_path(&repo, "service1");
+    let options = CalcOptions {
+        include_untracked: true,
+        ..Default::default()
+    };
+    let (sender, receiver) = channel();
+    let watcher =
+        {
+            let target = target.clone();
+            let options = options.clone();
+            std::thread::spawn(move || {
+                let sver_repo = SverRepository::new(&target).unwrap().with_options(options);
+                sver::watch::watch_version(&sver_repo, Duration::from_millis(50), |version| {
+                    match sender.send(version.unwrap().version.clone()) {
+                        Ok(_) => ControlFlow::Continue(()),
+                        Err(_) => ControlFlow::Break(()),
+                    }
+                })
+                .unwrap();
+            })
+        };
+    let timeout = Duration::from_secs(10);
+    let initial = receiver.recv_timeout(timeout).unwrap();
+
+    // exercise
+    std::fs::write(work_dir.join("service1/draft.txt"), "draft modified").unwrap();
+    let modified = receiver.recv_timeout(timeout).unwrap();
+    // an unstaged edit of a tracked file does not change the version
+    std::fs::write(work_dir.join("service1/hello.txt"), "hello world").unwrap();
+    let unstaged = receiver.recv_timeout(Duration::from_millis(500));
+    add_blob(&repo, "service1/hello.txt", "hello world".as_bytes());
+    let staged = receiver.recv_timeout(timeout).unwrap();
+
+    // verify
+    assert_ne!(initial, modified);
+    assert!(unstaged.is_err());
+    assert_ne!(modified, staged);
+    let expected = SverRepository::new(&target)
+        .unwrap()
+        .with_options(options)
+        .calc_version()
+        .unwrap();
+    assert_eq!(staged, expected.version);
+
+    // the watcher stops at the next version once the receiver is dropped
+    drop(receiver);
+    std::fs::write(work_dir.join("service1/draft.txt"), "draft stopped").unwrap();
+    watcher.join().unwrap();
+}
+
+// repo layout
+// .
+// + lib/file{0..3000}.txt
+// + service1/src/file{0..3000}.txt
+// + service1/generated/file{0..1000}.txt
+// + service1/sver.toml → dependency = [ "lib" ], excludes = [ "generated" ]
+// + service2/file{0..3000}.txt
+#[test]
+fn large_generated_repository() {
+    initialize();
+
+    // setup
+    let repo = setup_test_repository();
+    let generate = |dir: &str, count: usize| {
+        (0..count)
+            .map(|i| {
+                (
+                    format!("{dir}/file{i}.txt"),
+                    format!("{dir} {i}").into_bytes(),
+                )
+            })
+            .collect::<Vec<_>>()
+    };
+    let mut files = [
+        generate("lib", 3000),
+        generate("service1/src", 3000),
+        generate("service1/generated", 1000),
+        generate("service2", 3000),
+    ]
+    .concat();
+    files.push((
+        "service1/sver.toml".to_string(),
+        "
+        [default]
+        dependencies = [
+            \"lib\",
+        ]
+        excludes = [
+            \"generated\",
+        ]"
+        .as_bytes()
+        .to_vec(),
+    ));
+    add_blobs(&repo, &files);
+    commit(&repo, "setup");
+    let sver_repo = SverRepository::new(&calc_target_path(&repo, "service1")).unwrap();
+
+    // exercise
+    let sources = sver_repo.list_sources().unwrap();
+    let version = sver_repo.calc_version().unwrap();
+
+    // verify
+    let mut expected = files
+        .iter()
+        .map(|(path, _)| path.as_str())
+        .filter(|path| {
+            path.starts_with("lib/")
+                || (path.starts_with("service1/") && !path.starts_with("service1/generated/"))
+        })
+        .collect::<Vec<_>>();
+    expected.sort();
+    assert_eq!(sources.len(), 6001);
+    assert_eq!(sources, expected);
+
+    // the version folds the sources serially in byte order
+    let index = repo.index().unwrap();
+    let mut hasher = Sha256::default();
+    hasher.update("service1".as_bytes());
+    for path in &expected {
+        let entry = index.get_path(Path::new(path), 0).unwrap();
+        hasher.update(path.as_bytes());
+        hasher.update(entry.mode.to_le_bytes());
+        hasher.update(entry.id.as_bytes());
+    }
+    assert_eq!(version.version, format!("{:#x}", hasher.finalize()));
+    assert_eq!(sver_repo.calc_version().unwrap(), version);
+}
+
+// repo layout
+// .
+// + service1/.gitattributes → secret.txt export-ignore, generated export-ignore
+// + service1/hello.txt
+// + service1/secret.txt
+// + service1/generated/out.txt
+#[cfg(feature = "export")]
+#[test]
+fn export_ignore_repository() {
+    initialize();
+
+    // setup
+    let repo = setup_test_repository();
+    add_blob(
+        &repo,
+        "service1/.gitattributes",
+        "secret.txt export-ignore\ngenerated export-ignore\n".as_bytes(),
+    );
+    add_blob(&repo, "service1/hello.txt", "hello world!".as_bytes());
+    add_blob(&repo, "service1/secret.txt", "secret".as_bytes());
+    add_blob(&repo, "service1/generated/out.txt", "generated".as_bytes());
+    commit(&repo, "setup");
+    let target = calc_target_path(&repo, "service1");
+
+    // exercise
+    let export_dir = sver::export::create_export_dir(None).unwrap();
+    let result = sver::export::export(&target, export_dir.clone());
+    let sources = SverRepository::new(&target)
+        .unwrap()
+        .list_sources()
+        .unwrap();
+
+    // verify
+    assert!(result.is_ok());
+    assert!(export_dir.join("service1/.gitattributes").exists());
+    assert!(export_dir.join("service1/hello.txt").exists());
+    assert!(!export_dir.join("service1/secret.txt").exists());
+    assert!(!export_dir.join("service1/generated").exists());
+    assert_eq!(
+        sources,
+        vec![
+            "service1/.gitattributes",
+            "service1/generated/out.txt",
+            "service1/hello.txt",
+            "service1/secret.txt",
+        ]
+    );
+}
+
+// repo layout
+// .
+// + .gitattributes → secret.txt export-ignore
+// + service1/hello.txt
+// + service1/run.sh (executable)
+// + service1/link → ../lib1/hello.txt
+// + service1/sver.toml → dependency = [ "lib1/hello.txt" ]
+// + service1/secret.txt
+// + lib1/hello.txt
+// + lib1/unknown.txt
+#[cfg(feature = "export")]
+#[test]
+fn export_from_index_same_as_clone() {
+    initialize();
+
+    // setup
+    let repo = setup_test_repository();
+    add_blob(
+        &repo,
+        ".gitattributes",
+        "secret.txt export-ignore\n".as_bytes(),
+    );
+    add_blob(&repo, "service1/hello.txt", "hello world!".as_bytes());
+    add_blob_executable(&repo, "service1/run.sh", "#!/bin/sh\necho hello".as_bytes());
+    add_symlink(&repo, "service1/link", "../lib1/hello.txt");
+    add_blob(
+        &repo,
+        "service1/sver.toml",
+        "
+        [default]
+        dependencies = [
+            \"lib1/hello.txt\",
+        ]"
+        .as_bytes(),
+    );
+    add_blob(&repo, "service1/secret.txt", "secret".as_bytes());
+    add_blob(&repo, "lib1/hello.txt", "hello lib!".as_bytes());
+    add_blob(&repo, "lib1/unknown.txt", "good bye!".as_bytes());
+    commit(&repo, "setup");
+    let target = calc_target_path(&repo, "service1");
+
+    // exercise
+    let cloned_dir = sver::export::create_export_dir(None).unwrap();
+    sver::export::export(&target, cloned_dir.clone()).unwrap();
+    let exported_dir = sver::export::create_export_dir(None).unwrap();
+    sver::export::export_from_index(&SverRepository::new(&target).unwrap(), exported_dir.clone())
+        .unwrap();
+
+    // verify
+    // (path, symlink target or content, executable)
+    let files = |dir: &Path| {
+        walkdir::WalkDir::new(dir)
+            .sort_by_file_name()
+            .into_iter()
+            .map(|e| e.unwrap())
+            .filter(|e| !e.path().starts_with(dir.join(".git")) && !e.file_type().is_dir())
+            .map(|e| {
+                let metadata = e.path().symlink_metadata().unwrap();
+                let content = if metadata.is_symlink() {
+                    std::fs::read_link(e.path())
+                        .unwrap()
+                        .to_str()
+                        .unwrap()
+                        .as_bytes()
+                        .to_vec()
+                } else {
+                    std::fs::read(e.path()).unwrap()
+                };
+                #[cfg(unix)]
+                let executable = {
+                    use std::os::unix::fs::PermissionsExt;
+                    !metadata.is_symlink() && metadata.permissions().mode() & 0o111 != 0
+                };
+                #[cfg(not(unix))]
+                let executable = false;
+                let path = e.path().strip_prefix(dir).unwrap().to_path_buf();
+                (path, content, executable)
+            })
+            .collect::<Vec<_>>()
+    };
+    let exported = files(&exported_dir);
+    assert_eq!(exported, files(&cloned_dir));
+    assert_eq!(
+        exported
+            .iter()
+            .map(|(path, _, executable)| (path.to_str().unwrap(), *executable))
+            .collect::<Vec<_>>(),
+        vec![
+            ("lib1/hello.txt", false),
+            ("service1/hello.txt", false),
+            ("service1/link", false),
+            ("service1/run.sh", cfg!(unix)),
+            ("service1/sver.toml", false),
+        ]
+    );
+}
+
+// repo layout
+// .
+// + sub → submodule ../sub e40a885afd013606e105c027a5c31910137e5566
+#[cfg(feature = "export")]
+#[test]
+fn export_from_index_has_submodule() {
+    initialize();
+
+    // setup
+    let mut tmp_dir = temp_dir();
+    let uuid = Uuid::now_v7();
+    tmp_dir.push(format!("sver-{}", uuid));
+    create_dir(tmp_dir.clone()).unwrap();
+
+    // setup external repo
+    let mut sub_repo_dir = tmp_dir.clone();
+    sub_repo_dir.push("sub");
+
+    let sub_repo = Repository::init(sub_repo_dir).unwrap();
+    add_blob(&sub_repo, "hello.txt", "hello".as_bytes());
+    commit_at(
+        &sub_repo,
+        "setup",
+        Utc.with_ymd_and_hms(2022, 10, 1, 10, 20, 30)
+            .earliest()
+            .unwrap(),
+    );
+
+    // setup sut repo
+    let mut sut_repo_dir = tmp_dir.clone();
+    sut_repo_dir.push("sut");
+
+    let mut repo = Repository::init(sut_repo_dir).unwrap();
+    add_submodule(
+        &mut repo,
+        "../sub",
+        "sub",
+        "e40a885afd013606e105c027a5c31910137e5566",
+    );
+    commit(&repo, "setup");
+
+    // exercise
+    let export_dir = sver::export::create_export_dir(None).unwrap();
+    let sver_repo = SverRepository::new(&calc_target_path(&repo, "")).unwrap();
+    let result = sver::export::export_from_index(&sver_repo, export_dir.clone());
+
+    // verify
+    assert!(result.is_ok());
+    assert_eq!(
+        std::fs::read_to_string(export_dir.join("sub/hello.txt")).unwrap(),
+        "hello"
+    );
+    assert!(!export_dir.join("sub/.git").exists());
+    assert!(export_dir.join(".gitmodules").exists());
+}
+
+// repo layout
+// .
+// + service1/hello.txt
+// + service1/untracked.txt → not added to the index
+#[cfg(feature = "export")]
+#[test]
+fn export_from_index_untracked_repository() {
+    initialize();
+
+    // setup
+    let repo = setup_test_repository();
+    add_blob(&repo, "service1/hello.txt", "hello".as_bytes());
+    commit(&repo, "setup");
+    std::fs::write(
+        repo.workdir().unwrap().join("service1/untracked.txt"),
+        "untracked",
+    )
+    .unwrap();
+
+    // exercise
+    let export_dir = sver::export::create_export_dir(None).unwrap();
+    let sver_repo = SverRepository::builder()
+        .repository(repo.workdir().unwrap().to_str().unwrap())
+        .path("service1")
+        .options(CalcOptions {
+            include_untracked: true,
+            ..Default::default()
+        })
+        .build()
+        .unwrap();
+    let result = sver::export::export_from_index(&sver_repo, export_dir.clone());
+
+    // verify
+    assert!(result.is_ok());
+    assert_eq!(
+        std::fs::read_to_string(export_dir.join("service1/hello.txt")).unwrap(),
+        "hello"
+    );
+    assert_eq!(
+        std::fs::read_to_string(export_dir.join("service1/untracked.txt")).unwrap(),
+        "untracked"
+    );
+}
+
+// repo layout
+// .
+// + service1/main.txt
+// + src/test2.txt
+// + src/test4.txt
+// + lib/test3.txt
+#[cfg(all(target_os = "linux", feature = "inspect"))]
+#[test]
+fn inspect_emit_config() {
+    initialize();
+
+    // setup
+    let repo = setup_test_repository();
+    add_blob(&repo, "service1/main.txt", "main".as_bytes());
+    add_blob(&repo, "src/test2.txt", "world".as_bytes());
+    add_blob(&repo, "src/test4.txt", "evening".as_bytes());
+    add_blob(&repo, "lib/test3.txt", "morning".as_bytes());
+    commit(&repo, "setup");
+    let target = calc_target_path(&repo, "service1");
+
+    // exercise
+    let files = sver::inspect::inspect(
+        &target,
+        "cat".to_string(),
+        vec![
+            "main.txt".to_string(),
+            "../src/test2.txt".to_string(),
+            "../lib/test3.txt".to_string(),
+        ],
+        std::process::Stdio::null(),
+    )
+    .unwrap()
+    .paths();
+    let sver_repo = SverRepository::new(&target).unwrap();
+    let config = sver_repo.config_from_files(&files).unwrap();
+    let message = sver_repo.init_sver_config_from_files(&files).unwrap();
+
+    // verify
+    assert_eq!(
+        files,
+        vec!["lib/test3.txt", "service1/main.txt", "src/test2.txt"]
+    );
+    // lib is collapsed since all of its files were accessed
+    assert_eq!(
+        config,
+        "[default]\ndependencies = [\n  \"lib\",\n  \"src/test2.txt\",\n]\nexcludes = [\n]\n"
+    );
+    assert_eq!(message, "sver.toml is generated. path:service1");
+    assert_eq!(
+        std::fs::read_to_string(Path::new(&target).join("sver.toml")).unwrap(),
+        config
+    );
+}
+
+// repo layout
+// .
+// + test1.txt
+// + src/test2.txt
+// + lib/test3.txt
+#[cfg(all(target_os = "linux", feature = "inspect"))]
+#[test]
+fn inspect_open_and_modify_events() {
+    use sver::inspect::{inspect_with_events, InspectEvent};
+
+    initialize();
+
+    // setup
+    let repo = setup_test_repository();
+    add_blob(&repo, "test1.txt", "hello".as_bytes());
+    add_blob(&repo, "src/test2.txt", "world".as_bytes());
+    add_blob(&repo, "lib/test3.txt", "morning".as_bytes());
+    commit(&repo, "setup");
+    let work_dir = repo.workdir().unwrap().to_string_lossy().to_string();
+    // test1.txt is opened without being read, and src/test2.txt is written and read
+    let command = ": < test1.txt && echo hi >> src/test2.txt && cat src/test2.txt";
+    let run = |events: &[InspectEvent]| {
+        inspect_with_events(
+            &work_dir,
+            "sh".to_string(),
+            vec!["-c".to_string(), command.to_string()],
+            std::process::Stdio::null(),
+            events,
+        )
+        .unwrap()
+        .paths()
+    };
+
+    // exercise
+    let accessed = run(&[InspectEvent::Access]);
+    let opened = run(&[InspectEvent::Open]);
+    let modified = run(&[InspectEvent::Modify]);
+    let all = run(&[
+        InspectEvent::Access,
+        InspectEvent::Open,
+        InspectEvent::Modify,
+    ]);
+
+    // verify
+    assert_eq!(accessed, vec!["src/test2.txt"]);
+    assert_eq!(opened, vec!["src/test2.txt", "test1.txt"]);
+    assert_eq!(modified, vec!["src/test2.txt"]);
+    assert_eq!(all, vec!["src/test2.txt", "test1.txt"]);
+    assert!(inspect_with_events(
+        &work_dir,
+        "true".to_string(),
+        vec![],
+        std::process::Stdio::null(),
+        &[],
+    )
+    .is_err());
+}
+
+// repo layout
+// .
+// + test1.txt
+// + src/test2.txt
+// + src/sub/test3.txt
+// + lib/test4.txt
+#[cfg(all(target_os = "linux", feature = "inspect"))]
+#[test]
+fn inspect_subtree_repository() {
+    use sver::inspect::{inspect_subtree, InspectEvent};
+
+    initialize();
+
+    // setup
+    let repo = setup_test_repository();
+    add_blob(&repo, "test1.txt", "hello".as_bytes());
+    add_blob(&repo, "src/test2.txt", "world".as_bytes());
+    add_blob(&repo, "src/sub/test3.txt", "morning".as_bytes());
+    add_blob(&repo, "lib/test4.txt", "evening".as_bytes());
+    commit(&repo, "setup");
+    let src_dir = repo.workdir().unwrap().join("src");
+    let run = |include_dirs: &[String]| {
+        inspect_subtree(
+            &src_dir.to_string_lossy(),
+            "sh".to_string(),
+            vec![
+                "-c".to_string(),
+                "cat ../test1.txt test2.txt sub/test3.txt ../lib/test4.txt".to_string(),
+            ],
+            std::process::Stdio::null(),
+            &[InspectEvent::Access],
+            include_dirs,
+        )
+    };
+
+    // exercise
+    let subtree = run(&[]).unwrap();
+    let with_lib = run(&["../lib".to_string()]).unwrap();
+    let outside = run(&["../..".to_string()]);
+
+    // verify
+    assert_eq!(subtree.paths(), vec!["src/sub/test3.txt", "src/test2.txt"]);
+    assert_eq!(
+        with_lib.paths(),
+        vec!["lib/test4.txt", "src/sub/test3.txt", "src/test2.txt"]
+    );
+    match outside {
+        Err(e) => assert!(e.to_string().contains("outside the repository")),
+        Ok(_) => unreachable!("this line will not be execute"),
+    }
+}
+
+// repo layout
+// .
+// + many/file{0..3000}.txt
+#[cfg(all(target_os = "linux", feature = "inspect"))]
+#[test]
+fn inspect_many_files() {
+    initialize();
+
+    // setup
+    let repo = setup_test_repository();
+    let files = (0..3000)
+        .map(|i| {
+            (
+                format!("many/file{i}.txt"),
+                format!("file {i}").into_bytes(),
+            )
+        })
+        .collect::<Vec<_>>();
+    add_blobs(&repo, &files);
+    commit(&repo, "setup");
+
+    // exercise
+    let result = sver::inspect::inspect(
+        &repo.workdir().unwrap().to_string_lossy(),
+        "sh".to_string(),
+        vec!["-c".to_string(), "cat many/*.txt".to_string()],
+        std::process::Stdio::null(),
+    );
+
+    // verify
+    // all files are listed, or the result tells events were dropped
+    match result {
+        Ok(accessed) => {
+            let mut expected = files.into_iter().map(|(path, _)| path).collect::<Vec<_>>();
+            expected.sort();
+            assert_eq!(accessed.paths(), expected);
+        }
+        Err(e) => assert!(e.to_string().contains("overflowed")),
+    }
+}
+
+// repo layout
+// .
+// + test1.txt
+// + src/test2.txt
+#[cfg(all(target_os = "linux", feature = "inspect"))]
+#[test]
+fn inspect_result_repository() {
+    use sver::inspect::InspectedFile;
+
+    initialize();
+
+    // setup
+    let repo = setup_test_repository();
+    add_blob(&repo, "test1.txt", "hello".as_bytes());
+    add_blob(&repo, "src/test2.txt", "world".as_bytes());
+    commit(&repo, "setup");
+
+    // exercise
+    let result = sver::inspect::inspect(
+        &repo.workdir().unwrap().to_string_lossy(),
+        "sh".to_string(),
+        vec![
+            "-c".to_string(),
+            "echo hi > src/untracked.txt && cat test1.txt src/test2.txt test1.txt src/untracked.txt"
+                .to_string(),
+        ],
+        std::process::Stdio::null(),
+    )
+    .unwrap();
+
+    // verify
+    assert_eq!(
+        result.files,
+        vec![
+            InspectedFile {
+                path: "src/test2.txt".to_string(),
+                tracked: true,
+                directory: "src".to_string(),
+                access_count: 1,
+            },
+            InspectedFile {
+                path: "src/untracked.txt".to_string(),
+                tracked: false,
+                directory: "src".to_string(),
+                access_count: 1,
+            },
+            InspectedFile {
+                path: "test1.txt".to_string(),
+                tracked: true,
+                directory: "".to_string(),
+                access_count: 2,
+            },
+        ]
+    );
+    assert_eq!(
+        result.paths(),
+        vec!["src/test2.txt", "src/untracked.txt", "test1.txt"]
+    );
+}
+
+// repo layout
+// .
+// + service1/hello.txt
+// + service2/sver.toml → three profiles depend on `service1` and exclude `generated` with different spellings
+// + service2/generated/gen.txt
+#[test]
+fn normalized_dependency_repository() {
+    initialize();
+
+    // setup
+    let repo = setup_test_repository();
+    add_blob(&repo, "service1/hello.txt", "hello world!".as_bytes());
+    add_blob(&repo, "service2/generated/gen.txt", "generated".as_bytes());
+    add_blob(
+        &repo,
+        "service2/sver.toml",
+        "
+        [plain]
+        dependencies = [\"service1\"]
+        excludes = [\"generated\"]
+
+        [dot]
+        dependencies = [\"./service1\"]
+        excludes = [\"./generated/\"]
+
+        [slash]
+        dependencies = [\"service1//\"]
+        excludes = [\"generated//\"]
+        "
+        .as_bytes(),
+    );
+    commit(&repo, "setup");
+
+    // exercise
+    let results = ["plain", "dot", "slash"].map(|profile| {
+        let sver_repo =
+            SverRepository::new(&calc_target_path(&repo, &format!("service2:{profile}"))).unwrap();
+        (
+            sver_repo.list_sources().unwrap(),
+            sver_repo.calc_version().unwrap().version,
+        )
+    });
+    let sver_repo = SverRepository::new(&calc_target_path(&repo, "service2")).unwrap();
+    let ValidationResults { has_invalid, .. } = sver_repo.validate_sver_config().unwrap();
+
+    // verify
+    assert_eq!(
+        results[0].0,
+        vec!["service1/hello.txt", "service2/sver.toml"]
+    );
+    assert_eq!(results[0], results[1]);
+    assert_eq!(results[0], results[2]);
+    assert!(!has_invalid);
+}
+
+// repo layout
+// .
+// + service1/hello.txt
+// + service1/sver.toml → [default] excludes = [ "sver.toml" ], [reincluded] excludes = [ "*.toml", "!sver.toml" ]
+#[test]
+fn config_file_excluded_repository() {
+    initialize();
+
+    // setup
+    let repo = setup_test_repository();
+    add_blob(&repo, "service1/hello.txt", "hello world!".as_bytes());
+    add_blob(
+        &repo,
+        "service1/sver.toml",
+        "
+        [default]
+        excludes = [\"sver.toml\"]
+
+        [reincluded]
+        excludes = [\"*.toml\", \"!sver.toml\"]
+        "
+        .as_bytes(),
+    );
+    commit(&repo, "setup");
+
+    let sver_repo = SverRepository::new(&calc_target_path(&repo, "service1")).unwrap();
+
+    // exercise
+    let ValidationResults {
+        has_invalid,
+        results,
+        ..
+    } = sver_repo.validate_sver_config().unwrap();
+
+    // verify
+    assert!(!has_invalid);
+    assert_eq!(results.len(), 2);
+    if let ValidationResult::Warning {
+        calcuration_target: CalculationTarget { path, profile },
+        config_file_exclude,
+        cyclic_dependencies,
+        ..
+    } = &results[0]
+    {
+        assert_eq!(path, "service1");
+        assert_eq!(profile, "default");
+        assert_eq!(config_file_exclude.as_deref(), Some("sver.toml"));
+        assert!(cyclic_dependencies.is_empty());
+    } else {
+        unreachable!("this line will not be execute");
+    }
+    assert!(matches!(
+        &results[1],
+        ValidationResult::Valid {
+            calcuration_target: CalculationTarget { profile, .. },
+            ..
+        } if profile == "reincluded"
+    ));
+    // the calculation is not changed
+    assert_eq!(
+        sver_repo.list_sources().unwrap(),
+        vec!["service1/hello.txt"]
+    );
+}
+
+// repo layout
+// .
+// + service1/[draft].md
+// + service1/d.md
+// + service1/sver.toml → [default] excludes = [ "[draft].md" ], [escaped] excludes = [ "\\[draft\\].md" ]
+#[test]
+fn ambiguous_exclude_repository() {
+    initialize();
+
+    // setup
+    let repo = setup_test_repository();
+    add_blob(&repo, "service1/[draft].md", "draft".as_bytes());
+    add_blob(&repo, "service1/d.md", "d".as_bytes());
+    add_blob(
+        &repo,
+        "service1/sver.toml",
+        r#"
+        [default]
+        excludes = ["[draft].md"]
+
+        [escaped]
+        excludes = ["\\[draft\\].md"]
+        "#
+        .as_bytes(),
+    );
+    commit(&repo, "setup");
+
+    let sver_repo = SverRepository::new(&calc_target_path(&repo, "service1")).unwrap();
+    let escaped_repo =
+        SverRepository::new(&calc_target_path_with_profile(&repo, "service1", "escaped")).unwrap();
+
+    // exercise
+    let ValidationResults {
+        has_invalid,
+        results,
+        ..
+    } = sver_repo.validate_sver_config().unwrap();
+
+    // verify
+    assert!(!has_invalid);
+    assert_eq!(results.len(), 2);
+    if let ValidationResult::Warning {
+        calcuration_target: CalculationTarget { profile, .. },
+        ambiguous_excludes,
+        ..
+    } = &results[0]
+    {
+        assert_eq!(profile, "default");
+        assert_eq!(ambiguous_excludes, &vec!["[draft].md"]);
+    } else {
+        unreachable!("this line will not be execute");
+    }
+    assert!(matches!(
+        &results[1],
+        ValidationResult::Valid {
+            calcuration_target: CalculationTarget { profile, .. },
+            ..
+        } if profile == "escaped"
+    ));
+    // the glob matches `d.md` and not the file named by it
+    assert_eq!(
+        sver_repo.list_sources().unwrap(),
+        vec!["service1/[draft].md", "service1/sver.toml"]
+    );
+    assert_eq!(
+        escaped_repo.list_sources().unwrap(),
+        vec!["service1/d.md", "service1/sver.toml"]
+    );
+}
+
+// repo layout
+// .
+// + services/shared/shared.txt
+// + services/app/app.txt
+// + services/app/sver.toml → dependencies = [ "../shared" ]
+// + services/escape/sver.toml → dependencies = [ "../../../outside" ]
+#[test]
+fn relative_dependency_repository() {
+    initialize();
+
+    // setup
+    let repo = setup_test_repository();
+    add_blob(&repo, "services/shared/shared.txt", "shared".as_bytes());
+    add_blob(&repo, "services/app/app.txt", "app".as_bytes());
+    add_blob(
+        &repo,
+        "services/app/sver.toml",
+        "
+        [default]
+        dependencies = [\"../shared\"]
+        "
+        .as_bytes(),
+    );
+    add_blob(
+        &repo,
+        "services/escape/sver.toml",
+        "
+        [default]
+        dependencies = [\"../../../outside\"]
+        "
+        .as_bytes(),
+    );
+    commit(&repo, "setup");
+
+    // exercise
+    let app_repo = SverRepository::new(&calc_target_path(&repo, "services/app")).unwrap();
+    let sources = app_repo.list_sources().unwrap();
+    let escape_repo = SverRepository::new(&calc_target_path(&repo, "services/escape")).unwrap();
+    let escape_result = escape_repo.calc_version();
+    let ValidationResults {
+        has_invalid,
+        results,
+        ..
+    } = app_repo.validate_sver_config().unwrap();
+
+    // verify
+    assert_eq!(
+        sources,
+        vec![
+            "services/app/app.txt",
+            "services/app/sver.toml",
+            "services/shared/shared.txt",
+        ]
+    );
+    assert!(escape_result.is_err());
+    assert!(has_invalid);
+    assert!(matches!(
+        &results[0],
+        ValidationResult::Valid {
+            calcuration_target: CalculationTarget { path, .. },
+            ..
+        } if path == "services/app"
+    ));
+    if let ValidationResult::Invalid {
+        calcuration_target: CalculationTarget { path, .. },
+        invalid_dependencies,
+        ..
+    } = &results[1]
+    {
+        assert_eq!(path, "services/escape");
+        assert_eq!(invalid_dependencies, &vec!["../../../outside"]);
+    } else {
+        unreachable!("this line will not be execute");
+    }
+}
+
+// repo layout
+// .
+// + service1/hello.txt
+// + service1/sver.toml → dependencies = [ "lib1" ]
+// + lib1/lib.txt
+// + lib2/other.txt
+#[test]
+fn calc_version_with_stats_repository() {
+    initialize();
+
+    // setup
+    let repo = setup_test_repository();
+    let config = "
+        [default]
+        dependencies = [\"lib1\"]
+        ";
+    add_blob(&repo, "service1/hello.txt", "hello world!".as_bytes());
+    add_blob(&repo, "service1/sver.toml", config.as_bytes());
+    add_blob(&repo, "lib1/lib.txt", "lib1".as_bytes());
+    add_blob(&repo, "lib2/other.txt", "not a source".as_bytes());
+    commit(&repo, "setup");
+
+    let sver_repo = SverRepository::new(&calc_target_path(&repo, "service1")).unwrap();
+
+    // exercise
+    let (version, stats) = sver_repo.calc_version_with_stats().unwrap();
+
+    // verify
+    assert_eq!(version, sver_repo.calc_version().unwrap());
+    assert_eq!(stats.files, sver_repo.list_sources().unwrap().len());
+    assert_eq!(stats.files, 3);
+    assert_eq!(
+        stats.bytes,
+        ("hello world!".len() + config.len() + "lib1".len()) as u64
+    );
+}
+
+// repo layout
+// .
+// + test1.txt
+// + test2.txt
+// + sver.toml → [default] no setting, [prof1] exclude test1.txt
+// + lib1/test3.txt
+#[test]
+fn calc_versions_of_all_profiles() {
+    initialize();
+
+    // setup
+    let repo = setup_test_repository();
+    add_blob(&repo, "test1.txt", "hello".as_bytes());
+    add_blob(&repo, "test2.txt", "world".as_bytes());
+    add_blob(
+        &repo,
+        "sver.toml",
+        "
+        [default]
+        
+        [prof1]
+        excludes = [
+            \"test1.txt\",
+        ]"
+        .as_bytes(),
+    );
+    add_blob(&repo, "lib1/test3.txt", "lib".as_bytes());
+    commit(&repo, "setup");
+
+    // exercise
+    let sver_repo =
+        SverRepository::new(&calc_target_path_with_profile(&repo, ".", "prof1")).unwrap();
+    let versions = sver_repo.calc_versions_of_all_profiles().unwrap();
+    let lib_versions = SverRepository::new(&calc_target_path(&repo, "lib1"))
+        .unwrap()
+        .calc_versions_of_all_profiles()
+        .unwrap();
+
+    // verify
+    assert_eq!(
+        versions
+            .iter()
+            .map(|v| v.profile.as_str())
+            .collect::<Vec<_>>(),
+        vec!["default", "prof1"]
+    );
+    for version in &versions {
+        let expected =
+            SverRepository::new(&calc_target_path_with_profile(&repo, ".", &version.profile))
+                .unwrap()
+                .calc_version()
+                .unwrap();
+        assert_eq!(version, &expected);
+    }
+    // a directory without config file has only the default profile
+    assert_eq!(lib_versions.len(), 1);
+    assert_eq!(lib_versions[0].profile, "default");
+}
+
+// repo layout
+// .
+// + service1/hello.txt
+// + service1/sver.toml → [default] no setting, [prof1] excludes = [ "hello.txt" ]
+#[cfg(feature = "export")]
+#[test]
+fn versioned_export_dir_repository() {
+    initialize();
+
+    // setup
+    let repo = setup_test_repository();
+    add_blob(&repo, "service1/hello.txt", "versioned export".as_bytes());
+    add_blob(
+        &repo,
+        "service1/sver.toml",
+        "[default]\n[prof1]\nexcludes = [\"hello.txt\"]\n".as_bytes(),
+    );
+    commit(&repo, "setup");
+    let path = calc_target_path(&repo, "service1");
+    let sver_repo = SverRepository::new(&path).unwrap();
+    let prof1_repo = SverRepository::builder()
+        .repository(repo.workdir().unwrap().to_str().unwrap())
+        .path("service1")
+        .profile("prof1")
+        .build()
+        .unwrap();
+    let export = |export_dir| sver::export::export_from_index(&sver_repo, export_dir);
+
+    // exercise
+    let export_dir = sver::export::versioned_export_dir(&sver_repo, true).unwrap();
+    // left by a previous run of this test
+    let _ = std::fs::remove_dir_all(&export_dir);
+    let first = sver::export::export_if_absent(&export_dir, export).unwrap();
+    let second_dir = sver::export::versioned_export_dir(&sver_repo, true).unwrap();
+    let second = sver::export::export_if_absent(&second_dir, export).unwrap();
+    let clone_dir = sver::export::versioned_export_dir(&sver_repo, false).unwrap();
+    let prof1_dir = sver::export::versioned_export_dir(&prof1_repo, false).unwrap();
+    add_blob(&repo, "service1/hello.txt", "changed".as_bytes());
+    commit(&repo, "change");
+    let changed_dir = sver::export::versioned_export_dir(&sver_repo, false).unwrap();
+
+    // verify
+    let version = sver_repo.calc_version().unwrap();
+    // the directory is named after the version of the repository as opened
+    let prof1_version = prof1_repo.calc_version().unwrap();
+    assert!(prof1_dir
+        .to_string_lossy()
+        .ends_with(&format!("sver-export-{}", &prof1_version.version[..12])));
+    assert_eq!(export_dir, second_dir);
+    assert!(first);
+    assert!(!second);
+    assert!(export_dir.join("service1/hello.txt").exists());
+    assert_ne!(export_dir, clone_dir);
+    assert_ne!(export_dir, changed_dir);
+    assert!(changed_dir
+        .to_string_lossy()
+        .ends_with(&format!("sver-export-{}", &version.version[..12])));
+}
+
+// repo layout
+// .
+// + service1/hello.txt → changed and staged after the commit
+#[cfg(feature = "export")]
+#[test]
+fn versioned_export_dir_staged_repository() {
+    initialize();
+
+    // setup
+    let repo = setup_test_repository();
+    add_blob(&repo, "service1/hello.txt", "committed".as_bytes());
+    commit(&repo, "setup");
+    add_blob(&repo, "service1/hello.txt", "staged".as_bytes());
+    let sver_repo = SverRepository::new(&calc_target_path(&repo, "service1")).unwrap();
+    let export = |export_dir| sver::export::export_with_clone(&sver_repo, export_dir);
+
+    // exercise
+    let staged_dir = sver::export::versioned_export_dir(&sver_repo, false).unwrap();
+    // left by a previous run of this test
+    let _ = std::fs::remove_dir_all(&staged_dir);
+    let exported = sver::export::export_if_absent(&staged_dir, export).unwrap();
+    commit(&repo, "stage");
+    let committed_dir = sver::export::versioned_export_dir(&sver_repo, false).unwrap();
+
+    // verify
+    // the clone has the committed file, so the directory is named after the version at HEAD
+    let head_version = sver_repo.calc_version_at("HEAD~1").unwrap();
+    assert!(exported);
+    assert!(staged_dir
+        .to_string_lossy()
+        .ends_with(&format!("sver-export-{}", &head_version.version[..12])));
+    assert_eq!(
+        std::fs::read_to_string(staged_dir.join("service1/hello.txt")).unwrap(),
+        "committed"
+    );
+    // the staged change is exported into another directory once committed
+    let version = sver_repo.calc_version().unwrap();
+    assert!(committed_dir
+        .to_string_lossy()
+        .ends_with(&format!("sver-export-{}", &version.version[..12])));
+    assert_ne!(staged_dir, committed_dir);
+}
+
+// repo layout
+// .
+// + lib1/hello.txt
+// + service1/VERSION → "../lib1", an ordinary file with core.symlinks=false
+#[test]
+fn plain_file_is_not_symlink() {
+    initialize();
+
+    // setup
+    let repo = setup_test_repository();
+    add_blob(&repo, "lib1/hello.txt", "hello".as_bytes());
+    add_blob(&repo, "service1/VERSION", "../lib1".as_bytes());
+    commit(&repo, "setup");
+    let sver_repo = SverRepository::new(&calc_target_path(&repo, "service1")).unwrap();
+    let version = sver_repo.calc_version().unwrap();
+
+    // exercise
+    repo.config()
+        .unwrap()
+        .set_bool("core.symlinks", false)
+        .unwrap();
+    let sources = sver_repo.list_sources().unwrap();
+
+    // verify
+    assert_eq!(sources, vec!["service1/VERSION"]);
+    assert_eq!(sver_repo.calc_version().unwrap(), version);
+}
+
+// repo layout
+// .
+// + linkdir
+//   + symlink → original (stored as a regular file with core.symlinks=false)
+// + original
+//   + README.txt
+//   + Sample.txt
+#[test]
+fn symlink_stored_as_blob() {
+    initialize();
+
+    // setup
+    let repo = setup_test_repository();
+    add_blob(&repo, "original/README.txt", "hello.world".as_bytes());
+    add_blob(&repo, "original/Sample.txt", "sample".as_bytes());
+    add_blob(&repo, "linkdir/symlink", "../original".as_bytes());
+    commit(&repo, "setup");
+
+    let sver_repo = SverRepository::builder()
+        .path(calc_target_path(&repo, "linkdir"))
+        .options(CalcOptions {
+            symlinks_as_blobs: true,
+            ..Default::default()
+        })
+        .build()
+        .unwrap();
+    let default_repo = SverRepository::new(&calc_target_path(&repo, "linkdir")).unwrap();
+
+    // exercise
+    let sources_with_symlinks = sver_repo.list_sources().unwrap();
+    repo.config()
+        .unwrap()
+        .set_bool("core.symlinks", false)
+        .unwrap();
+    let sources = sver_repo.list_sources().unwrap();
+    let version = sver_repo.calc_version().unwrap();
+
+    // verify
+    // a regular file is not followed when symbolic links are supported
+    assert_eq!(sources_with_symlinks, vec!["linkdir/symlink"]);
+    // the index mode is trusted without the option
+    assert_eq!(
+        default_repo.list_sources().unwrap(),
+        vec!["linkdir/symlink"]
+    );
+    // same as has_symlink_dir
+    assert_eq!(
+        sources,
+        vec![
+            "linkdir/symlink",
+            "original/README.txt",
+            "original/Sample.txt"
+        ]
+    );
+    assert_eq!(
+        version.version,
+        "bfd875f92865460d1fcff4769bcd39e7c894c196265ec89937ca05505b41c935"
+    );
+}
+
+// repo layout
+// .
+// + service1/hello.txt
+// + service1/doc/guide.md
+// + service1/sver.toml → excludes = [ "doc" ]
+// + service2/world.txt
+#[cfg(feature = "export")]
+#[test]
+fn plan_export_repository() {
+    initialize();
+
+    // setup
+    let repo = setup_test_repository();
+    add_blob(&repo, "service1/hello.txt", "hello".as_bytes());
+    add_blob(&repo, "service1/doc/guide.md", "guide".as_bytes());
+    add_blob(
+        &repo,
+        "service1/sver.toml",
+        "
+        [default]
+        excludes = [\"doc\"]
+        "
+        .as_bytes(),
+    );
+    add_blob(&repo, "service2/world.txt", "world".as_bytes());
+    commit(&repo, "setup");
+
+    // exercise
+    let sver_repo = SverRepository::new(&calc_target_path(&repo, "service1")).unwrap();
+    let plan = sver::export::plan_export(&sver_repo).unwrap();
+
+    // verify
+    assert_eq!(plan.kept, vec!["service1/hello.txt", "service1/sver.toml"]);
+    assert_eq!(
+        plan.removed,
+        vec!["service1/doc/guide.md", "service2/world.txt"]
+    );
+    assert_eq!(
+        plan.to_string(),
+        "keep\tservice1/hello.txt\nkeep\tservice1/sver.toml\nremove\tservice1/doc/guide.md\nremove\tservice2/world.txt\n"
+    );
+}
+
+// repo layout
+// .
+// + a/drop.txt
+// + a/b/drop.txt
+// + a/b/c/keep.txt
+// + a/b/c/drop.txt
+// + a/bc/drop.txt
+// + service1/sver.toml → dependencies = [ "a/b/c/keep.txt" ]
+#[cfg(feature = "export")]
+#[test]
+fn export_nested_source_repository() {
+    initialize();
+
+    // setup
+    let repo = setup_test_repository();
+    for path in [
+        "a/drop.txt",
+        "a/b/drop.txt",
+        "a/b/c/drop.txt",
+        "a/bc/drop.txt",
+    ] {
+        add_blob(&repo, path, "drop".as_bytes());
+    }
+    add_blob(&repo, "a/b/c/keep.txt", "keep".as_bytes());
+    add_blob(
+        &repo,
+        "service1/sver.toml",
+        "
+        [default]
+        dependencies = [\"a/b/c/keep.txt\"]
+        "
+        .as_bytes(),
+    );
+    commit(&repo, "setup");
+    let path = calc_target_path(&repo, "service1");
+
+    // exercise
+    let export_dir = sver::export::create_export_dir(None).unwrap();
+    sver::export::export(&path, export_dir.clone()).unwrap();
+
+    // verify
+    let mut files = walkdir::WalkDir::new(&export_dir)
+        .into_iter()
+        .map(|e| e.unwrap())
+        .filter(|e| e.file_type().is_file())
+        .map(|e| {
+            e.path()
+                .strip_prefix(&export_dir)
+                .unwrap()
+                .to_string_lossy()
+                .to_string()
+        })
+        .collect::<Vec<_>>();
+    files.sort();
+    assert_eq!(files, vec!["a/b/c/keep.txt", "service1/sver.toml"]);
+}
+
+// repo layout
+// .
+// + linkdir
+//   + hello.txt
+//   + symlink → original
+//   + sver.toml → exclude_modes = [ "link" ]
+// + original
+//   + README.txt
+#[test]
+fn exclude_modes_repository() {
+    initialize();
+
+    // setup
+    let config = "
+        [default]
+        exclude_modes = [\"link\"]
+        ";
+    let setup = |with_symlink: bool| {
+        let repo = setup_test_repository();
+        add_blob(&repo, "linkdir/hello.txt", "hello".as_bytes());
+        add_blob(&repo, "linkdir/sver.toml", config.as_bytes());
+        add_blob(&repo, "original/README.txt", "hello.world".as_bytes());
+        if with_symlink {
+            add_symlink(&repo, "linkdir/symlink", "../original");
+        }
+        commit(&repo, "setup");
+        repo
+    };
+    let repo = setup(true);
+    let repo_without_symlink = setup(false);
+
+    // exercise
+    let sver_repo = SverRepository::new(&calc_target_path(&repo, "linkdir")).unwrap();
+    let sources = sver_repo.list_sources().unwrap();
+    let version = sver_repo.calc_version().unwrap();
+    let version_without_symlink =
+        SverRepository::new(&calc_target_path(&repo_without_symlink, "linkdir"))
+            .unwrap()
+            .calc_version()
+            .unwrap();
+
+    // verify
+    assert_eq!(sources, vec!["linkdir/hello.txt", "linkdir/sver.toml"]);
+    assert_eq!(version.version, version_without_symlink.version);
+}
+
+// repo layout
+// .
+// + lib1/hello.txt
+// + service1/run.sh (executable)
+// + service1/link → ../lib1/hello.txt
+// + service1/sver.toml → dependency = [ "lib1" ]
+#[test]
+fn calc_version_from_index_and_tree() {
+    initialize();
+
+    // setup
+    let repo = setup_test_repository();
+    add_blob(&repo, "lib1/hello.txt", "hello".as_bytes());
+    add_blob_executable(&repo, "service1/run.sh", "#!/bin/sh".as_bytes());
+    add_symlink(&repo, "service1/link", "../lib1/hello.txt");
+    add_blob(
+        &repo,
+        "service1/sver.toml",
+        "
+        [default]
+        dependencies = [\"lib1\"]"
+            .as_bytes(),
+    );
+    commit(&repo, "setup");
+
+    let sver_repo = SverRepository::new(&calc_target_path(&repo, "service1")).unwrap();
+
+    // exercise
+    let from_index = sver_repo.calc_version().unwrap();
+    let from_tree = sver_repo.calc_version_at("HEAD").unwrap();
+
+    // verify
+    assert_eq!(from_index, from_tree);
+    assert_eq!(
+        sver_repo.list_sources().unwrap(),
+        vec![
+            "lib1/hello.txt",
+            "service1/link",
+            "service1/run.sh",
+            "service1/sver.toml"
+        ]
+    );
+}
+
+// repo layout
+// .
+// + service1/hello.txt
+// core.worktree points to a sibling directory outside of this directory
+#[test]
+fn target_path_outside_repository() {
+    initialize();
+
+    // setup
+    let repo = setup_test_repository();
+    add_blob(&repo, "service1/hello.txt", "hello".as_bytes());
+    commit(&repo, "setup");
+    let target_path = calc_target_path(&repo, "service1");
+    let worktree = repo.workdir().unwrap().with_extension("worktree");
+    create_dir(&worktree).unwrap();
+    repo.config()
+        .unwrap()
+        .set_str("core.worktree", worktree.to_str().unwrap())
+        .unwrap();
+
+    // exercise
+    let result = SverRepository::new(&target_path);
+
+    // verify
+    let Err(SverError::OutsideRepository { path, repository }) = result else {
+        unreachable!("this line will not be execute");
+    };
+    assert_eq!(
+        Path::new(&path),
+        Path::new(&target_path).canonicalize().unwrap()
+    );
+    assert_eq!(Path::new(&repository), worktree.canonicalize().unwrap());
+}
+
+// repo layout
+// .
+// + service1/hello.txt
+// + service1/sver.toml → excludes = [ "sver.toml" ]
+#[test]
+fn validate_fail_on_warning_repository() {
+    initialize();
+
+    // setup
+    let repo = setup_test_repository();
+    add_blob(&repo, "service1/hello.txt", "hello world!".as_bytes());
+    add_blob(
+        &repo,
+        "service1/sver.toml",
+        "
+        [default]
+        excludes = [\"sver.toml\"]
+        "
+        .as_bytes(),
+    );
+    commit(&repo, "setup");
+
+    let sver_repo = SverRepository::new(&calc_target_path(&repo, "service1")).unwrap();
+
+    // exercise
+    let results = sver_repo.validate_sver_config().unwrap();
+
+    // verify
+    assert!(!results.has_invalid);
+    assert!(results.has_warning);
+    assert!(!results.is_failure(false));
+    assert!(results.is_failure(true));
+}
+
+// repo layout
+// .
+// + service1/hello.txt
+// + service1/sver.toml → dependencies = [ "unknown/path" ] at HEAD~1, [ "service2" ] at HEAD
+// + service2/hello.txt
+#[test]
+fn validate_sver_config_at_revision() {
+    initialize();
+
+    // setup
+    let repo = setup_test_repository();
+    add_blob(&repo, "service1/hello.txt", "hello world!".as_bytes());
+    add_blob(&repo, "service2/hello.txt", "hello world!".as_bytes());
+    add_blob(
+        &repo,
+        "service1/sver.toml",
+        "
+        [default]
+        dependencies = [\"unknown/path\"]
+        "
+        .as_bytes(),
+    );
+    commit(&repo, "bad config");
+    add_blob(
+        &repo,
+        "service1/sver.toml",
+        "
+        [default]
+        dependencies = [\"service2\"]
+        "
+        .as_bytes(),
+    );
+    commit(&repo, "fix config");
+    // the index is broken again, but not committed
+    add_blob(
+        &repo,
+        "service1/sver.toml",
+        "
+        [default]
+        dependencies = [\"unknown/path\"]
+        "
+        .as_bytes(),
+    );
+
+    let sver_repo = SverRepository::new(&calc_target_path(&repo, "service1")).unwrap();
+
+    // exercise
+    let bad = sver_repo.validate_sver_config_at("HEAD~1").unwrap();
+    let fixed = sver_repo.validate_sver_config_at("HEAD").unwrap();
+    let index = sver_repo.validate_sver_config().unwrap();
+
+    // verify
+    assert!(bad.has_invalid);
+    if let ValidationResult::Invalid {
+        invalid_dependencies,
+        ..
+    } = &bad.results[0]
+    {
+        assert_eq!(invalid_dependencies, &vec!["unknown/path".to_string()]);
+    } else {
+        unreachable!("this line will not be execute");
+    }
+    assert!(!fixed.has_invalid);
+    assert!(matches!(&fixed.results[0], ValidationResult::Valid { .. }));
+    assert!(index.has_invalid);
+}
+
+// repo layout
+// .
+// + service1/hello.txt
+// + service1/sub/world.txt
+// + service1/sver.toml → dependencies = [ "service2" ]
+// + service2/hello.txt
+// + service3/hello.txt
+#[test]
+fn sources_iter_repository() {
+    initialize();
+
+    // setup
+    let repo = setup_test_repository();
+    add_blob(&repo, "service1/hello.txt", "hello world!".as_bytes());
+    add_blob(&repo, "service1/sub/world.txt", "hello world!".as_bytes());
+    add_blob(
+        &repo,
+        "service1/sver.toml",
+        "
+        [default]
+        dependencies = [\"service2\"]
+        "
+        .as_bytes(),
+    );
+    add_blob(&repo, "service2/hello.txt", "hello world!".as_bytes());
+    add_blob(&repo, "service3/hello.txt", "hello world!".as_bytes());
+    commit(&repo, "setup");
+
+    let sver_repo = SverRepository::new(&calc_target_path(&repo, "service1")).unwrap();
+
+    // exercise
+    let sources = sver_repo
+        .sources_iter()
+        .unwrap()
+        .collect::<Result<Vec<_>, _>>()
+        .unwrap();
+
+    // verify
+    assert_eq!(
+        sources,
+        vec![
+            "service1/hello.txt",
+            "service1/sub/world.txt",
+            "service1/sver.toml",
+            "service2/hello.txt",
+        ]
+    );
+    assert_eq!(sources, sver_repo.list_sources().unwrap());
+}
+
+// repo layout
+// .
+// + service1/sub/hello.txt
+// + service1\sub\hello.txt (a file in the root whose name has backslashes)
+#[test]
+fn backslash_path_repository() {
+    initialize();
+
+    // setup
+    let repo = setup_test_repository();
+    add_blob(&repo, "service1/sub/hello.txt", "hello world!".as_bytes());
+    add_blob_with_raw_path(&repo, b"service1\\sub\\hello.txt", "backslash".as_bytes());
+    commit(&repo, "setup");
+
+    let root_sver_repo = SverRepository::new(&calc_target_path(&repo, "")).unwrap();
+    let service1_sver_repo = SverRepository::new(&calc_target_path(&repo, "service1")).unwrap();
+
+    // exercise
+    let root_sources = root_sver_repo.list_sources().unwrap();
+    let service1_sources = service1_sver_repo.list_sources().unwrap();
+
+    // verify
+    // a backslash is not a separator of the index
+    assert_eq!(
+        root_sources,
+        vec!["service1/sub/hello.txt", "service1\\sub\\hello.txt"]
+    );
+    assert_eq!(service1_sources, vec!["service1/sub/hello.txt"]);
+}
+
+// repo layout
+// .
+// + app1/sver.toml → dependencies = [ "lib1:prof1" ]
+// + app2/sver.toml → dependencies = [ "lib1:prof1!" ]
+// + app3/sver.toml → dependencies = [ "lib1:prof1!", "lib1:prof1" ]
+// + lib1/hello.txt
+// + lib1/sver.toml → [prof1] dependencies = [ "lib2" ]
+// + lib2/sver.toml → dependencies = [ "lib3" ]
+// + lib3/hello.txt
+#[test]
+fn non_transitive_dependency_repository() {
+    initialize();
+
+    // setup
+    let repo = setup_test_repository();
+    add_blob(
+        &repo,
+        "app1/sver.toml",
+        "
+        [default]
+        dependencies = [\"lib1:prof1\"]
+        "
+        .as_bytes(),
+    );
+    add_blob(
+        &repo,
+        "app2/sver.toml",
+        "
+        [default]
+        dependencies = [\"lib1:prof1!\"]
+        "
+        .as_bytes(),
+    );
+    add_blob(
+        &repo,
+        "app3/sver.toml",
+        "
+        [default]
+        dependencies = [\"lib1:prof1!\", \"lib1:prof1\"]
+        "
+        .as_bytes(),
+    );
+    add_blob(&repo, "lib1/hello.txt", "hello world!".as_bytes());
+    add_blob(
+        &repo,
+        "lib1/sver.toml",
+        "
+        [prof1]
+        dependencies = [\"lib2\"]
+        "
+        .as_bytes(),
+    );
+    add_blob(
+        &repo,
+        "lib2/sver.toml",
+        "
+        [default]
+        dependencies = [\"lib3\"]
+        "
+        .as_bytes(),
+    );
+    add_blob(&repo, "lib3/hello.txt", "hello world!".as_bytes());
+    commit(&repo, "setup");
+
+    let list_sources = |path: &str| {
+        SverRepository::new(&calc_target_path(&repo, path))
+            .unwrap()
+            .list_sources()
+            .unwrap()
+    };
+
+    // exercise
+    let transitive = list_sources("app1");
+    let non_transitive = list_sources("app2");
+    let both = list_sources("app3");
+
+    // verify
+    assert_eq!(
+        transitive,
+        vec![
+            "app1/sver.toml",
+            "lib1/hello.txt",
+            "lib1/sver.toml",
+            "lib2/sver.toml",
+            "lib3/hello.txt",
+        ]
+    );
+    assert_eq!(
+        non_transitive,
+        vec!["app2/sver.toml", "lib1/hello.txt", "lib1/sver.toml"]
+    );
+    // a transitive dependency wins over a non-transitive one of the same target
+    assert_eq!(
+        both,
+        vec![
+            "app3/sver.toml",
+            "lib1/hello.txt",
+            "lib1/sver.toml",
+            "lib2/sver.toml",
+            "lib3/hello.txt",
+        ]
+    );
+    let validation = SverRepository::new(&calc_target_path(&repo, "app2"))
+        .unwrap()
+        .validate_sver_config()
+        .unwrap();
+    assert!(!validation.has_invalid);
+}
+
+// repo layout
+// .
+// + lib1/hello.txt
+// + service1/hello.txt
+// + service1/sub/world.txt
+// + service1/sver.toml → dependencies = [ "lib1" ]
+// + moved/service1/hello.txt
+// + moved/service1/sub/world.txt
+// + moved/service1/sver.toml → dependencies = [ "lib1" ]
+#[test]
+fn path_independent_repository() {
+    initialize();
+
+    // setup
+    let repo = setup_test_repository();
+    add_blob(&repo, "lib1/hello.txt", "hello lib!".as_bytes());
+    for dir in ["service1", "moved/service1"] {
+        add_blob(
+            &repo,
+            &format!("{dir}/hello.txt"),
+            "hello world!".as_bytes(),
+        );
+        add_blob(&repo, &format!("{dir}/sub/world.txt"), "world!".as_bytes());
+        add_blob(
+            &repo,
+            &format!("{dir}/sver.toml"),
+            "
+            [default]
+            dependencies = [\"lib1\"]
+            "
+            .as_bytes(),
+        );
+    }
+    commit(&repo, "setup");
+
+    let calc_version = |path: &str, path_independent: bool| {
+        SverRepository::new(&calc_target_path(&repo, path))
+            .unwrap()
+            .with_options(CalcOptions {
+                path_independent,
+                ..Default::default()
+            })
+            .calc_version()
+            .unwrap()
+            .version
+    };
+
+    // exercise
+    let original = calc_version("service1", false);
+    let moved = calc_version("moved/service1", false);
+    let original_independent = calc_version("service1", true);
+    let moved_independent = calc_version("moved/service1", true);
+
+    // verify
+    assert_ne!(original, moved);
+    assert_eq!(original_independent, moved_independent);
+    assert_ne!(original, original_independent);
+}
+
+// repo layout
+// .
+// + lib1/hello.txt
+// + lib1/sver.toml → dependencies = [ "lib2" ]
+// + lib2/hello.txt
+// + lib3/hello.txt
+// + service1/sver.toml → dependencies = [ "lib1" ]
+// + service1/link → ../lib3
+// + service2/sver.toml → dependencies = [ "lib1" ]
+// + service2/link → ../lib3
+#[test]
+fn shared_config_and_link_repository() {
+    initialize();
+
+    // setup
+    let repo = setup_test_repository();
+    add_blob(&repo, "lib1/hello.txt", "hello lib1!".as_bytes());
+    add_blob(
+        &repo,
+        "lib1/sver.toml",
+        "
+        [default]
+        dependencies = [\"lib2\"]
+        "
+        .as_bytes(),
+    );
+    add_blob(&repo, "lib2/hello.txt", "hello lib2!".as_bytes());
+    add_blob(&repo, "lib3/hello.txt", "hello lib3!".as_bytes());
+    for service in ["service1", "service2"] {
+        add_blob(
+            &repo,
+            &format!("{service}/sver.toml"),
+            "
+            [default]
+            dependencies = [\"lib1\"]
+            "
+            .as_bytes(),
+        );
+        add_symlink(&repo, &format!("{service}/link"), "../lib3");
+    }
+    commit(&repo, "setup");
+
+    let list_sources = |path: &str| {
+        SverRepository::new(&calc_target_path(&repo, path))
+            .unwrap()
+            .list_sources()
+            .unwrap()
+    };
+
+    // exercise
+    // the config files and the links of the second target are read from the cache
+    let service1 = list_sources("service1");
+    let service2 = list_sources("service2");
+    let service1_again = list_sources("service1");
+
+    // verify
+    assert_eq!(
+        service1,
+        vec![
+            "lib1/hello.txt",
+            "lib1/sver.toml",
+            "lib2/hello.txt",
+            "lib3/hello.txt",
+            "service1/link",
+            "service1/sver.toml",
+        ]
+    );
+    assert_eq!(
+        service2,
+        vec![
+            "lib1/hello.txt",
+            "lib1/sver.toml",
+            "lib2/hello.txt",
+            "lib3/hello.txt",
+            "service2/link",
+            "service2/sver.toml",
+        ]
+    );
+    assert_eq!(service1, service1_again);
+}
+
+// repo layout
+// .
+// + lib1/hello.txt
+// + lib2/sver.toml → [prof1]
+// + services/shared/hello.txt
+// + services/app/sver.toml → dependencies = [ "./lib1/", "lib2:prof1!", "../shared" ], excludes = [ "./target/", "!target/keep.txt" ]
+#[test]
+fn resolved_config_repository() {
+    initialize();
+
+    // setup
+    let repo = setup_test_repository();
+    add_blob(&repo, "lib1/hello.txt", "hello world!".as_bytes());
+    add_blob(&repo, "lib2/sver.toml", "[prof1]".as_bytes());
+    add_blob(
+        &repo,
+        "services/shared/hello.txt",
+        "hello world!".as_bytes(),
+    );
+    add_blob(
+        &repo,
+        "services/app/sver.toml",
+        "
+        [default]
+        dependencies = [\"./lib1/\", \"lib2:prof1!\", \"../shared\"]
+        excludes = [\"./target/\", \"!target/keep.txt\"]
+        env = [\"TARGET_ENV\"]
+        "
+        .as_bytes(),
+    );
+    commit(&repo, "setup");
+
+    // exercise
+    let resolved = SverRepository::new(&calc_target_path(&repo, "services/app"))
+        .unwrap()
+        .resolved_config()
+        .unwrap();
+    let without_config = SverRepository::new(&calc_target_path(&repo, "lib1"))
+        .unwrap()
+        .resolved_config()
+        .unwrap();
+
+    // verify
+    assert_eq!(
+        resolved.to_string(),
+        "[default]
+excludes = [\"target\", \"!target/keep.txt\"]
+dependencies = [\"lib1:default\", \"lib2:prof1!\", \"services/shared:default\"]
+env = [\"TARGET_ENV\"]
+"
+    );
+    assert_eq!(
+        without_config.to_string(),
+        "[default]
+excludes = []
+dependencies = []
+"
+    );
+}
+
+// repo layout
+// .
+// + service1/hello.txt
+// + service1/sver.toml → [default] description = "..."
+#[test]
+fn ignore_config_metadata_repository() {
+    initialize();
+
+    // setup
+    let repo = setup_test_repository();
+    add_blob(&repo, "service1/hello.txt", "hello world!".as_bytes());
+    let config = |description: &str| {
+        format!(
+            "
+            [default]
+            description = \"{description}\"
+            excludes = [\"target\"]
+            "
+        )
+    };
+    let calc_version = |ignore_config_metadata: bool| {
+        SverRepository::new(&calc_target_path(&repo, "service1"))
+            .unwrap()
+            .with_options(CalcOptions {
+                ignore_config_metadata,
+                ..Default::default()
+            })
+            .calc_version()
+            .unwrap()
+            .version
+    };
+    add_blob(&repo, "service1/sver.toml", config("hello").as_bytes());
+    commit(&repo, "setup");
+    let before = calc_version(false);
+    let before_ignored = calc_version(true);
+
+    // exercise
+    add_blob(
+        &repo,
+        "service1/sver.toml",
+        config("hello world").as_bytes(),
+    );
+    commit(&repo, "edit description");
+
+    // verify
+    assert_ne!(before, calc_version(false));
+    assert_eq!(before_ignored, calc_version(true));
+    let descriptions = SverRepository::new(&calc_target_path(&repo, "service1"))
+        .unwrap()
+        .target_descriptions()
+        .unwrap();
+    assert_eq!(
+        descriptions.into_iter().collect::<Vec<_>>(),
+        vec![(
+            CalculationTarget::new("service1".to_string(), "default".to_string()),
+            "hello world".to_string()
+        )]
+    );
+}
+
+// repo layout
+// .
+// + .sverignore → "*.log", "!keep.log", "unused/"
+// + root.log
+// + service1/hello.txt
+// + service1/error.log
+// + service1/keep.log
+// + service1/sver.toml → dependencies = [ "service2" ]
+// + service2/hello.txt
+// + service2/sub/debug.log
+#[test]
+fn sverignore_repository() {
+    initialize();
+
+    // setup
+    let repo = setup_test_repository();
+    add_blob(
+        &repo,
+        ".sverignore",
+        "# logs are not sources\n*.log\n!keep.log\n\nunused/\n".as_bytes(),
+    );
+    add_blob(&repo, "root.log", "log".as_bytes());
+    add_blob(&repo, "service1/hello.txt", "hello world!".as_bytes());
+    add_blob(&repo, "service1/error.log", "log".as_bytes());
+    add_blob(&repo, "service1/keep.log", "log".as_bytes());
+    add_blob(
+        &repo,
+        "service1/sver.toml",
+        "
+        [default]
+        dependencies = [\"service2\"]
+        "
+        .as_bytes(),
+    );
+    add_blob(&repo, "service2/hello.txt", "hello world!".as_bytes());
+    add_blob(&repo, "service2/sub/debug.log", "log".as_bytes());
+    commit(&repo, "setup");
+
+    let sver_repo = |path: &str| SverRepository::new(&calc_target_path(&repo, path)).unwrap();
+
+    // exercise
+    let service1 = sver_repo("service1").list_sources().unwrap();
+    let service2 = sver_repo("service2").list_sources().unwrap();
+    let root = sver_repo("").list_sources().unwrap();
+    let validation = sver_repo("").validate_sver_config().unwrap();
+
+    // verify
+    assert_eq!(
+        service1,
+        vec![
+            "service1/hello.txt",
+            "service1/keep.log",
+            "service1/sver.toml",
+            "service2/hello.txt",
+        ]
+    );
+    assert_eq!(service2, vec!["service2/hello.txt"]);
+    assert_eq!(
+        root,
+        vec![
+            ".sverignore",
+            "service1/hello.txt",
+            "service1/keep.log",
+            "service1/sver.toml",
+            "service2/hello.txt",
+        ]
+    );
+    assert!(!validation.has_invalid);
+    assert!(validation.has_warning);
+    if let Some(ValidationResult::UnmatchedIgnore {
+        ignore_file,
+        unmatched_patterns,
+    }) = validation.results.last()
+    {
+        assert_eq!(ignore_file, ".sverignore");
+        assert_eq!(unmatched_patterns, &vec!["unused/".to_string()]);
+    } else {
+        unreachable!("this line will not be execute");
+    }
+}
@@ -30,6 +30,29 @@ pub fn setup_test_repository() -> Repository {
 }
 
 fn add_file(repo: &Repository, path: &str, content: &[u8], mode: FileMode) {
+    add_file_with_raw_path(repo, path.as_bytes(), content, mode)
+}
+
+fn add_file_with_raw_path(repo: &Repository, path: &[u8], content: &[u8], mode: FileMode) {
+    let mut index = repo.index().unwrap();
+
+    let blob = repo.blob(content).unwrap();
+    let mut entry = entry();
+    entry.mode = mode.into();
+    entry.id = blob;
+    entry.path = path.to_vec();
+    index.add(&entry).unwrap();
+    index.write().unwrap();
+}
+
+// add a conflicted entry of the stage (1: ancestor, 2: ours, 3: theirs)
+pub fn add_conflicted_file(
+    repo: &Repository,
+    path: &str,
+    content: &[u8],
+    mode: FileMode,
+    stage: u16,
+) {
     let mut index = repo.index().unwrap();
 
     let blob = repo.blob(content).unwrap();
@@ -37,6 +60,7 @@ fn add_file(repo: &Repository, path: &str, content: &[u8], mode: FileMode) {
     entry.mode = mode.into();
     entry.id = blob;
     entry.path = path.as_bytes().to_vec();
+    entry.flags = stage << 12;
     index.add(&entry).unwrap();
     index.write().unwrap();
 }
@@ -45,6 +69,24 @@ pub fn add_blob(repo: &Repository, path: &str, content: &[u8]) {
     add_file(repo, path, content, FileMode::Blob)
 }
 
+// add many files with a single write of the index
+pub fn add_blobs(repo: &Repository, files: &[(String, Vec<u8>)]) {
+    let mut index = repo.index().unwrap();
+    for (path, content) in files {
+        let mut entry = entry();
+        entry.mode = FileMode::Blob.into();
+        entry.id = repo.blob(content).unwrap();
+        entry.path = path.as_bytes().to_vec();
+        index.add(&entry).unwrap();
+    }
+    index.write().unwrap();
+}
+
+// path may not be valid UTF-8
+pub fn add_blob_with_raw_path(repo: &Repository, path: &[u8], content: &[u8]) {
+    add_file_with_raw_path(repo, path, content, FileMode::Blob)
+}
+
 pub fn add_blob_executable(repo: &Repository, path: &str, content: &[u8]) {
     add_file(repo, path, content, FileMode::BlobExecutable)
 }